@@ -1,9 +1,22 @@
 use anyhow::{bail, Context, Result};
 use provenact_spec_rs::{
-    evaluate_capability, parse_json, verify_receipt_hash, verify_snapshot_hash,
-    CapabilityEvalVector, ExecutionReceipt, RegistrySnapshot,
+    compute_policy_hash, diff_policies, diff_snapshots, downgrade_manifest, downgrade_snapshot,
+    evaluate_capability, evaluate_signer_policy, explain_capability, lint_policy,
+    minimal_ceiling_entry, negotiate, net_limits, parse_json, parse_spdx, upgrade_manifest,
+    upgrade_snapshot, validate_policy, validate_signer_rules, verify_receipt_hash,
+    verify_sbom_artifact, verify_signatures, verify_signed_snapshot, verify_snapshot_hash,
+    verify_snapshot_v1_hash, AnyManifest, CapabilityEvalVector, ExecutionReceipt,
+    ExpectedLintFinding, ExplainVector, LintConfig, LocalDirFetcher, Manifest,
+    ManifestDowngradeVector, ManifestFormatError, ManifestUpgradeVector, NegotiationVector,
+    OciReference, OciReferenceVector, Policy, PolicyDiffVector, PolicyLintVector, PolicyStack,
+    PolicyStackVector, RegistrySnapshot, RegistrySnapshotV1, SignatureError, SignatureVerification,
+    SignedSnapshotVector, SignerPolicyVector, SignerTrustVector, SnapshotBuildError,
+    SnapshotBuilder, SnapshotDiffVector, SnapshotResolveVector, SnapshotResolver, SnapshotTrust,
+    SnapshotUpgradeVector, TrustAnchors, VerifiedSignature, VersionQuery, VersionQueryVector,
+};
+use provenact_spec_validate::{
+    detect_and_validate, load_policy, DetectVector, PolicyLoadVector, SchemaStore,
 };
-use provenact_spec_validate::SchemaStore;
 use std::path::Path;
 
 #[derive(Debug, Clone)]
@@ -11,6 +24,16 @@ pub struct Report {
     pub checks: usize,
 }
 
+/// Validates a policy embedded in a vector against the schema for its
+/// `version`.
+fn validate_policy_doc(store: &SchemaStore, policy: &Policy) -> Result<()> {
+    let schema = match policy.version {
+        2 => "spec/policy/policy.v2.schema.json",
+        _ => "spec/policy/policy.schema.json",
+    };
+    store.validate_value(schema, &serde_json::to_value(policy)?)
+}
+
 fn files(root: &Path, rel_dir: &str) -> Result<Vec<String>> {
     let dir = root.join(rel_dir);
     if !dir.exists() {
@@ -84,7 +107,9 @@ fn check_capability_vectors(store: &SchemaStore) -> Result<usize> {
             } else {
                 "deny"
             };
-            if got != case.expect {
+            if got != case.expect
+                || explain_capability(&vector.policy, &case.capability).allowed != (got == "allow")
+            {
                 bail!(
                     "capability mismatch in {} for {}:{} expected={} actual={}",
                     file,
@@ -94,6 +119,736 @@ fn check_capability_vectors(store: &SchemaStore) -> Result<usize> {
                     got
                 );
             }
+            if let Some(expected) = case.expect_limits {
+                let limits = net_limits(&vector.policy, &case.capability);
+                if limits != Some(expected) {
+                    bail!(
+                        "net limits mismatch in {} for {}: expected={:?} actual={:?}",
+                        file,
+                        case.capability.value,
+                        expected,
+                        limits
+                    );
+                }
+            }
+            checks += 1;
+        }
+    }
+    Ok(checks)
+}
+
+fn check_signer_trust_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/signer-trust")? {
+        if file.ends_with("schema.json") {
+            continue;
+        }
+        let value = store.parse_doc_file(&file)?;
+        store.validate_value("test-vectors/signer-trust/schema.json", &value)?;
+        let vector: SignerTrustVector = serde_json::from_value(value)?;
+        let anchors =
+            TrustAnchors::from_bytes_unpinned(&serde_json::to_vec(&vector.trust_anchors)?)
+                .with_context(|| format!("loading trust anchors in {file}"))?;
+        let (got, signers, rejected) =
+            match verify_signatures(&vector.signatures, &vector.manifest, &anchors, vector.at) {
+                Ok(outcome) => (
+                    "accept",
+                    outcome.signers().map(str::to_string).collect(),
+                    outcome.rejected,
+                ),
+                Err(SignatureError::NoTrustedSignature(rejected)) => ("reject", vec![], rejected),
+                Err(err) => bail!("signer trust vector {file} failed before key checks: {err}"),
+            };
+        if got != vector.expect {
+            bail!(
+                "signer trust mismatch in {file}: expected={} actual={got}",
+                vector.expect
+            );
+        }
+        if got == "accept" && signers != vector.expect_signers {
+            bail!(
+                "signer trust mismatch in {file}: expected signers={:?} actual={signers:?}",
+                vector.expect_signers
+            );
+        }
+        let rejections: Vec<(String, String)> = rejected
+            .iter()
+            .map(|r| (r.signer.clone(), r.reason.code().to_string()))
+            .collect();
+        let expected: Vec<(String, String)> = vector
+            .expect_rejections
+            .iter()
+            .map(|r| (r.signer.clone(), r.reason.clone()))
+            .collect();
+        if rejections != expected {
+            bail!(
+                "signer trust mismatch in {file}: expected rejections={expected:?} actual={rejections:?}"
+            );
+        }
+        checks += 1;
+    }
+    Ok(checks)
+}
+
+fn check_signer_policy_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/signer-policy")? {
+        if file.ends_with("schema.json") {
+            continue;
+        }
+        let value = store.parse_doc_file(&file)?;
+        store.validate_value("test-vectors/signer-policy/schema.json", &value)?;
+        let vector: SignerPolicyVector = serde_json::from_value(value)?;
+        store.validate_value(
+            "spec/policy/policy.schema.json",
+            &serde_json::to_value(&vector.policy)?,
+        )?;
+        validate_signer_rules(&vector.policy)
+            .with_context(|| format!("invalid signer rules in {file}"))?;
+        for case in vector.cases {
+            let mut manifest = vector.manifest.clone();
+            manifest.capabilities = case.capabilities;
+            let verification = SignatureVerification {
+                verified: case
+                    .verified_signers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, signer)| VerifiedSignature {
+                        signer: signer.clone(),
+                        key_id: format!("{signer}#{i}"),
+                    })
+                    .collect(),
+                rejected: vec![],
+            };
+            let (got, no_trusted_signer, unmet) =
+                match evaluate_signer_policy(&vector.policy, &manifest, &verification) {
+                    Ok(()) => ("allow", false, vec![]),
+                    Err(denial) => (
+                        "deny",
+                        denial.no_trusted_signer,
+                        denial
+                            .unmet_rules
+                            .into_iter()
+                            .map(|r| (r.rule, r.missing))
+                            .collect(),
+                    ),
+                };
+            let expected: Vec<(usize, Vec<String>)> = case
+                .expect_unmet
+                .into_iter()
+                .map(|r| (r.rule, r.missing))
+                .collect();
+            if got != case.expect
+                || no_trusted_signer != case.expect_no_trusted_signer
+                || unmet != expected
+            {
+                bail!(
+                    "signer policy mismatch in {file} case {}: expected={} no_trusted_signer={} unmet={expected:?} actual={got} no_trusted_signer={no_trusted_signer} unmet={unmet:?}",
+                    case.name,
+                    case.expect,
+                    case.expect_no_trusted_signer
+                );
+            }
+            checks += 1;
+        }
+    }
+    Ok(checks)
+}
+
+fn check_snapshot_resolve_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/registry/resolve")? {
+        if file.ends_with("schema.json") {
+            continue;
+        }
+        let value = store.parse_doc_file(&file)?;
+        store.validate_value("test-vectors/registry/resolve/schema.json", &value)?;
+        store.validate_value("spec/registry/snapshot.schema.json", &value["snapshot"])?;
+        let vector: SnapshotResolveVector = serde_json::from_value(value)?;
+        let dir = store
+            .root()
+            .join("test-vectors/registry/resolve")
+            .join(&vector.artifacts);
+        let resolver = SnapshotResolver::new(vector.snapshot, LocalDirFetcher::new(dir))
+            .with_context(|| format!("loading snapshot in {file}"))?;
+        for case in vector.cases {
+            let got = match resolver.resolve(&case.entry) {
+                Ok(_) => "ok",
+                Err(err) => err.code(),
+            };
+            if got != case.expect {
+                bail!(
+                    "snapshot resolve mismatch in {file} entry {}: expected={} actual={got}",
+                    case.entry,
+                    case.expect
+                );
+            }
+            checks += 1;
+        }
+    }
+    Ok(checks)
+}
+
+fn check_signed_snapshot_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/registry/signed")? {
+        if file.ends_with("schema.json") {
+            continue;
+        }
+        let value = store.parse_doc_file(&file)?;
+        store.validate_value("test-vectors/registry/signed/schema.json", &value)?;
+        store.validate_value(
+            "spec/registry/signed-snapshot.schema.json",
+            &value["signed_snapshot"],
+        )?;
+        let snapshot_schema = if value["signed_snapshot"]["snapshot"]
+            .get("version")
+            .is_some()
+        {
+            "spec/registry/snapshot.v1.schema.json"
+        } else {
+            "spec/registry/snapshot.schema.json"
+        };
+        store.validate_value(snapshot_schema, &value["signed_snapshot"]["snapshot"])?;
+        let vector: SignedSnapshotVector = serde_json::from_value(value)?;
+        let anchors =
+            TrustAnchors::from_bytes_unpinned(&serde_json::to_vec(&vector.trust_anchors)?)
+                .with_context(|| format!("loading trust anchors in {file}"))?;
+        let trust = SnapshotTrust {
+            anchors: &anchors,
+            registry_signer: &vector.registry_signer,
+            max_age: vector.max_age,
+        };
+        let got = match verify_signed_snapshot(
+            &vector.signed_snapshot,
+            &trust,
+            vector.previous.as_ref(),
+            vector.now,
+        ) {
+            Ok(_) => "accept",
+            Err(err) => err.code(),
+        };
+        if got != vector.expect {
+            bail!(
+                "signed snapshot mismatch in {file}: expected={} actual={got}",
+                vector.expect
+            );
+        }
+        checks += 1;
+    }
+    Ok(checks)
+}
+
+/// Every `.tar.zst` under `registry/build/bad` is an install artifact a
+/// snapshot builder must refuse.
+fn check_snapshot_build_vectors(store: &SchemaStore) -> Result<usize> {
+    let dir = store.root().join("test-vectors/registry/build/bad");
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.to_string_lossy().ends_with(".tar.zst") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    for path in &paths {
+        match SnapshotBuilder::new().add_install_artifact(path) {
+            Err(SnapshotBuildError::InvalidArchive { .. }) => {}
+            other => bail!(
+                "snapshot build mismatch in {}: expected=invalid_archive actual={other:?}",
+                path.display()
+            ),
+        }
+    }
+    Ok(paths.len())
+}
+
+fn check_snapshot_diff_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/registry/diff")? {
+        let value = store.parse_doc_file(&file)?;
+        for side in ["old", "new"] {
+            store.validate_value("spec/registry/snapshot.schema.json", &value[side])?;
+        }
+        let vector: SnapshotDiffVector = serde_json::from_value(value)?;
+        let diff = diff_snapshots(&vector.old, &vector.new);
+        if diff != vector.expect {
+            bail!(
+                "snapshot diff mismatch in {file}: expected={} actual={}",
+                serde_json::to_string(&vector.expect)?,
+                serde_json::to_string(&diff)?
+            );
+        }
+        if diff.to_string() != vector.expect_text {
+            bail!("snapshot diff text mismatch in {file}:\n{diff}");
+        }
+        checks += 1;
+    }
+    Ok(checks)
+}
+
+fn check_snapshot_v1_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for good in files(store.root(), "test-vectors/registry/snapshot-v1/good")? {
+        let raw = std::fs::read_to_string(store.root().join(&good))?;
+        let snapshot: RegistrySnapshotV1 = parse_json(&raw)?;
+        verify_snapshot_v1_hash(&snapshot)?;
+        checks += 1;
+    }
+    let bad = "test-vectors/registry/snapshot-v1/bad/hash-mismatch.json";
+    let snapshot: RegistrySnapshotV1 =
+        parse_json(&std::fs::read_to_string(store.root().join(bad))?)?;
+    if verify_snapshot_v1_hash(&snapshot).is_ok() {
+        bail!("expected snapshot hash verification failure: {bad}");
+    }
+    checks += 1;
+
+    for file in files(store.root(), "test-vectors/registry/versions")? {
+        let value = store.parse_doc_file(&file)?;
+        store.validate_value("spec/registry/snapshot.v1.schema.json", &value["snapshot"])?;
+        let vector: VersionQueryVector = serde_json::from_value(value)?;
+        verify_snapshot_v1_hash(&vector.snapshot)
+            .with_context(|| format!("loading snapshot in {file}"))?;
+        for case in vector.cases {
+            let query = VersionQuery::parse(&case.query)?;
+            let got = if !vector.snapshot.skills.contains_key(&case.skill) {
+                Err("unknown_skill")
+            } else {
+                vector
+                    .snapshot
+                    .select(&case.skill, &query)
+                    .map(|s| (s.key.to_string(), s.entry.yanked.is_some()))
+                    .ok_or("no_matching_version")
+            };
+            let expected = match (&case.expect_version, &case.expect_error) {
+                (Some(version), None) => Ok((version.clone(), case.expect_yanked)),
+                (None, Some(code)) => Err(code.as_str()),
+                _ => bail!("{file}: case needs exactly one of expect_version/expect_error"),
+            };
+            if got != expected {
+                bail!(
+                    "version query mismatch in {file} {} {}: expected={expected:?} actual={got:?}",
+                    case.skill,
+                    case.query
+                );
+            }
+            checks += 1;
+        }
+    }
+
+    for file in files(store.root(), "test-vectors/registry/upgrade")? {
+        let value = store.parse_doc_file(&file)?;
+        store.validate_value("spec/registry/snapshot.schema.json", &value["v0"])?;
+        store.validate_value("spec/registry/snapshot.v1.schema.json", &value["v1"])?;
+        let vector: SnapshotUpgradeVector = serde_json::from_value(value)?;
+        let upgraded = upgrade_snapshot(&vector.v0)?;
+        if upgraded != vector.v1 {
+            bail!("snapshot upgrade mismatch in {file}");
+        }
+        let downgraded = downgrade_snapshot(&upgraded)?;
+        if serde_json::to_value(&downgraded)? != serde_json::to_value(&vector.v0)? {
+            bail!("snapshot downgrade mismatch in {file}");
+        }
+        checks += 1;
+    }
+    Ok(checks)
+}
+
+fn check_oci_reference_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/oci")? {
+        let raw = std::fs::read_to_string(store.root().join(&file))?;
+        let vector: OciReferenceVector = parse_json(&raw)?;
+        for case in vector.cases {
+            let parsed = if case.pinned {
+                OciReference::parse_pinned(&case.reference)
+            } else {
+                OciReference::parse(&case.reference)
+            };
+            let ok = match (&parsed, &case.expect, &case.expect_error) {
+                (Ok(parsed), Some(expect), None) => parsed == expect,
+                (Err(err), None, Some(code)) => err.code() == code,
+                _ => false,
+            };
+            if !ok {
+                bail!(
+                    "oci reference mismatch in {file} {}: actual={parsed:?}",
+                    case.reference
+                );
+            }
+            checks += 1;
+        }
+    }
+    Ok(checks)
+}
+
+fn check_manifest_compat_vectors(store: &SchemaStore) -> Result<usize> {
+    const V0_SCHEMA: &str = "spec/skill-format/manifest.schema.json";
+    const V1_SCHEMA: &str = "spec/skill-format/manifest.v1.experimental.schema.json";
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/skill-format/manifest-upgrade")? {
+        let value = store.parse_doc_file(&file)?;
+        store.validate_value(V0_SCHEMA, &value["v0"])?;
+        let vector: ManifestUpgradeVector = serde_json::from_value(value.clone())?;
+        if let Some(expect) = vector.expect_invalid_capability {
+            match upgrade_manifest(&vector.v0) {
+                Err(ManifestFormatError::InvalidCapability { index, .. }) if index == expect => {}
+                got => bail!("manifest upgrade mismatch in {file}: actual={got:?}"),
+            }
+            checks += 1;
+            continue;
+        }
+        store.validate_value(V1_SCHEMA, &value["v1"])?;
+        let upgrade = upgrade_manifest(&vector.v0)?;
+        if Some(&upgrade.manifest) != vector.v1.as_ref()
+            || upgrade.synthesized != vector.synthesized
+        {
+            bail!(
+                "manifest upgrade mismatch in {file}: synthesized={:?}",
+                upgrade.synthesized
+            );
+        }
+        if downgrade_manifest(&upgrade.manifest)? != vector.v0 {
+            bail!("manifest downgrade mismatch in {file}");
+        }
+        checks += 1;
+    }
+    for file in files(store.root(), "test-vectors/skill-format/manifest-downgrade")? {
+        let value = store.parse_doc_file(&file)?;
+        store.validate_value(V1_SCHEMA, &value["v1"])?;
+        let vector: ManifestDowngradeVector = serde_json::from_value(value)?;
+        match (downgrade_manifest(&vector.v1), &vector.expect_v0) {
+            (Ok(v0), Some(expect)) if v0 == *expect => {
+                store.validate_value(V0_SCHEMA, &serde_json::to_value(&v0)?)?;
+            }
+            (Err(ManifestFormatError::LossyDowngrade(lost)), None)
+                if lost == vector.expect_lost => {}
+            (got, _) => bail!("manifest downgrade mismatch in {file}: actual={got:?}"),
+        }
+        checks += 1;
+    }
+    Ok(checks)
+}
+
+fn check_negotiation_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/compatibility")? {
+        let raw = std::fs::read_to_string(store.root().join(&file))?;
+        let vector: NegotiationVector = parse_json(&raw)?;
+        for case in vector.cases {
+            let manifest = AnyManifest::parse(&case.manifest.to_string())?;
+            let schema = match manifest {
+                AnyManifest::V0(_) => "spec/skill-format/manifest.schema.json",
+                AnyManifest::V1(_) => "spec/skill-format/manifest.v1.experimental.schema.json",
+            };
+            store.validate_value(schema, &case.manifest)?;
+            let got = negotiate(&vector.runtime, &manifest);
+            let ok = match (&got, &case.expect, &case.expect_error) {
+                (Ok(got), Some(expect), None) => got == expect,
+                (Err(err), None, Some(code)) => err.code() == code,
+                _ => false,
+            };
+            if !ok {
+                bail!(
+                    "negotiation mismatch in {file} case {:?}: actual={got:?}",
+                    case.name
+                );
+            }
+            checks += 1;
+        }
+    }
+    Ok(checks)
+}
+
+fn check_detect_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for (dir, kind, version) in [
+        ("test-vectors/skill-format/manifest/good", "manifest", "v0"),
+        (
+            "test-vectors/skill-format/manifest-v1/good",
+            "manifest",
+            "1.0.0-draft",
+        ),
+        (
+            "test-vectors/v0/skill-manifest/good",
+            "legacy-manifest",
+            "v0",
+        ),
+        ("test-vectors/v0/pipeline/good", "pipeline", "v0"),
+        ("test-vectors/policy/valid", "policy", "1"),
+        ("test-vectors/policy/v2/valid", "policy", "2"),
+        ("test-vectors/receipt/good", "receipt", "v0"),
+        ("test-vectors/receipt-v1/good", "receipt", "1.0.0-draft"),
+        ("test-vectors/registry/snapshot/good", "snapshot", "v0"),
+        ("test-vectors/registry/snapshot-v1/good", "snapshot", "1"),
+        (
+            "test-vectors/skill-format/provenance/good",
+            "provenance",
+            "v0",
+        ),
+        (
+            "test-vectors/skill-format/signatures/good",
+            "signatures",
+            "v0",
+        ),
+        ("test-vectors/trust-anchors/good", "trust-anchors", "1"),
+    ] {
+        for file in files(store.root(), dir)? {
+            let doc = detect_and_validate(&store.parse_doc_file(&file)?)
+                .with_context(|| format!("detecting {file}"))?;
+            if (doc.kind().as_str(), doc.version()) != (kind, version) {
+                bail!(
+                    "detected {file} as {} {}, expected {kind} {version}",
+                    doc.kind(),
+                    doc.version()
+                );
+            }
+            checks += 1;
+        }
+    }
+    for file in files(store.root(), "test-vectors/detect")? {
+        let raw = std::fs::read_to_string(store.root().join(&file))?;
+        let vector: DetectVector = parse_json(&raw)?;
+        for case in vector.cases {
+            let got = detect_and_validate(&case.document);
+            let ok = match (&got, &case.expect_error) {
+                (Ok(doc), None) => {
+                    Some(doc.kind().as_str()) == case.expect_kind.as_deref()
+                        && Some(doc.version()) == case.expect_version.as_deref()
+                }
+                (Err(err), Some(code)) => err.code() == code,
+                _ => false,
+            };
+            if !ok {
+                bail!(
+                    "detection mismatch in {file} case {:?}: actual={got:?}",
+                    case.name
+                );
+            }
+            checks += 1;
+        }
+    }
+    Ok(checks)
+}
+
+fn check_policy_load_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    let mut valid = files(store.root(), "test-vectors/policy/valid")?;
+    valid.extend(files(store.root(), "test-vectors/policy/v2/valid")?);
+    valid.push("spec/policy/policy.example.yaml".to_string());
+    for file in valid {
+        load_policy(&store.root().join(&file)).with_context(|| format!("loading {file}"))?;
+        checks += 1;
+    }
+    let mut invalid = files(store.root(), "test-vectors/policy/invalid")?;
+    invalid.extend(files(store.root(), "test-vectors/policy/v2/invalid")?);
+    for file in invalid {
+        if load_policy(&store.root().join(&file)).is_ok() {
+            bail!("expected policy load failure: {file}");
+        }
+        checks += 1;
+    }
+    let mut hashes = Vec::new();
+    for file in files(store.root(), "test-vectors/policy/encodings")? {
+        let policy =
+            load_policy(&store.root().join(&file)).with_context(|| format!("loading {file}"))?;
+        hashes.push((file, compute_policy_hash(&policy)?));
+    }
+    if let Some((first, hash)) = hashes.first() {
+        if let Some((file, other)) = hashes.iter().find(|(_, other)| other != hash) {
+            bail!("policy hash differs between encodings: {first}={hash} {file}={other}");
+        }
+        checks += 1;
+    }
+    for file in files(store.root(), "test-vectors/policy/load")? {
+        let raw = std::fs::read_to_string(store.root().join(&file))?;
+        let vector: PolicyLoadVector = parse_json(&raw)?;
+        for case in vector.cases {
+            let got = load_policy(case.source.as_str());
+            let ok = match (&got, &case.expect_hash, &case.expect_error) {
+                (Ok(policy), Some(expect), None) => &compute_policy_hash(policy)? == expect,
+                (Err(err), None, Some(code)) => err.code() == code,
+                _ => false,
+            };
+            if !ok {
+                let actual = match got {
+                    Ok(policy) => compute_policy_hash(&policy)?,
+                    Err(err) => format!("{}: {err}", err.code()),
+                };
+                bail!(
+                    "policy load mismatch in {file} case {:?}: actual={actual:?}",
+                    case.name
+                );
+            }
+            checks += 1;
+        }
+    }
+    Ok(checks)
+}
+
+fn check_policy_lint_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/policy/lint")? {
+        let raw = std::fs::read_to_string(store.root().join(&file))?;
+        let vector: PolicyLintVector = parse_json(&raw)?;
+        for case in vector.cases {
+            validate_policy_doc(store, &case.policy)?;
+            let anchors = match &case.trust_anchors {
+                Some(doc) => Some(TrustAnchors::from_bytes_unpinned(&serde_json::to_vec(
+                    doc,
+                )?)?),
+                None => None,
+            };
+            let report = lint_policy(&case.policy, &case.config, anchors.as_ref());
+            let got: Vec<ExpectedLintFinding> = report
+                .findings
+                .iter()
+                .map(|finding| ExpectedLintFinding {
+                    rule: finding.rule,
+                    severity: finding.severity,
+                    path: finding.path.clone(),
+                })
+                .collect();
+            if got != case.expect {
+                bail!(
+                    "policy lint mismatch in {file} case {:?}: actual={got:?}",
+                    case.name
+                );
+            }
+            checks += 1;
+        }
+    }
+    for file in files(store.root(), "docs/policies")? {
+        let policy =
+            load_policy(&store.root().join(&file)).with_context(|| format!("loading {file}"))?;
+        let report = lint_policy(&policy, &LintConfig::default(), None);
+        if !report.findings.is_empty() {
+            bail!("policy template {file} has lint findings:\n{report}");
+        }
+        checks += 1;
+    }
+    Ok(checks)
+}
+
+fn check_policy_diff_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/policy/diff")? {
+        let raw = std::fs::read_to_string(store.root().join(&file))?;
+        let vector: PolicyDiffVector = parse_json(&raw)?;
+        for policy in [&vector.old, &vector.new] {
+            validate_policy_doc(store, policy)?;
+        }
+        let got = diff_policies(&vector.old, &vector.new);
+        if got != vector.expect {
+            bail!(
+                "policy diff mismatch in {file}: actual={}",
+                serde_json::to_string_pretty(&got)?
+            );
+        }
+        checks += 1;
+    }
+    Ok(checks)
+}
+
+fn check_policy_stack_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/policy/stack")? {
+        let raw = std::fs::read_to_string(store.root().join(&file))?;
+        let vector: PolicyStackVector = parse_json(&raw)?;
+        for case in vector.cases {
+            for layer in &case.layers {
+                validate_policy_doc(store, &layer.policy)?;
+            }
+            let stack: PolicyStack = case.layers.into_iter().collect();
+            let ok = match (stack.effective(), &case.expect, &case.expect_error) {
+                (Ok(effective), Some(expect), None) => {
+                    let actual = serde_json::to_value(&effective.policy)?;
+                    validate_policy_doc(store, &effective.policy)?;
+                    if &actual != expect || effective.cuts != case.expect_cuts {
+                        bail!(
+                            "policy stack mismatch in {file} case {:?}: actual={} cuts={}",
+                            case.name,
+                            serde_json::to_string_pretty(&actual)?,
+                            serde_json::to_string_pretty(&effective.cuts)?
+                        );
+                    }
+                    validate_policy(&effective.policy).is_ok()
+                        && effective.policy_hash()?
+                            == compute_policy_hash(&parse_json(&expect.to_string())?)?
+                }
+                (Err(err), None, Some(code)) => err.code() == code,
+                (got, _, _) => bail!(
+                    "policy stack mismatch in {file} case {:?}: actual={:?}",
+                    case.name,
+                    got.map(|effective| effective.policy)
+                ),
+            };
+            if !ok {
+                bail!("policy stack mismatch in {file} case {:?}", case.name);
+            }
+            checks += 1;
+        }
+    }
+    Ok(checks)
+}
+
+fn check_policy_explain_vectors(store: &SchemaStore) -> Result<usize> {
+    fn reason(value: serde_json::Value) -> String {
+        value["reason"].as_str().unwrap_or_default().to_string()
+    }
+    let mut checks = 0usize;
+    for file in files(store.root(), "test-vectors/policy/explain")? {
+        let raw = std::fs::read_to_string(store.root().join(&file))?;
+        let vector: ExplainVector = parse_json(&raw)?;
+        validate_policy_doc(store, &vector.policy)?;
+        for case in vector.cases {
+            let explanation = explain_capability(&vector.policy, &case.capability);
+            let rejection = explanation
+                .rejection
+                .as_ref()
+                .map(serde_json::to_value)
+                .transpose()?
+                .map(reason);
+            let reasons = explanation
+                .entries
+                .iter()
+                .map(|entry| {
+                    let mismatch = entry.mismatch.as_ref().map(serde_json::to_value);
+                    Ok(mismatch.transpose()?.map(reason))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let minimal = minimal_ceiling_entry(&case.capability);
+            let minimal_rejection = minimal
+                .as_ref()
+                .err()
+                .map(serde_json::to_value)
+                .transpose()?
+                .map(reason);
+            if explanation.allowed != case.expect_allowed
+                || explanation.allowed != evaluate_capability(&vector.policy, &case.capability)
+                || rejection != case.expect_rejection
+                || reasons != case.expect_reasons
+                || minimal.as_ref().ok() != case.expect_minimal.as_ref()
+                || minimal_rejection != case.expect_minimal_rejection
+            {
+                bail!(
+                    "policy explain mismatch in {file} case {:?}: actual={} minimal={:?}",
+                    case.name,
+                    serde_json::to_string_pretty(&explanation)?,
+                    minimal
+                );
+            }
+            if let Ok(entry) = minimal {
+                let mut granted = vector.policy.clone();
+                granted.capability_ceiling = Default::default();
+                entry.grant(&mut granted.capability_ceiling);
+                if !evaluate_capability(&granted, &case.capability) {
+                    bail!(
+                        "minimal ceiling entry does not allow the request in {file} case {:?}",
+                        case.name
+                    );
+                }
+            }
             checks += 1;
         }
     }
@@ -137,6 +892,33 @@ fn check_hash_semantics(store: &SchemaStore) -> Result<usize> {
     Ok(checks)
 }
 
+fn check_sbom_vectors(store: &SchemaStore) -> Result<usize> {
+    let mut checks = 0usize;
+    let raw = std::fs::read_to_string(store.root().join("test-vectors/sbom/manifest.json"))?;
+    let manifest: Manifest = parse_json(&raw)?;
+
+    for good in files(store.root(), "test-vectors/sbom/good")? {
+        let raw = std::fs::read_to_string(store.root().join(&good))?;
+        let sbom = parse_spdx(&raw).with_context(|| format!("expected valid sbom: {good}"))?;
+        verify_sbom_artifact(&sbom, &manifest)
+            .with_context(|| format!("expected sbom artifact match: {good}"))?;
+        checks += 1;
+    }
+    for bad in files(store.root(), "test-vectors/sbom/bad")? {
+        let raw = std::fs::read_to_string(store.root().join(&bad))?;
+        let verified = parse_spdx(&raw).and_then(|sbom| {
+            verify_sbom_artifact(&sbom, &manifest)?;
+            Ok(())
+        });
+        if verified.is_ok() {
+            bail!("expected sbom verification failure: {bad}");
+        }
+        checks += 1;
+    }
+
+    Ok(checks)
+}
+
 pub fn run_all(root: &Path) -> Result<Report> {
     let store = SchemaStore::load(root)?;
     let mut checks = 0usize;
@@ -162,6 +944,13 @@ pub fn run_all(root: &Path) -> Result<Report> {
         "test-vectors/policy/invalid",
         &[],
     )?;
+    checks += check_schema_group(
+        &store,
+        "spec/policy/policy.v2.schema.json",
+        "test-vectors/policy/v2/valid",
+        "test-vectors/policy/v2/invalid",
+        &[],
+    )?;
     checks += check_schema_group(
         &store,
         "spec/execution-receipt.schema.json",
@@ -183,6 +972,13 @@ pub fn run_all(root: &Path) -> Result<Report> {
         "test-vectors/registry/snapshot/bad",
         &["test-vectors/registry/snapshot/bad/hash-mismatch.json"],
     )?;
+    checks += check_schema_group(
+        &store,
+        "spec/registry/snapshot.v1.schema.json",
+        "test-vectors/registry/snapshot-v1/good",
+        "test-vectors/registry/snapshot-v1/bad",
+        &["test-vectors/registry/snapshot-v1/bad/hash-mismatch.json"],
+    )?;
     checks += check_schema_group(
         &store,
         "spec/skill-format/manifest.schema.json",
@@ -212,8 +1008,33 @@ pub fn run_all(root: &Path) -> Result<Report> {
         &[],
     )?;
 
+    checks += check_schema_group(
+        &store,
+        "spec/trust/trust-anchors.schema.json",
+        "test-vectors/trust-anchors/good",
+        "test-vectors/trust-anchors/bad",
+        &[],
+    )?;
+
     checks += check_capability_vectors(&store)?;
+    checks += check_signer_trust_vectors(&store)?;
+    checks += check_signer_policy_vectors(&store)?;
+    checks += check_snapshot_resolve_vectors(&store)?;
+    checks += check_signed_snapshot_vectors(&store)?;
+    checks += check_snapshot_build_vectors(&store)?;
+    checks += check_snapshot_diff_vectors(&store)?;
+    checks += check_snapshot_v1_vectors(&store)?;
+    checks += check_oci_reference_vectors(&store)?;
+    checks += check_manifest_compat_vectors(&store)?;
+    checks += check_negotiation_vectors(&store)?;
+    checks += check_detect_vectors(&store)?;
+    checks += check_policy_load_vectors(&store)?;
+    checks += check_policy_lint_vectors(&store)?;
+    checks += check_policy_diff_vectors(&store)?;
+    checks += check_policy_stack_vectors(&store)?;
+    checks += check_policy_explain_vectors(&store)?;
     checks += check_hash_semantics(&store)?;
+    checks += check_sbom_vectors(&store)?;
 
     Ok(Report { checks })
 }
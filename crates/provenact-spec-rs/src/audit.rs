@@ -0,0 +1,42 @@
+//! Audit execution events (`docs/audit/execution-event.schema.json`).
+
+use serde::{Deserialize, Serialize};
+
+pub const AUDIT_EVENT_SCHEMA_VERSION: &str = "1.0.0";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditExecutionEvent {
+    pub schema_version: String,
+    /// RFC 3339 date-time.
+    pub timestamp: String,
+    pub request_id: String,
+    pub tool_digest: String,
+    pub policy_hash: String,
+    pub receipt_hash: String,
+    pub decision: AuditDecision,
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signer_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caps_requested: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caps_granted: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caps_used: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditDecision {
+    Allow,
+    Deny,
+}
@@ -0,0 +1,499 @@
+//! Capability requests checked against a policy's ceiling
+//! (`spec/policy/capability-evaluation.md`).
+//!
+//! [`evaluate_capability`] and [`crate::explain_capability`] share the
+//! request parsing and entry checks here, so an explanation always agrees
+//! with the decision.
+
+use crate::{
+    is_valid_env_name, is_valid_http_method, is_within_prefix, Capability, CapabilityCeiling,
+    NamePattern, NetEntry, NetLimits, Policy, NAME_PATTERN_POLICY_VERSION,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathRejection {
+    NotAbsolute,
+    NulByte,
+    DotSegment,
+    DotDotSegment,
+    Backslash,
+    PercentEncoded,
+}
+
+impl fmt::Display for PathRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NotAbsolute => "is not absolute",
+            Self::NulByte => "contains a NUL byte",
+            Self::DotSegment => "contains a `.` segment",
+            Self::DotDotSegment => "contains a `..` segment",
+            Self::Backslash => "contains a backslash",
+            Self::PercentEncoded => "contains a percent-encoded byte",
+        })
+    }
+}
+
+/// Why a request is denied before any ceiling entry is consulted, or why no
+/// ceiling entry can allow it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum RequestRejection {
+    UnknownKind,
+    EmptyValue,
+    /// `exec` requests must have the value `true`.
+    InvalidValue,
+    InvalidEnvName,
+    UnparseableUri,
+    Path {
+        rejection: PathRejection,
+    },
+    /// A method on a request that is not `net.http`.
+    UnexpectedMethod,
+    /// A method that is not uppercase ASCII letters.
+    InvalidMethod,
+    /// Only from [`minimal_ceiling_entry`]: no `net` prefix matches a URI
+    /// without an authority, with a fragment, or with an unnormalizable path.
+    NoAuthority,
+    Fragment,
+}
+
+impl fmt::Display for RequestRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownKind => f.write_str("unknown capability kind"),
+            Self::EmptyValue => f.write_str("empty capability value"),
+            Self::InvalidValue => f.write_str("exec requires the value \"true\""),
+            Self::InvalidEnvName => f.write_str("not a valid environment variable name"),
+            Self::UnparseableUri => f.write_str("not a parseable URI"),
+            Self::Path { rejection } => write!(f, "requested path {rejection}"),
+            Self::UnexpectedMethod => f.write_str("only net.http requests take a method"),
+            Self::InvalidMethod => f.write_str("method is not uppercase ASCII letters"),
+            Self::NoAuthority => f.write_str("URI has no authority"),
+            Self::Fragment => f.write_str("URI has a fragment"),
+        }
+    }
+}
+
+/// The first comparison that failed between a request and one entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum EntryMismatch {
+    UnparseableEntry,
+    NoAuthority,
+    Scheme {
+        requested: String,
+        allowed: String,
+    },
+    Host {
+        requested: Option<String>,
+        allowed: Option<String>,
+    },
+    Port {
+        requested: Option<u16>,
+        allowed: Option<u16>,
+    },
+    /// Values are withheld because userinfo may carry credentials.
+    Userinfo,
+    RequestFragment,
+    EntryQuery,
+    EntryFragment,
+    RequestPath {
+        rejection: PathRejection,
+    },
+    EntryPath {
+        rejection: PathRejection,
+    },
+    /// Both paths normalize, but the requested one is not the prefix or
+    /// below it at a `/` boundary.
+    OutsidePrefix {
+        path: String,
+        prefix: String,
+    },
+    /// The URI is within the prefix, but the entry allows other methods.
+    /// `requested` is `None` for a request without a method.
+    Method {
+        requested: Option<String>,
+        allowed: Vec<String>,
+    },
+    /// A version 2 `kv`/`queue` entry that is not a valid name pattern.
+    InvalidPattern,
+    ValueMismatch,
+    Disabled,
+}
+
+impl fmt::Display for EntryMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnparseableEntry => f.write_str("entry is not a parseable URI"),
+            Self::NoAuthority => f.write_str("request or entry has no authority"),
+            Self::Scheme { requested, allowed } => {
+                write!(f, "scheme {requested} does not match {allowed}")
+            }
+            Self::Host { requested, allowed } => write!(
+                f,
+                "host {} does not match {}",
+                requested.as_deref().unwrap_or("<none>"),
+                allowed.as_deref().unwrap_or("<none>")
+            ),
+            Self::Port { requested, allowed } => write!(
+                f,
+                "effective port {} does not match {}",
+                requested.map_or("<none>".into(), |p| p.to_string()),
+                allowed.map_or("<none>".into(), |p| p.to_string())
+            ),
+            Self::Userinfo => f.write_str("userinfo does not match"),
+            Self::RequestFragment => f.write_str("request has a fragment"),
+            Self::EntryQuery => f.write_str("entry has a query"),
+            Self::EntryFragment => f.write_str("entry has a fragment"),
+            Self::RequestPath { rejection } => write!(f, "requested path {rejection}"),
+            Self::EntryPath { rejection } => write!(f, "entry path {rejection}"),
+            Self::OutsidePrefix { path, prefix } => {
+                write!(f, "path {path} is outside prefix {prefix}")
+            }
+            Self::Method { requested, allowed } => write!(
+                f,
+                "method {} is not one of {}",
+                requested.as_deref().unwrap_or("<none>"),
+                allowed.join(", ")
+            ),
+            Self::InvalidPattern => f.write_str("entry is not a valid name pattern"),
+            Self::ValueMismatch => f.write_str("value does not match"),
+            Self::Disabled => f.write_str("disabled"),
+        }
+    }
+}
+
+pub(crate) fn check_fs_path(path: &str) -> Result<String, PathRejection> {
+    if !path.starts_with('/') {
+        return Err(PathRejection::NotAbsolute);
+    }
+    if path.contains('\0') {
+        return Err(PathRejection::NulByte);
+    }
+    let mut parts = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" => continue,
+            "." => return Err(PathRejection::DotSegment),
+            ".." => return Err(PathRejection::DotDotSegment),
+            _ => parts.push(segment),
+        }
+    }
+    Ok(format!("/{}", parts.join("/")))
+}
+
+pub(crate) fn check_uri_path(path: &str) -> Result<String, PathRejection> {
+    let raw = if path.is_empty() { "/" } else { path };
+    if raw.contains('\\') {
+        return Err(PathRejection::Backslash);
+    }
+    if contains_pct_encoded_triplet(raw) {
+        return Err(PathRejection::PercentEncoded);
+    }
+    check_fs_path(raw)
+}
+
+fn contains_pct_encoded_triplet(value: &str) -> bool {
+    value.as_bytes().windows(3).any(|window| {
+        window[0] == b'%' && window[1].is_ascii_hexdigit() && window[2].is_ascii_hexdigit()
+    })
+}
+
+pub(crate) fn check_net_prefix(requested: &Url, allowed: &Url) -> Result<(), EntryMismatch> {
+    if !requested.has_authority() || !allowed.has_authority() {
+        return Err(EntryMismatch::NoAuthority);
+    }
+    if requested.scheme() != allowed.scheme() {
+        return Err(EntryMismatch::Scheme {
+            requested: requested.scheme().to_string(),
+            allowed: allowed.scheme().to_string(),
+        });
+    }
+    if requested.host_str() != allowed.host_str() {
+        return Err(EntryMismatch::Host {
+            requested: requested.host_str().map(str::to_string),
+            allowed: allowed.host_str().map(str::to_string),
+        });
+    }
+    if requested.port_or_known_default() != allowed.port_or_known_default() {
+        return Err(EntryMismatch::Port {
+            requested: requested.port_or_known_default(),
+            allowed: allowed.port_or_known_default(),
+        });
+    }
+    if requested.username() != allowed.username() || requested.password() != allowed.password() {
+        return Err(EntryMismatch::Userinfo);
+    }
+    if requested.fragment().is_some() {
+        return Err(EntryMismatch::RequestFragment);
+    }
+    if allowed.query().is_some() {
+        return Err(EntryMismatch::EntryQuery);
+    }
+    if allowed.fragment().is_some() {
+        return Err(EntryMismatch::EntryFragment);
+    }
+    let path = check_uri_path(requested.path())
+        .map_err(|rejection| EntryMismatch::RequestPath { rejection })?;
+    let prefix = check_uri_path(allowed.path())
+        .map_err(|rejection| EntryMismatch::EntryPath { rejection })?;
+    if is_within_prefix(&path, &prefix) {
+        Ok(())
+    } else {
+        Err(EntryMismatch::OutsidePrefix { path, prefix })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Flag {
+    Exec,
+    Time,
+    Random,
+}
+
+impl Flag {
+    pub(crate) fn pointer(self) -> &'static str {
+        match self {
+            Self::Exec => "/capability_ceiling/exec",
+            Self::Time => "/capability_ceiling/time",
+            Self::Random => "/capability_ceiling/random",
+        }
+    }
+
+    pub(crate) fn enabled(self, ceiling: &CapabilityCeiling) -> bool {
+        match self {
+            Self::Exec => ceiling.exec,
+            Self::Time => ceiling.time,
+            Self::Random => ceiling.random,
+        }
+    }
+}
+
+/// JSON pointer to the `net` entries of a policy.
+pub(crate) const NET_POINTER: &str = "/capability_ceiling/net";
+
+/// A ceiling list of plain string entries.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum NameList {
+    FsRead,
+    FsWrite,
+    Env,
+    KvRead,
+    KvWrite,
+    QueuePublish,
+    QueueConsume,
+}
+
+impl NameList {
+    pub(crate) fn pointer(self) -> &'static str {
+        match self {
+            Self::FsRead => "/capability_ceiling/fs/read",
+            Self::FsWrite => "/capability_ceiling/fs/write",
+            Self::Env => "/capability_ceiling/env",
+            Self::KvRead => "/capability_ceiling/kv/read",
+            Self::KvWrite => "/capability_ceiling/kv/write",
+            Self::QueuePublish => "/capability_ceiling/queue/publish",
+            Self::QueueConsume => "/capability_ceiling/queue/consume",
+        }
+    }
+
+    pub(crate) fn entries(self, ceiling: &CapabilityCeiling) -> &[String] {
+        match self {
+            Self::FsRead => &ceiling.fs.read,
+            Self::FsWrite => &ceiling.fs.write,
+            Self::Env => &ceiling.env,
+            Self::KvRead => &ceiling.kv.read,
+            Self::KvWrite => &ceiling.kv.write,
+            Self::QueuePublish => &ceiling.queue.publish,
+            Self::QueueConsume => &ceiling.queue.consume,
+        }
+    }
+}
+
+/// A request that passed its own validation, ready to compare with entries.
+pub(crate) enum Request<'a> {
+    /// Allowed by a boolean ceiling flag alone.
+    Flag(Flag),
+    /// Compared with the string entries of one list.
+    Name(NameRequest<'a>),
+    /// Compared with the `net` entries.
+    Net(NetRequest<'a>),
+}
+
+pub(crate) enum NameRequest<'a> {
+    /// `env`: exact match.
+    Exact(NameList, &'a str),
+    /// `kv`/`queue`: exact match or `"*"`, or a name pattern from policy
+    /// version 2.
+    Pattern(NameList, &'a str),
+    /// `fs`: the normalized path, within an entry's prefix.
+    Fs(NameList, String),
+}
+
+pub(crate) struct NetRequest<'a> {
+    pub(crate) url: Url,
+    pub(crate) method: Option<&'a str>,
+}
+
+impl<'a> Request<'a> {
+    pub(crate) fn parse(capability: &'a Capability) -> Result<Self, RequestRejection> {
+        let value = capability.value.as_str();
+        let method = capability.method.as_deref();
+        if let Some(method) = method {
+            if capability.kind != "net.http" {
+                return Err(RequestRejection::UnexpectedMethod);
+            }
+            if !is_valid_http_method(method) {
+                return Err(RequestRejection::InvalidMethod);
+            }
+        }
+        let non_empty = |request| {
+            if value.is_empty() {
+                Err(RequestRejection::EmptyValue)
+            } else {
+                Ok(request)
+            }
+        };
+        let pattern = |list| non_empty(Self::Name(NameRequest::Pattern(list, value)));
+        match capability.kind.as_str() {
+            "exec" if value == "true" => Ok(Self::Flag(Flag::Exec)),
+            "exec" => Err(RequestRejection::InvalidValue),
+            "exec.safe" => non_empty(Self::Flag(Flag::Exec)),
+            "time.now" => non_empty(Self::Flag(Flag::Time)),
+            "random.bytes" => non_empty(Self::Flag(Flag::Random)),
+            "env" if is_valid_env_name(value) => {
+                Ok(Self::Name(NameRequest::Exact(NameList::Env, value)))
+            }
+            "env" => Err(RequestRejection::InvalidEnvName),
+            "net.http" => Url::parse(value)
+                .map(|url| Self::Net(NetRequest { url, method }))
+                .map_err(|_| RequestRejection::UnparseableUri),
+            "fs.read" | "fs.write" => {
+                let list = if capability.kind == "fs.read" {
+                    NameList::FsRead
+                } else {
+                    NameList::FsWrite
+                };
+                check_fs_path(value)
+                    .map(|path| Self::Name(NameRequest::Fs(list, path)))
+                    .map_err(|rejection| RequestRejection::Path { rejection })
+            }
+            "kv.read" => pattern(NameList::KvRead),
+            "kv.write" => pattern(NameList::KvWrite),
+            "queue.publish" => pattern(NameList::QueuePublish),
+            "queue.consume" => pattern(NameList::QueueConsume),
+            _ => Err(RequestRejection::UnknownKind),
+        }
+    }
+
+    fn allowed_by(&self, policy: &Policy) -> bool {
+        let ceiling = &policy.capability_ceiling;
+        match self {
+            Self::Flag(flag) => flag.enabled(ceiling),
+            Self::Name(request) => request
+                .list()
+                .entries(ceiling)
+                .iter()
+                .any(|entry| request.check(entry, policy.version).is_ok()),
+            Self::Net(request) => ceiling.net.iter().any(|entry| request.check(entry).is_ok()),
+        }
+    }
+}
+
+impl NameRequest<'_> {
+    pub(crate) fn list(&self) -> NameList {
+        match self {
+            Self::Exact(list, _) | Self::Pattern(list, _) | Self::Fs(list, _) => *list,
+        }
+    }
+
+    /// `version` is the policy version, which decides how `kv`/`queue`
+    /// entries match.
+    pub(crate) fn check(&self, entry: &str, version: u64) -> Result<(), EntryMismatch> {
+        let matched = match self {
+            Self::Exact(_, value) => entry == *value,
+            Self::Pattern(list, value) if version >= NAME_PATTERN_POLICY_VERSION => {
+                let pattern = match list {
+                    NameList::KvRead | NameList::KvWrite => NamePattern::kv(entry),
+                    _ => NamePattern::queue(entry),
+                };
+                pattern
+                    .map_err(|_| EntryMismatch::InvalidPattern)?
+                    .matches(value)
+            }
+            Self::Pattern(_, value) => entry == "*" || entry == *value,
+            Self::Fs(_, path) => {
+                let prefix = check_fs_path(entry)
+                    .map_err(|rejection| EntryMismatch::EntryPath { rejection })?;
+                if is_within_prefix(path, &prefix) {
+                    return Ok(());
+                }
+                return Err(EntryMismatch::OutsidePrefix {
+                    path: path.clone(),
+                    prefix,
+                });
+            }
+        };
+        if matched {
+            Ok(())
+        } else {
+            Err(EntryMismatch::ValueMismatch)
+        }
+    }
+}
+
+impl NetRequest<'_> {
+    pub(crate) fn check(&self, entry: &NetEntry) -> Result<(), EntryMismatch> {
+        let allowed = Url::parse(entry.prefix()).map_err(|_| EntryMismatch::UnparseableEntry)?;
+        check_net_prefix(&self.url, &allowed)?;
+        if !entry.allows_method(self.method) {
+            return Err(EntryMismatch::Method {
+                requested: self.method.map(str::to_string),
+                allowed: entry.methods().to_vec(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Every capability kind a manifest may request.
+pub const CAPABILITY_KINDS: [&str; 12] = [
+    "exec",
+    "exec.safe",
+    "time.now",
+    "random.bytes",
+    "env",
+    "net.http",
+    "fs.read",
+    "fs.write",
+    "kv.read",
+    "kv.write",
+    "queue.publish",
+    "queue.consume",
+];
+
+/// Decides a request against `policy.capability_ceiling`;
+/// [`crate::explain_capability`] reports why.
+pub fn evaluate_capability(policy: &Policy, capability: &Capability) -> bool {
+    Request::parse(capability).is_ok_and(|request| request.allowed_by(policy))
+}
+
+/// The limits for an allowed `net.http` request: each limit is the loosest
+/// of the entries that allow it, since any of them would. `None` when the
+/// request is denied or is not `net.http`.
+pub fn net_limits(policy: &Policy, capability: &Capability) -> Option<NetLimits> {
+    let Ok(Request::Net(request)) = Request::parse(capability) else {
+        return None;
+    };
+    policy
+        .capability_ceiling
+        .net
+        .iter()
+        .filter(|entry| request.check(entry).is_ok())
+        .map(NetEntry::limits)
+        .reduce(NetLimits::loosest)
+}
@@ -0,0 +1,379 @@
+//! Capability decisions explained entry by entry
+//! (`spec/policy/capability-evaluation.md`).
+//!
+//! Explanations reuse the request parsing and entry checks behind
+//! [`crate::evaluate_capability`], so they always agree with the decision.
+
+use crate::capability::{check_uri_path, NameRequest, NetRequest, Request, NET_POINTER};
+use crate::{
+    Capability, CapabilityCeiling, EntryMismatch, NetEntry, NetLimits, Policy, RequestRejection,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntryExplanation {
+    /// JSON pointer to the entry (or flag) in the policy.
+    pub path: String,
+    /// The entry as written, the prefix of a structured `net` entry, or the
+    /// flag value.
+    pub entry: String,
+    /// `None` when the entry allows the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mismatch: Option<EntryMismatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapabilityExplanation {
+    pub capability: Capability,
+    pub allowed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<RequestRejection>,
+    /// Every entry consulted for the request's kind, in policy order.
+    pub entries: Vec<EntryExplanation>,
+}
+
+impl fmt::Display for CapabilityExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.capability.kind, self.capability.value)?;
+        if let Some(method) = &self.capability.method {
+            write!(f, " {method}")?;
+        }
+        f.write_str(if self.allowed { " allowed" } else { " denied" })?;
+        if let Some(rejection) = &self.rejection {
+            write!(f, ": {rejection}")?;
+        } else if self.entries.is_empty() {
+            write!(f, ": no ceiling entries")?;
+        }
+        for entry in &self.entries {
+            write!(f, "\n  {} {:?}: ", entry.path, entry.entry)?;
+            match &entry.mismatch {
+                Some(mismatch) => write!(f, "{mismatch}")?,
+                None => f.write_str("matches")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Explains [`crate::evaluate_capability`] for `capability`: the request's
+/// own validation, then every ceiling entry of its kind with the first
+/// comparison that failed.
+pub fn explain_capability(policy: &Policy, capability: &Capability) -> CapabilityExplanation {
+    let ceiling = &policy.capability_ceiling;
+    let (rejection, entries) = match Request::parse(capability) {
+        Err(rejection) => (Some(rejection), Vec::new()),
+        Ok(Request::Flag(flag)) => {
+            let enabled = flag.enabled(ceiling);
+            let entry = EntryExplanation {
+                path: flag.pointer().to_string(),
+                entry: enabled.to_string(),
+                mismatch: (!enabled).then_some(EntryMismatch::Disabled),
+            };
+            (None, vec![entry])
+        }
+        Ok(Request::Name(request)) => {
+            let list = request.list();
+            let entries = list
+                .entries(ceiling)
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| EntryExplanation {
+                    path: format!("{}/{index}", list.pointer()),
+                    entry: entry.clone(),
+                    mismatch: request.check(entry, policy.version).err(),
+                })
+                .collect();
+            (None, entries)
+        }
+        Ok(Request::Net(request)) => {
+            let entries = ceiling
+                .net
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| EntryExplanation {
+                    path: format!("{NET_POINTER}/{index}"),
+                    entry: entry.prefix().to_string(),
+                    mismatch: request.check(entry).err(),
+                })
+                .collect();
+            (None, entries)
+        }
+    };
+    CapabilityExplanation {
+        capability: capability.clone(),
+        allowed: rejection.is_none() && entries.iter().any(|e| e.mismatch.is_none()),
+        rejection,
+        entries,
+    }
+}
+
+/// A ceiling entry: a list entry at `path` (e.g. `/capability_ceiling/net`),
+/// or `true` for a flag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CeilingEntry {
+    pub path: String,
+    pub value: Value,
+}
+
+impl CeilingEntry {
+    /// Adds the entry to `ceiling` unless it is already present.
+    pub fn grant(&self, ceiling: &mut CapabilityCeiling) {
+        let list = match self.path.as_str() {
+            "/capability_ceiling/exec" => return ceiling.exec = true,
+            "/capability_ceiling/time" => return ceiling.time = true,
+            "/capability_ceiling/random" => return ceiling.random = true,
+            "/capability_ceiling/fs/read" => &mut ceiling.fs.read,
+            "/capability_ceiling/fs/write" => &mut ceiling.fs.write,
+            "/capability_ceiling/net" => {
+                if let Ok(entry) = serde_json::from_value::<NetEntry>(self.value.clone()) {
+                    if !ceiling.net.contains(&entry) {
+                        ceiling.net.push(entry);
+                    }
+                }
+                return;
+            }
+            "/capability_ceiling/env" => &mut ceiling.env,
+            "/capability_ceiling/kv/read" => &mut ceiling.kv.read,
+            "/capability_ceiling/kv/write" => &mut ceiling.kv.write,
+            "/capability_ceiling/queue/publish" => &mut ceiling.queue.publish,
+            "/capability_ceiling/queue/consume" => &mut ceiling.queue.consume,
+            _ => return,
+        };
+        if let Some(value) = self.value.as_str() {
+            if !list.iter().any(|entry| entry == value) {
+                list.push(value.to_string());
+            }
+        }
+    }
+}
+
+/// The narrowest ceiling entry that allows `capability`: the normalized path
+/// for `fs`, the URI without query and with a normalized path for `net`
+/// (a rule allowing only the requested method, if any, which needs policy
+/// version 2), the exact name for `env`/`kv`/`queue`, and the flag otherwise. A version 2
+/// policy reads a `kv`/`queue` name as a pattern, which is only exact when
+/// the name has no empty, `*`-terminated, or `>` segments.
+pub fn minimal_ceiling_entry(capability: &Capability) -> Result<CeilingEntry, RequestRejection> {
+    let (list, value) = match Request::parse(capability)? {
+        Request::Flag(flag) => {
+            return Ok(CeilingEntry {
+                path: flag.pointer().to_string(),
+                value: Value::Bool(true),
+            })
+        }
+        Request::Name(NameRequest::Exact(list, value) | NameRequest::Pattern(list, value)) => {
+            (list, value.to_string())
+        }
+        Request::Name(NameRequest::Fs(list, path)) => (list, path),
+        Request::Net(NetRequest { mut url, method }) => {
+            if !url.has_authority() {
+                return Err(RequestRejection::NoAuthority);
+            }
+            if url.fragment().is_some() {
+                return Err(RequestRejection::Fragment);
+            }
+            let path = check_uri_path(url.path())
+                .map_err(|rejection| RequestRejection::Path { rejection })?;
+            url.set_query(None);
+            url.set_path(&path);
+            let methods = method.into_iter().map(str::to_string).collect();
+            let entry = NetEntry::new(url.to_string(), methods, NetLimits::default());
+            return Ok(CeilingEntry {
+                path: NET_POINTER.to_string(),
+                value: serde_json::json!(entry),
+            });
+        }
+    };
+    Ok(CeilingEntry {
+        path: list.pointer().to_string(),
+        value: Value::String(value),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExplainVector {
+    pub name: String,
+    pub policy: Policy,
+    pub cases: Vec<ExplainCase>,
+}
+
+/// `expect_reasons` lists each entry's mismatch `reason`, `null` for a
+/// matching entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExplainCase {
+    pub name: String,
+    pub capability: Capability,
+    pub expect_allowed: bool,
+    #[serde(default)]
+    pub expect_rejection: Option<String>,
+    #[serde(default)]
+    pub expect_reasons: Vec<Option<String>>,
+    #[serde(default)]
+    pub expect_minimal: Option<CeilingEntry>,
+    #[serde(default)]
+    pub expect_minimal_rejection: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_capability, net_limits, PathRejection};
+    use serde_json::json;
+
+    fn capability(kind: &str, value: &str) -> Capability {
+        Capability {
+            kind: kind.to_string(),
+            value: value.to_string(),
+            method: None,
+        }
+    }
+
+    fn policy() -> Policy {
+        serde_json::from_value(json!({
+            "version": 1,
+            "trusted_signers": ["alice.dev"],
+            "capability_ceiling": {
+                "fs": { "read": ["/data", "/srv/./x"] },
+                "net": [
+                    "http://api.example.test/v1",
+                    "https://api.example.test:8443/v1",
+                    "https://ops@api.example.test/v1",
+                    "https://api.example.test/v1"
+                ]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn dot_segments_in_urls_are_explained_as_outside_prefix() {
+        let explanation = explain_capability(
+            &policy(),
+            &capability("net.http", "https://api.example.test/v1/../admin"),
+        );
+        assert!(!explanation.allowed);
+        let reasons: Vec<_> = explanation
+            .entries
+            .iter()
+            .map(|e| e.mismatch.clone())
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                Some(EntryMismatch::Scheme {
+                    requested: "https".into(),
+                    allowed: "http".into()
+                }),
+                Some(EntryMismatch::Port {
+                    requested: Some(443),
+                    allowed: Some(8443)
+                }),
+                Some(EntryMismatch::Userinfo),
+                Some(EntryMismatch::OutsidePrefix {
+                    path: "/admin".into(),
+                    prefix: "/v1".into()
+                }),
+            ]
+        );
+        assert!(explanation
+            .to_string()
+            .ends_with("/capability_ceiling/net/3 \"https://api.example.test/v1\": path /admin is outside prefix /v1"));
+    }
+
+    #[test]
+    fn fs_explanations_cover_request_and_entry_paths() {
+        let explanation = explain_capability(&policy(), &capability("fs.read", "/data2/x"));
+        assert_eq!(
+            explanation.entries[1].mismatch,
+            Some(EntryMismatch::EntryPath {
+                rejection: PathRejection::DotSegment
+            })
+        );
+        let explanation = explain_capability(&policy(), &capability("fs.read", "/data/../etc"));
+        assert_eq!(
+            explanation.rejection,
+            Some(RequestRejection::Path {
+                rejection: PathRejection::DotDotSegment
+            })
+        );
+        assert!(explanation.entries.is_empty());
+        let explanation = explain_capability(&policy(), &capability("exec", "true"));
+        assert_eq!(
+            explanation.entries[0].mismatch,
+            Some(EntryMismatch::Disabled)
+        );
+    }
+
+    #[test]
+    fn method_violations_are_explained_after_the_prefix() {
+        let policy: Policy = serde_json::from_value(json!({
+            "version": 2,
+            "trusted_signers": ["alice.dev"],
+            "capability_ceiling": {
+                "net": [
+                    { "prefix": "https://api.example.test/v1", "methods": ["GET"], "timeout_ms": 500 },
+                    { "prefix": "https://api.example.test", "methods": ["GET"], "timeout_ms": 2000 },
+                ]
+            }
+        }))
+        .unwrap();
+        let mut request = capability("net.http", "https://api.example.test/v1/users");
+        request.method = Some("POST".into());
+        let explanation = explain_capability(&policy, &request);
+        assert!(!explanation.allowed);
+        assert_eq!(
+            explanation.entries[0].mismatch,
+            Some(EntryMismatch::Method {
+                requested: Some("POST".into()),
+                allowed: vec!["GET".into()]
+            })
+        );
+        assert!(explanation
+            .to_string()
+            .starts_with("net.http:https://api.example.test/v1/users POST denied"));
+        assert_eq!(net_limits(&policy, &request), None);
+        request.method = Some("GET".into());
+        assert_eq!(
+            net_limits(&policy, &request).and_then(|limits| limits.timeout_ms),
+            Some(2000)
+        );
+        request.method = None;
+        assert!(!evaluate_capability(&policy, &request));
+    }
+
+    #[test]
+    fn minimal_entries_allow_exactly_the_request() {
+        for (kind, value, expect) in [
+            (
+                "net.http",
+                "https://API.example.test:443/v1//users/?page=2",
+                json!("https://api.example.test/v1/users"),
+            ),
+            ("fs.write", "/tmp//out/", json!("/tmp/out")),
+            ("kv.read", "token", json!("token")),
+            ("time.now", "wall", json!(true)),
+        ] {
+            let request = capability(kind, value);
+            let entry = minimal_ceiling_entry(&request).unwrap();
+            assert_eq!(entry.value, expect);
+            let mut granted = Policy {
+                capability_ceiling: CapabilityCeiling::default(),
+                ..policy()
+            };
+            assert!(!evaluate_capability(&granted, &request));
+            entry.grant(&mut granted.capability_ceiling);
+            assert!(evaluate_capability(&granted, &request), "{kind} {value}");
+        }
+        assert_eq!(
+            minimal_ceiling_entry(&capability("net.http", "https://a.test/x#frag")),
+            Err(RequestRejection::Fragment)
+        );
+    }
+}
@@ -0,0 +1,36 @@
+//! Local install store metadata (`spec/install/meta.schema.json`,
+//! `spec/install/index.schema.json`).
+
+use serde::{Deserialize, Serialize};
+
+pub const INSTALL_SCHEMA_VERSION: &str = "1.0.0";
+
+/// `meta.json` written next to an installed skill.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstallMeta {
+    pub schema_version: String,
+    pub skill: String,
+    pub source: String,
+    pub manifest_name: String,
+    pub manifest_version: String,
+    pub installed_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstallIndex {
+    pub schema_version: String,
+    pub entries: Vec<InstallIndexEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstallIndexEntry {
+    pub skill: String,
+    pub source: String,
+    pub store: String,
+    pub installed_at: u64,
+    pub manifest_name: String,
+    pub manifest_version: String,
+}
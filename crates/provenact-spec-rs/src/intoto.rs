@@ -0,0 +1,417 @@
+//! in-toto Statement v1 / SLSA Provenance v1 interop and DSSE envelope verification.
+//!
+//! Mapping to and from `provenance.json` is documented in `docs/in-toto-provenance.md`.
+
+use crate::trust::verify_ed25519;
+use crate::{
+    sha256_prefixed, to_jcs_bytes, validate_sha256_prefixed, KeyStatus, Manifest, Provenance,
+    SpecError, TrustAnchors,
+};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub const IN_TOTO_STATEMENT_V1: &str = "https://in-toto.io/Statement/v1";
+pub const SLSA_PROVENANCE_V1: &str = "https://slsa.dev/provenance/v1";
+pub const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+pub const PROVENACT_BUILD_TYPE: &str = "https://provenact.dev/slsa/build-type/v0";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AttestationError {
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("unsupported statement type: {0}")]
+    UnsupportedStatementType(String),
+    #[error("unsupported predicate type: {0}")]
+    UnsupportedPredicateType(String),
+    #[error("no statement subject matches manifest artifact {0}")]
+    SubjectMismatch(String),
+    #[error("statement has no resolved dependency with a uri and gitCommit digest")]
+    MissingSource,
+    #[error("unsupported dsse payload type: {0}")]
+    UnsupportedPayloadType(String),
+    #[error("invalid base64 dsse payload")]
+    InvalidPayloadEncoding,
+    #[error("dsse envelope has no signature from a trusted key")]
+    NoValidSignature,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InTotoStatement {
+    #[serde(rename = "_type")]
+    pub statement_type: String,
+    pub subject: Vec<ResourceDescriptor>,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+    pub predicate: SlsaProvenance,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceDescriptor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub digest: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlsaProvenance {
+    pub build_definition: BuildDefinition,
+    pub run_details: RunDetails,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildDefinition {
+    pub build_type: String,
+    pub external_parameters: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub internal_parameters: Option<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolved_dependencies: Vec<ResourceDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunDetails {
+    pub builder: Builder,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BuildMetadata>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub byproducts: Vec<ResourceDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Builder {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub version: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub builder_dependencies: Vec<ResourceDescriptor>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invocation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_on: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_on: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DsseEnvelope {
+    pub payload: String,
+    #[serde(rename = "payloadType")]
+    pub payload_type: String,
+    pub signatures: Vec<DsseSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DsseSignature {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub keyid: String,
+    pub sig: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedStatement {
+    pub statement: InTotoStatement,
+    pub signers: Vec<String>,
+}
+
+/// Converts an in-toto SLSA v1 statement into `provenance.json`, requiring a
+/// subject whose `sha256` digest equals `manifest.artifact`.
+pub fn provenance_from_statement(
+    statement: &InTotoStatement,
+    manifest: &Manifest,
+) -> Result<Provenance, AttestationError> {
+    if statement.statement_type != IN_TOTO_STATEMENT_V1 {
+        return Err(AttestationError::UnsupportedStatementType(
+            statement.statement_type.clone(),
+        ));
+    }
+    if statement.predicate_type != SLSA_PROVENANCE_V1 {
+        return Err(AttestationError::UnsupportedPredicateType(
+            statement.predicate_type.clone(),
+        ));
+    }
+    validate_sha256_prefixed(&manifest.artifact)?;
+    let artifact_hex = &manifest.artifact["sha256:".len()..];
+    let subject_matches = statement.subject.iter().any(|subject| {
+        subject
+            .digest
+            .get("sha256")
+            .is_some_and(|digest| digest.eq_ignore_ascii_case(artifact_hex))
+    });
+    if !subject_matches {
+        return Err(AttestationError::SubjectMismatch(manifest.artifact.clone()));
+    }
+
+    let definition = &statement.predicate.build_definition;
+    let (source, commit) = definition
+        .resolved_dependencies
+        .iter()
+        .find_map(|dep| Some((dep.uri.clone()?, dep.digest.get("gitCommit")?.clone())))
+        .ok_or(AttestationError::MissingSource)?;
+    let build_system = definition
+        .external_parameters
+        .get("build_system")
+        .and_then(Value::as_str)
+        .unwrap_or(&definition.build_type)
+        .to_string();
+    let build_recipe_hash = match definition
+        .external_parameters
+        .get("build_recipe_hash")
+        .and_then(Value::as_str)
+    {
+        Some(hash) => {
+            validate_sha256_prefixed(hash)?;
+            hash.to_string()
+        }
+        None => sha256_prefixed(&to_jcs_bytes(definition)?),
+    };
+
+    Ok(Provenance {
+        source,
+        commit,
+        build_system,
+        build_recipe_hash,
+    })
+}
+
+/// Exports `provenance.json` as an in-toto SLSA v1 statement whose single
+/// subject is `manifest.artifact`.
+pub fn statement_from_provenance(
+    provenance: &Provenance,
+    manifest: &Manifest,
+    builder_id: &str,
+) -> Result<InTotoStatement, AttestationError> {
+    validate_sha256_prefixed(&manifest.artifact)?;
+    validate_sha256_prefixed(&provenance.build_recipe_hash)?;
+    let subject = ResourceDescriptor {
+        name: Some(manifest.name.clone()),
+        digest: BTreeMap::from([(
+            "sha256".to_string(),
+            manifest.artifact["sha256:".len()..].to_string(),
+        )]),
+        ..ResourceDescriptor::default()
+    };
+    let source = ResourceDescriptor {
+        uri: Some(provenance.source.clone()),
+        digest: BTreeMap::from([("gitCommit".to_string(), provenance.commit.clone())]),
+        ..ResourceDescriptor::default()
+    };
+    Ok(InTotoStatement {
+        statement_type: IN_TOTO_STATEMENT_V1.to_string(),
+        subject: vec![subject],
+        predicate_type: SLSA_PROVENANCE_V1.to_string(),
+        predicate: SlsaProvenance {
+            build_definition: BuildDefinition {
+                build_type: PROVENACT_BUILD_TYPE.to_string(),
+                external_parameters: serde_json::json!({
+                    "build_system": provenance.build_system,
+                    "build_recipe_hash": provenance.build_recipe_hash,
+                }),
+                internal_parameters: None,
+                resolved_dependencies: vec![source],
+            },
+            run_details: RunDetails {
+                builder: Builder {
+                    id: builder_id.to_string(),
+                    version: BTreeMap::new(),
+                    builder_dependencies: Vec::new(),
+                },
+                metadata: None,
+                byproducts: Vec::new(),
+            },
+        },
+    })
+}
+
+/// DSSE v1 pre-authentication encoding.
+pub fn dsse_pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut out = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Verifies a DSSE envelope carrying an in-toto statement against trust anchors.
+///
+/// A signature with a non-empty `keyid` is checked only against anchor keys
+/// whose key id or signer id equals it; an empty `keyid` is checked against
+/// every anchor key. Only keys valid at UNIX time `at` count, and at least one
+/// signature must verify.
+pub fn verify_dsse_envelope(
+    envelope: &DsseEnvelope,
+    anchors: &TrustAnchors,
+    at: u64,
+) -> Result<VerifiedStatement, AttestationError> {
+    if envelope.payload_type != IN_TOTO_PAYLOAD_TYPE {
+        return Err(AttestationError::UnsupportedPayloadType(
+            envelope.payload_type.clone(),
+        ));
+    }
+    let payload = BASE64
+        .decode(&envelope.payload)
+        .map_err(|_| AttestationError::InvalidPayloadEncoding)?;
+    let pae = dsse_pae(&envelope.payload_type, &payload);
+
+    let mut signers = Vec::new();
+    for entry in &envelope.signatures {
+        for key in anchors.keys() {
+            if !entry.keyid.is_empty() && entry.keyid != key.key_id && entry.keyid != key.signer {
+                continue;
+            }
+            let trusted = key.status_at(at) == KeyStatus::Valid
+                && verify_ed25519(&key.public_key, &pae, &entry.sig);
+            if trusted && !signers.contains(&key.signer) {
+                signers.push(key.signer.clone());
+            }
+        }
+    }
+    if signers.is_empty() {
+        return Err(AttestationError::NoValidSignature);
+    }
+
+    let statement = serde_json::from_slice(&payload).map_err(SpecError::from)?;
+    Ok(VerifiedStatement { statement, signers })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn manifest() -> Manifest {
+        Manifest {
+            name: "echo.minimal".to_string(),
+            version: "0.1.0".to_string(),
+            entrypoint: "run".to_string(),
+            artifact: format!("sha256:{}", "a".repeat(64)),
+            capabilities: vec![],
+            signers: vec!["alice.dev".to_string()],
+        }
+    }
+
+    fn provenance() -> Provenance {
+        Provenance {
+            source: "https://github.com/opertus/provenact".to_string(),
+            commit: "8f9c1d2".to_string(),
+            build_system: "cargo".to_string(),
+            build_recipe_hash: format!("sha256:{}", "b".repeat(64)),
+        }
+    }
+
+    fn envelope(key: &SigningKey, keyid: &str, statement: &InTotoStatement) -> DsseEnvelope {
+        let payload = serde_json::to_vec(statement).unwrap();
+        let sig = key.sign(&dsse_pae(IN_TOTO_PAYLOAD_TYPE, &payload));
+        DsseEnvelope {
+            payload: BASE64.encode(&payload),
+            payload_type: IN_TOTO_PAYLOAD_TYPE.to_string(),
+            signatures: vec![DsseSignature {
+                keyid: keyid.to_string(),
+                sig: BASE64.encode(sig.to_bytes()),
+            }],
+        }
+    }
+
+    #[test]
+    fn provenance_round_trips_through_statement() {
+        let statement = statement_from_provenance(&provenance(), &manifest(), "ci").unwrap();
+        let raw = serde_json::to_string(&statement).unwrap();
+        let parsed: InTotoStatement = serde_json::from_str(&raw).unwrap();
+        assert_eq!(
+            provenance_from_statement(&parsed, &manifest()).unwrap(),
+            provenance()
+        );
+    }
+
+    #[test]
+    fn statement_subject_must_match_manifest_artifact() {
+        let statement = statement_from_provenance(&provenance(), &manifest(), "ci").unwrap();
+        let mut other = manifest();
+        other.artifact = format!("sha256:{}", "c".repeat(64));
+        assert!(matches!(
+            provenance_from_statement(&statement, &other),
+            Err(AttestationError::SubjectMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn foreign_statement_derives_build_recipe_hash() {
+        let raw = serde_json::json!({
+            "_type": IN_TOTO_STATEMENT_V1,
+            "subject": [{"name": "skill.wasm", "digest": {"sha256": "a".repeat(64)}}],
+            "predicateType": SLSA_PROVENANCE_V1,
+            "predicate": {
+                "buildDefinition": {
+                    "buildType": "https://slsa-framework.github.io/github-actions-buildtypes/workflow/v1",
+                    "externalParameters": {"workflow": {"path": ".github/workflows/release.yml"}},
+                    "resolvedDependencies": [{
+                        "uri": "git+https://github.com/opertus/provenact@refs/heads/main",
+                        "digest": {"gitCommit": "8f9c1d2"}
+                    }]
+                },
+                "runDetails": {"builder": {"id": "https://github.com/actions/runner"}}
+            }
+        });
+        let statement: InTotoStatement = serde_json::from_value(raw).unwrap();
+        let provenance = provenance_from_statement(&statement, &manifest()).unwrap();
+        assert_eq!(
+            provenance.build_system,
+            "https://slsa-framework.github.io/github-actions-buildtypes/workflow/v1"
+        );
+        assert_eq!(
+            provenance.build_recipe_hash,
+            sha256_prefixed(&to_jcs_bytes(&statement.predicate.build_definition).unwrap())
+        );
+    }
+
+    #[test]
+    fn dsse_envelope_requires_trusted_signature() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let raw = serde_json::json!({"alice.dev": BASE64.encode(key.verifying_key().to_bytes())});
+        let keys = TrustAnchors::from_bytes_unpinned(raw.to_string().as_bytes()).unwrap();
+        let statement = statement_from_provenance(&provenance(), &manifest(), "ci").unwrap();
+
+        let verified = verify_dsse_envelope(&envelope(&key, "", &statement), &keys, 0).unwrap();
+        assert_eq!(verified.signers, vec!["alice.dev".to_string()]);
+        assert_eq!(verified.statement, statement);
+
+        assert!(matches!(
+            verify_dsse_envelope(&envelope(&key, "bob.dev", &statement), &keys, 0),
+            Err(AttestationError::NoValidSignature)
+        ));
+
+        let mut tampered = envelope(&key, "alice.dev", &statement);
+        let mut other = statement.clone();
+        other.subject[0].name = Some("other".to_string());
+        tampered.payload = BASE64.encode(serde_json::to_vec(&other).unwrap());
+        assert!(matches!(
+            verify_dsse_envelope(&tampered, &keys, 0),
+            Err(AttestationError::NoValidSignature)
+        ));
+    }
+}
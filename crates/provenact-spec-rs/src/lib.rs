@@ -4,6 +4,124 @@ use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
 use url::Url;
 
+mod audit;
+mod capability;
+mod explain;
+mod install;
+mod intoto;
+mod manifest_v1;
+mod name_pattern;
+mod negotiation;
+mod net_entry;
+mod oci;
+mod pipeline;
+mod policy;
+mod policy_diff;
+mod policy_lint;
+mod policy_stack;
+mod receipt_v1;
+mod registry;
+mod sbom;
+mod signatures;
+mod signer_policy;
+mod signing;
+mod snapshot_builder;
+mod snapshot_diff;
+mod snapshot_v1;
+mod trust;
+
+pub use audit::{AuditDecision, AuditExecutionEvent, AUDIT_EVENT_SCHEMA_VERSION};
+pub use capability::{
+    evaluate_capability, net_limits, EntryMismatch, PathRejection, RequestRejection,
+    CAPABILITY_KINDS,
+};
+pub use explain::{
+    explain_capability, minimal_ceiling_entry, CapabilityExplanation, CeilingEntry,
+    EntryExplanation, ExplainCase, ExplainVector,
+};
+pub use install::{InstallIndex, InstallIndexEntry, InstallMeta, INSTALL_SCHEMA_VERSION};
+pub use intoto::{
+    dsse_pae, provenance_from_statement, statement_from_provenance, verify_dsse_envelope,
+    AttestationError, BuildDefinition, BuildMetadata, Builder, DsseEnvelope, DsseSignature,
+    InTotoStatement, ResourceDescriptor, RunDetails, SlsaProvenance, VerifiedStatement,
+    IN_TOTO_PAYLOAD_TYPE, IN_TOTO_STATEMENT_V1, PROVENACT_BUILD_TYPE, SLSA_PROVENANCE_V1,
+};
+pub use manifest_v1::{
+    downgrade_manifest, upgrade_manifest, AnyManifest, CompatibilityV1, EntrypointDescriptor,
+    EntrypointKind, EntrypointV1, ManifestDowngradeVector, ManifestFormatError, ManifestUpgrade,
+    ManifestUpgradeVector, ManifestV1, SchemaRef, MANIFEST_V1_SCHEMA_VERSION,
+};
+pub use name_pattern::{NamePattern, NamePatternError, NAME_PATTERN_POLICY_VERSION};
+pub use negotiation::{
+    negotiate, Incompatibility, NegotiatedProfile, NegotiationCase, NegotiationVector, Profile,
+    RuntimeCapabilities, NO_ADAPTER_PROFILE, UNVERSIONED_SCHEMA,
+};
+pub use net_entry::{is_valid_http_method, NetEntry, NetLimits, NetRule, NET_RULE_POLICY_VERSION};
+pub use oci::{
+    export_oci_layout, import_oci_layout, OciDescriptor, OciImageManifest, OciIndex,
+    OciLayoutError, OciReference, OciReferenceCase, OciReferenceError, OciReferenceVector,
+    OciSelector, OciTransfer, OCI_IMAGE_INDEX_MEDIA_TYPE, OCI_IMAGE_MANIFEST_MEDIA_TYPE,
+    OCI_REF_NAME_ANNOTATION, OCI_TITLE_ANNOTATION, PROVENACT_LOG_PROOF_MEDIA_TYPE,
+    PROVENACT_MANIFEST_MEDIA_TYPE, PROVENACT_PROVENANCE_MEDIA_TYPE,
+    PROVENACT_SIGNATURES_MEDIA_TYPE, PROVENACT_SKILL_ARTIFACT_TYPE, PROVENACT_WASM_MEDIA_TYPE,
+};
+pub use pipeline::{
+    InputMappingRule, InputSource, MapRule, OnError, Pipeline, PipelineEdge, PipelineNode,
+    PipelineSkillRef, Redaction, RedactionMode, ResourceLimits, RunPolicy,
+};
+pub use policy::{validate_policy, PolicyIssue};
+pub use policy_diff::{
+    diff_policies, ChangeImpact, ChangeKind, PolicyChange, PolicyDiff, PolicyDiffVector,
+};
+pub use policy_lint::{
+    lint_policy, ExpectedLintFinding, LintConfig, LintFinding, LintRule, LintSeverity,
+    PolicyLintCase, PolicyLintReport, PolicyLintVector,
+};
+pub use policy_stack::{
+    EffectivePolicy, PermissionCut, PolicyLayer, PolicyStack, PolicyStackCase, PolicyStackError,
+    PolicyStackVector,
+};
+pub use receipt_v1::{
+    ExecutionReceiptV1, ReceiptAttestation, ReceiptResult, ReceiptRuntime, ReceiptStatus,
+    RECEIPT_V1_SCHEMA_VERSION,
+};
+pub use registry::{
+    verify_signed_snapshot, ArtifactFetcher, FetchError, FileUrlFetcher, HttpClient, HttpFetcher,
+    LocalDirFetcher, ResolveError, ResolvedArtifact, SignedSnapshot, SignedSnapshotVector,
+    SnapshotResolveCase, SnapshotResolveVector, SnapshotResolver, SnapshotState, SnapshotTrust,
+    VersionedSnapshotResolver, MAX_SNAPSHOT_CLOCK_SKEW,
+};
+pub use sbom::{
+    evaluate_sbom_policy, parse_spdx, verify_sbom_artifact, PackageDenyRule, SbomError, SbomPolicy,
+    SbomViolation, SpdxChecksum, SpdxCreationInfo, SpdxDocument, SpdxExternalRef, SpdxPackage,
+    SpdxRelationship, SPDX_DOCUMENT_ID, SPDX_VERSION,
+};
+pub use signatures::{
+    verify_signatures, ExpectedRejection, RejectedSignature, SignatureError, SignatureVerification,
+    SignerTrustVector, VerifiedSignature,
+};
+pub use signer_policy::{
+    evaluate_signer_policy, validate_signer_rules, ExpectedUnmetRule, SignerPolicyCase,
+    SignerPolicyDenial, SignerPolicyVector, SignerRule, SignerRuleError, UnmetSignerRule,
+};
+pub use signing::{sign_manifest, Ed25519Signer, FileSigner, Signer, SigningError};
+#[cfg(unix)]
+pub use signing::{AgentSigner, AGENT_TIMEOUT};
+pub use snapshot_builder::{snapshot_entry_name, BuiltEntry, SnapshotBuildError, SnapshotBuilder};
+pub use snapshot_diff::{
+    diff_snapshots, ChangedSnapshotEntry, SnapshotDiff, SnapshotDiffEntry, SnapshotDiffVector,
+};
+pub use snapshot_v1::{
+    compute_snapshot_v1_hash, downgrade_snapshot, upgrade_snapshot, verify_snapshot_v1_hash,
+    AnySnapshot, RegistrySnapshotV1, SelectedVersion, SkillVersions, SnapshotFormatError,
+    SnapshotUpgradeVector, VersionQuery, VersionQueryCase, VersionQueryVector, VersionedEntry,
+    Yank, REGISTRY_SNAPSHOT_V1,
+};
+pub use trust::{
+    AnchorKey, KeyRevocation, KeyStatus, RevocationReason, SignatureRejection, TrustAnchorDocument,
+    TrustAnchorKey, TrustAnchors, TrustError, TRUST_ANCHORS_VERSION,
+};
+
 #[derive(Debug, thiserror::Error)]
 pub enum SpecError {
     #[error("invalid json: {0}")]
@@ -14,11 +132,13 @@ pub enum SpecError {
     InvalidSha256(String),
     #[error("invalid md5 format: {0}")]
     InvalidMd5(String),
+    #[error("invalid ed25519 public key: {0}")]
+    InvalidPublicKey(String),
     #[error("hash mismatch: expected={expected} actual={actual}")]
     HashMismatch { expected: String, actual: String },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Manifest {
     pub name: String,
@@ -29,6 +149,31 @@ pub struct Manifest {
     pub signers: Vec<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Provenance {
+    pub source: String,
+    pub commit: String,
+    pub build_system: String,
+    pub build_recipe_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Signatures {
+    pub artifact: String,
+    pub manifest_hash: String,
+    pub signatures: Vec<SignatureEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignatureEntry {
+    pub signer: String,
+    pub algorithm: String,
+    pub signature: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Policy {
@@ -37,6 +182,10 @@ pub struct Policy {
     pub trusted_signers: Vec<String>,
     #[serde(default)]
     pub capability_ceiling: CapabilityCeiling,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sbom: Option<SbomPolicy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signer_rules: Vec<SignerRule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -45,7 +194,7 @@ pub struct CapabilityCeiling {
     #[serde(default)]
     pub fs: FsCeiling,
     #[serde(default)]
-    pub net: Vec<String>,
+    pub net: Vec<NetEntry>,
     #[serde(default)]
     pub env: Vec<String>,
     #[serde(default)]
@@ -106,7 +255,7 @@ pub struct RegistrySnapshot {
     pub snapshot_hash: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SnapshotEntry {
     pub sha256: String,
@@ -126,15 +275,21 @@ pub struct CapabilityEvalVector {
 pub struct CapabilityCase {
     pub capability: Capability,
     pub expect: String,
+    /// For allowed `net.http` requests: the limits from [`net_limits`].
+    #[serde(default)]
+    pub expect_limits: Option<NetLimits>,
     #[serde(default)]
     pub note: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Capability {
     pub kind: String,
     pub value: String,
+    /// HTTP method of a `net.http` request; other kinds take none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
 }
 
 pub fn parse_json<T: for<'de> Deserialize<'de>>(raw: &str) -> Result<T, SpecError> {
@@ -159,7 +314,7 @@ pub fn validate_sha256_prefixed(value: &str) -> Result<(), SpecError> {
     Ok(())
 }
 
-fn validate_md5_hex(value: &str) -> Result<(), SpecError> {
+pub(crate) fn validate_md5_hex(value: &str) -> Result<(), SpecError> {
     if value.len() != 32 {
         return Err(SpecError::InvalidMd5(value.to_string()));
     }
@@ -172,6 +327,22 @@ fn validate_md5_hex(value: &str) -> Result<(), SpecError> {
     Ok(())
 }
 
+pub(crate) fn decode_ed25519_public_key(
+    id: &str,
+    encoded: &str,
+) -> Result<ed25519_dalek::VerifyingKey, SpecError> {
+    use base64::Engine;
+
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| SpecError::InvalidPublicKey(id.to_string()))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| SpecError::InvalidPublicKey(id.to_string()))?;
+    ed25519_dalek::VerifyingKey::from_bytes(&bytes)
+        .map_err(|_| SpecError::InvalidPublicKey(id.to_string()))
+}
+
 pub fn to_jcs_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, SpecError> {
     serde_jcs::to_vec(value).map_err(|_| SpecError::CanonicalJson)
 }
@@ -236,143 +407,26 @@ pub fn verify_snapshot_hash(snapshot: &RegistrySnapshot) -> Result<(), SpecError
     }
 }
 
-fn normalize_fs_path(path: &str) -> Option<String> {
-    if !path.starts_with('/') || path.contains('\0') {
-        return None;
-    }
-    let mut parts = Vec::new();
-    for segment in path.split('/') {
-        if segment.is_empty() {
-            continue;
-        }
-        if segment == "." {
-            return None;
-        }
-        if segment == ".." {
-            return None;
-        }
-        parts.push(segment);
-    }
-    Some(format!("/{}", parts.join("/")))
+pub(crate) fn normalize_fs_path(path: &str) -> Option<String> {
+    capability::check_fs_path(path).ok()
 }
 
-fn is_within_prefix(candidate: &str, prefix: &str) -> bool {
+pub(crate) fn is_within_prefix(candidate: &str, prefix: &str) -> bool {
     if prefix == "/" {
         return candidate.starts_with('/');
     }
     candidate == prefix || candidate.starts_with(&format!("{prefix}/"))
 }
 
-fn normalize_uri_path(path: &str) -> Option<String> {
-    let raw = if path.is_empty() { "/" } else { path };
-    if raw.contains('\\') || contains_pct_encoded_triplet(raw) {
-        return None;
-    }
-    normalize_fs_path(raw)
-}
-
-fn contains_pct_encoded_triplet(value: &str) -> bool {
-    value.as_bytes().windows(3).any(|window| {
-        window[0] == b'%' && window[1].is_ascii_hexdigit() && window[2].is_ascii_hexdigit()
-    })
+pub(crate) fn normalize_uri_path(path: &str) -> Option<String> {
+    capability::check_uri_path(path).ok()
 }
 
-fn net_uri_within_prefix(requested: &Url, allowed: &Url) -> bool {
-    if !requested.has_authority() || !allowed.has_authority() {
-        return false;
-    }
-    if requested.scheme() != allowed.scheme() {
-        return false;
-    }
-    if requested.host_str() != allowed.host_str() {
-        return false;
-    }
-    if requested.port_or_known_default() != allowed.port_or_known_default() {
-        return false;
-    }
-    if requested.username() != allowed.username() || requested.password() != allowed.password() {
-        return false;
-    }
-    if requested.fragment().is_some() || allowed.query().is_some() || allowed.fragment().is_some() {
-        return false;
-    }
-    let Some(requested_path) = normalize_uri_path(requested.path()) else {
-        return false;
-    };
-    let Some(allowed_path) = normalize_uri_path(allowed.path()) else {
-        return false;
-    };
-    is_within_prefix(&requested_path, &allowed_path)
-}
-
-pub fn evaluate_capability(policy: &Policy, capability: &Capability) -> bool {
-    match capability.kind.as_str() {
-        "exec" => capability.value == "true" && policy.capability_ceiling.exec,
-        "exec.safe" => !capability.value.is_empty() && policy.capability_ceiling.exec,
-        "time.now" => !capability.value.is_empty() && policy.capability_ceiling.time,
-        "random.bytes" => !capability.value.is_empty() && policy.capability_ceiling.random,
-        "env" => {
-            if !is_valid_env_name(&capability.value) {
-                return false;
-            }
-            policy
-                .capability_ceiling
-                .env
-                .iter()
-                .any(|x| x == &capability.value)
-        }
-        "net.http" => {
-            let Ok(requested) = Url::parse(&capability.value) else {
-                return false;
-            };
-            policy.capability_ceiling.net.iter().any(|allowed| {
-                Url::parse(allowed)
-                    .ok()
-                    .map(|prefix| net_uri_within_prefix(&requested, &prefix))
-                    .unwrap_or(false)
-            })
-        }
-        "fs.read" | "fs.write" => {
-            let value = match normalize_fs_path(&capability.value) {
-                Some(v) => v,
-                None => return false,
-            };
-            let allowed = if capability.kind == "fs.read" {
-                &policy.capability_ceiling.fs.read
-            } else {
-                &policy.capability_ceiling.fs.write
-            };
-            allowed.iter().any(|prefix| {
-                normalize_fs_path(prefix)
-                    .map(|p| is_within_prefix(&value, &p))
-                    .unwrap_or(false)
-            })
-        }
-        "kv.read" => {
-            policy.capability_ceiling.kv.read.iter().any(|item| {
-                !capability.value.is_empty() && (item == "*" || item == &capability.value)
-            })
-        }
-        "kv.write" => {
-            policy.capability_ceiling.kv.write.iter().any(|item| {
-                !capability.value.is_empty() && (item == "*" || item == &capability.value)
-            })
-        }
-        "queue.publish" => {
-            policy.capability_ceiling.queue.publish.iter().any(|item| {
-                !capability.value.is_empty() && (item == "*" || item == &capability.value)
-            })
-        }
-        "queue.consume" => {
-            policy.capability_ceiling.queue.consume.iter().any(|item| {
-                !capability.value.is_empty() && (item == "*" || item == &capability.value)
-            })
-        }
-        _ => false,
-    }
+pub(crate) fn net_uri_within_prefix(requested: &Url, allowed: &Url) -> bool {
+    capability::check_net_prefix(requested, allowed).is_ok()
 }
 
-fn is_valid_env_name(value: &str) -> bool {
+pub(crate) fn is_valid_env_name(value: &str) -> bool {
     let mut chars = value.chars();
     let Some(first) = chars.next() else {
         return false;
@@ -507,14 +561,18 @@ mod tests {
                 time: true,
                 ..CapabilityCeiling::default()
             },
+            sbom: None,
+            signer_rules: vec![],
         };
         let allowed = Capability {
             kind: "time.now".to_string(),
             value: "utc".to_string(),
+            method: None,
         };
         let denied = Capability {
             kind: "time.now".to_string(),
             value: "".to_string(),
+            method: None,
         };
         assert!(evaluate_capability(&policy, &allowed));
         assert!(!evaluate_capability(&policy, &denied));
@@ -529,14 +587,18 @@ mod tests {
                 env: vec!["HOME".to_string(), "PATH".to_string()],
                 ..CapabilityCeiling::default()
             },
+            sbom: None,
+            signer_rules: vec![],
         };
         let allowed = Capability {
             kind: "env".to_string(),
             value: "HOME".to_string(),
+            method: None,
         };
         let denied = Capability {
             kind: "env".to_string(),
             value: "home".to_string(),
+            method: None,
         };
         assert!(evaluate_capability(&policy, &allowed));
         assert!(!evaluate_capability(&policy, &denied));
@@ -548,13 +610,16 @@ mod tests {
             version: 1,
             trusted_signers: vec!["alice.dev".to_string()],
             capability_ceiling: CapabilityCeiling {
-                net: vec!["https://api.example.test/v1".to_string()],
+                net: vec!["https://api.example.test/v1".into()],
                 ..CapabilityCeiling::default()
             },
+            sbom: None,
+            signer_rules: vec![],
         };
         let escaped = Capability {
             kind: "net.http".to_string(),
             value: "https://api.example.test/v1/%2f..%2fadmin".to_string(),
+            method: None,
         };
         assert!(!evaluate_capability(&policy, &escaped));
     }
@@ -565,13 +630,16 @@ mod tests {
             version: 1,
             trusted_signers: vec!["alice.dev".to_string()],
             capability_ceiling: CapabilityCeiling {
-                net: vec!["https://api.example.test/v1".to_string()],
+                net: vec!["https://api.example.test/v1".into()],
                 ..CapabilityCeiling::default()
             },
+            sbom: None,
+            signer_rules: vec![],
         };
         let requested = Capability {
             kind: "net.http".to_string(),
             value: "https://api.example.test:443/v1/forecast".to_string(),
+            method: None,
         };
         assert!(evaluate_capability(&policy, &requested));
     }
@@ -588,10 +656,13 @@ mod tests {
                 },
                 ..CapabilityCeiling::default()
             },
+            sbom: None,
+            signer_rules: vec![],
         };
         let requested = Capability {
             kind: "fs.read".to_string(),
             value: "/tmp/./report.json".to_string(),
+            method: None,
         };
         assert!(!evaluate_capability(&policy, &requested));
     }
@@ -612,23 +683,29 @@ mod tests {
                 },
                 ..CapabilityCeiling::default()
             },
+            sbom: None,
+            signer_rules: vec![],
         };
 
         let kv_empty = Capability {
             kind: "kv.read".to_string(),
             value: String::new(),
+            method: None,
         };
         let queue_empty = Capability {
             kind: "queue.publish".to_string(),
             value: String::new(),
+            method: None,
         };
         let kv_non_empty = Capability {
             kind: "kv.read".to_string(),
             value: "user-profile".to_string(),
+            method: None,
         };
         let queue_non_empty = Capability {
             kind: "queue.publish".to_string(),
             value: "jobs".to_string(),
+            method: None,
         };
 
         assert!(!evaluate_capability(&policy, &kv_empty));
@@ -0,0 +1,352 @@
+//! Draft v1 skill manifests
+//! (`spec/skill-format/manifest.v1.experimental.schema.json`) and conversion
+//! to and from v0 `Manifest`.
+
+use crate::{validate_sha256_prefixed, Capability, Manifest, SpecError};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+pub const MANIFEST_V1_SCHEMA_VERSION: &str = "1.0.0-draft";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ManifestFormatError {
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("unsupported manifest schema_version {0:?}")]
+    UnsupportedSchemaVersion(String),
+    #[error("capability {index}: {message}")]
+    InvalidCapability { index: usize, message: String },
+    #[error("downgrade to v0 would lose: {}", .0.join(", "))]
+    LossyDowngrade(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestV1 {
+    pub schema_version: String,
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub version: String,
+    pub entrypoint: EntrypointV1,
+    pub artifact: String,
+    pub inputs_schema: SchemaRef,
+    pub outputs_schema: SchemaRef,
+    pub capabilities: Vec<Capability>,
+    pub signers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Map<String, Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compatibility: Option<CompatibilityV1>,
+}
+
+/// An export name (as in v0) or a WASI command/reactor descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EntrypointV1 {
+    Export(String),
+    Descriptor(EntrypointDescriptor),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntrypointDescriptor {
+    pub kind: EntrypointKind,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntrypointKind {
+    WasiCommand,
+    WasiReactor,
+}
+
+/// An embedded JSON Schema object or a schema URI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SchemaRef {
+    Embedded(Map<String, Value>),
+    Uri(String),
+}
+
+impl SchemaRef {
+    /// The empty schema `{}`, which accepts any document.
+    pub fn any() -> Self {
+        SchemaRef::Embedded(Map::new())
+    }
+
+    pub fn is_any(&self) -> bool {
+        matches!(self, SchemaRef::Embedded(map) if map.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CompatibilityV1 {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runtime_profiles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adapter_profiles: Vec<String>,
+}
+
+/// A manifest of either version, dispatched on `schema_version`: absent for
+/// v0, [`MANIFEST_V1_SCHEMA_VERSION`] for v1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnyManifest {
+    V0(Manifest),
+    V1(ManifestV1),
+}
+
+impl AnyManifest {
+    pub fn parse(raw: &str) -> Result<Self, ManifestFormatError> {
+        let value: Value = serde_json::from_str(raw).map_err(SpecError::from)?;
+        match value.get("schema_version") {
+            None => Ok(Self::V0(
+                serde_json::from_value(value).map_err(SpecError::from)?,
+            )),
+            Some(Value::String(v)) if v == MANIFEST_V1_SCHEMA_VERSION => Ok(Self::V1(
+                serde_json::from_value(value).map_err(SpecError::from)?,
+            )),
+            Some(other) => Err(ManifestFormatError::UnsupportedSchemaVersion(
+                other
+                    .as_str()
+                    .map_or_else(|| other.to_string(), str::to_string),
+            )),
+        }
+    }
+
+    pub fn artifact(&self) -> &str {
+        match self {
+            Self::V0(manifest) => &manifest.artifact,
+            Self::V1(manifest) => &manifest.artifact,
+        }
+    }
+
+    pub fn into_v1(self) -> Result<ManifestV1, ManifestFormatError> {
+        match self {
+            Self::V0(manifest) => Ok(upgrade_manifest(&manifest)?.manifest),
+            Self::V1(manifest) => Ok(manifest),
+        }
+    }
+}
+
+/// Result of [`upgrade_manifest`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestUpgrade {
+    pub manifest: ManifestV1,
+    /// v1 fields with no v0 source, in schema order.
+    pub synthesized: Vec<String>,
+}
+
+/// Deterministically upgrades a v0 manifest.
+///
+/// `id` is synthesized from `name`, and `inputs_schema`/`outputs_schema` as
+/// the empty schema `{}` since v0 declares no I/O contract. `provenance` and
+/// `compatibility` are left absent. The upgraded manifest has a different
+/// manifest hash, so v0 signatures do not carry over. Capabilities with an
+/// empty `kind` or `value`, which v0 tolerates but v1 does not, are rejected.
+pub fn upgrade_manifest(manifest: &Manifest) -> Result<ManifestUpgrade, ManifestFormatError> {
+    validate_sha256_prefixed(&manifest.artifact)?;
+    let capabilities = manifest
+        .capabilities
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let capability = serde_json::from_value::<Capability>(value.clone()).map_err(|e| {
+                ManifestFormatError::InvalidCapability {
+                    index,
+                    message: e.to_string(),
+                }
+            })?;
+            if capability.kind.is_empty() || capability.value.is_empty() {
+                return Err(ManifestFormatError::InvalidCapability {
+                    index,
+                    message: "kind and value must be non-empty in v1".to_string(),
+                });
+            }
+            Ok(capability)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ManifestUpgrade {
+        manifest: ManifestV1 {
+            schema_version: MANIFEST_V1_SCHEMA_VERSION.to_string(),
+            id: manifest.name.clone(),
+            name: Some(manifest.name.clone()),
+            version: manifest.version.clone(),
+            entrypoint: EntrypointV1::Export(manifest.entrypoint.clone()),
+            artifact: manifest.artifact.clone(),
+            inputs_schema: SchemaRef::any(),
+            outputs_schema: SchemaRef::any(),
+            capabilities,
+            signers: manifest.signers.clone(),
+            provenance: None,
+            compatibility: None,
+        },
+        synthesized: ["schema_version", "id", "inputs_schema", "outputs_schema"]
+            .map(str::to_string)
+            .to_vec(),
+    })
+}
+
+/// Downgrades to v0, failing with every field whose v1 meaning v0 cannot
+/// carry: an `id` differing from `name`, an entrypoint descriptor,
+/// non-empty I/O schemas, `provenance`, compatibility profiles, or capability
+/// methods.
+pub fn downgrade_manifest(manifest: &ManifestV1) -> Result<Manifest, ManifestFormatError> {
+    if manifest.schema_version != MANIFEST_V1_SCHEMA_VERSION {
+        return Err(ManifestFormatError::UnsupportedSchemaVersion(
+            manifest.schema_version.clone(),
+        ));
+    }
+    let name = manifest.name.as_deref().unwrap_or(&manifest.id);
+    let mut lost = Vec::new();
+    if manifest.id != name {
+        lost.push("id");
+    }
+    let entrypoint = match &manifest.entrypoint {
+        EntrypointV1::Export(export) => export.clone(),
+        EntrypointV1::Descriptor(_) => {
+            lost.push("entrypoint");
+            String::new()
+        }
+    };
+    if !manifest.inputs_schema.is_any() {
+        lost.push("inputs_schema");
+    }
+    if !manifest.outputs_schema.is_any() {
+        lost.push("outputs_schema");
+    }
+    if manifest.provenance.is_some() {
+        lost.push("provenance");
+    }
+    if manifest.capabilities.iter().any(|c| c.method.is_some()) {
+        lost.push("capabilities");
+    }
+    if manifest
+        .compatibility
+        .as_ref()
+        .is_some_and(|c| *c != CompatibilityV1::default())
+    {
+        lost.push("compatibility");
+    }
+    if !lost.is_empty() {
+        return Err(ManifestFormatError::LossyDowngrade(
+            lost.into_iter().map(str::to_string).collect(),
+        ));
+    }
+    Ok(Manifest {
+        name: name.to_string(),
+        version: manifest.version.clone(),
+        entrypoint,
+        artifact: manifest.artifact.clone(),
+        capabilities: manifest
+            .capabilities
+            .iter()
+            .map(|c| serde_json::json!({ "kind": c.kind, "value": c.value }))
+            .collect(),
+        signers: manifest.signers.clone(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestUpgradeVector {
+    pub name: String,
+    pub v0: Manifest,
+    #[serde(default)]
+    pub v1: Option<ManifestV1>,
+    #[serde(default)]
+    pub synthesized: Vec<String>,
+    /// Index of the v0 capability that blocks the upgrade.
+    #[serde(default)]
+    pub expect_invalid_capability: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestDowngradeVector {
+    pub name: String,
+    pub v1: ManifestV1,
+    #[serde(default)]
+    pub expect_v0: Option<Manifest>,
+    #[serde(default)]
+    pub expect_lost: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    #[test]
+    fn any_manifest_dispatches_on_schema_version() {
+        let v0 = include_str!("../../../test-vectors/good/minimal-zero-cap/manifest.json");
+        assert!(matches!(
+            AnyManifest::parse(v0).unwrap(),
+            AnyManifest::V0(_)
+        ));
+
+        let v1 =
+            include_str!("../../../test-vectors/skill-format/manifest-v1/good/basic-draft.json");
+        let AnyManifest::V1(parsed) = AnyManifest::parse(v1).unwrap() else {
+            panic!("expected v1");
+        };
+        assert_eq!(parsed.entrypoint, EntrypointV1::Export("run".into()));
+
+        let other = include_str!(
+            "../../../test-vectors/skill-format/manifest-v1/bad/unsupported_schema_version.json"
+        );
+        assert!(matches!(
+            AnyManifest::parse(other),
+            Err(ManifestFormatError::UnsupportedSchemaVersion(_))
+        ));
+    }
+
+    #[test]
+    fn upgrade_vector_round_trips() {
+        let v: ManifestUpgradeVector = parse_json(include_str!(
+            "../../../test-vectors/skill-format/manifest-upgrade/basic.json"
+        ))
+        .unwrap();
+        let upgrade = upgrade_manifest(&v.v0).unwrap();
+        assert_eq!(Some(upgrade.manifest.clone()), v.v1);
+        assert_eq!(upgrade.synthesized, v.synthesized);
+        assert_eq!(downgrade_manifest(&upgrade.manifest).unwrap(), v.v0);
+    }
+
+    #[test]
+    fn lossy_downgrade_lists_every_lost_field() {
+        let v: ManifestDowngradeVector = parse_json(include_str!(
+            "../../../test-vectors/skill-format/manifest-downgrade/lossy.json"
+        ))
+        .unwrap();
+        assert!(matches!(
+            downgrade_manifest(&v.v1),
+            Err(ManifestFormatError::LossyDowngrade(lost)) if lost == v.expect_lost
+        ));
+    }
+
+    #[test]
+    fn malformed_v0_capability_is_not_upgraded() {
+        let mut manifest: Manifest = parse_json(include_str!(
+            "../../../test-vectors/good/minimal-zero-cap/manifest.json"
+        ))
+        .unwrap();
+        manifest.capabilities = vec![serde_json::json!({"kind": "fs.read"})];
+        assert!(matches!(
+            upgrade_manifest(&manifest),
+            Err(ManifestFormatError::InvalidCapability { index: 0, .. })
+        ));
+
+        let v: ManifestUpgradeVector = parse_json(include_str!(
+            "../../../test-vectors/skill-format/manifest-upgrade/empty-capability-value.json"
+        ))
+        .unwrap();
+        assert!(matches!(
+            upgrade_manifest(&v.v0),
+            Err(ManifestFormatError::InvalidCapability { index, .. })
+                if Some(index) == v.expect_invalid_capability
+        ));
+    }
+}
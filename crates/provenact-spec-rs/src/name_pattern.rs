@@ -0,0 +1,260 @@
+//! Segment patterns for `kv` and `queue` ceilings in policy version 2
+//! (`spec/policy/policy.md` "Name Patterns").
+
+use std::fmt;
+
+/// The first policy version whose `kv` and `queue` entries are patterns.
+/// Earlier versions match entries exactly, with `"*"` allowing any name.
+pub const NAME_PATTERN_POLICY_VERSION: u64 = 2;
+
+const KV_SEPARATOR: char = '/';
+const QUEUE_SEPARATOR: char = '.';
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NamePatternError {
+    #[error("pattern is empty")]
+    Empty,
+    #[error("segment {0} is empty")]
+    EmptySegment(usize),
+    #[error("segment {0} has `*` before its end")]
+    MisplacedWildcard(usize),
+    #[error("segment {0} is `>` but is not the last segment")]
+    MisplacedRest(usize),
+}
+
+impl NamePatternError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Empty => "empty_pattern",
+            Self::EmptySegment(_) => "empty_segment",
+            Self::MisplacedWildcard(_) => "misplaced_wildcard",
+            Self::MisplacedRest(_) => "misplaced_rest",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `*`: any one non-empty segment.
+    Any,
+    /// `p*`: one segment that starts with `p` and is longer than it.
+    Prefix(String),
+}
+
+impl Segment {
+    fn matches(&self, part: &str) -> bool {
+        match self {
+            Self::Literal(literal) => part == literal,
+            Self::Any => !part.is_empty(),
+            Self::Prefix(prefix) => part.len() > prefix.len() && part.starts_with(prefix.as_str()),
+        }
+    }
+
+    fn covers(&self, inner: &Segment) -> bool {
+        match (self, inner) {
+            (Self::Any, _) => true,
+            (Self::Literal(outer), Self::Literal(inner)) => outer == inner,
+            (Self::Prefix(_), Self::Literal(inner)) => self.matches(inner),
+            (Self::Prefix(outer), Self::Prefix(inner)) => inner.starts_with(outer.as_str()),
+            _ => false,
+        }
+    }
+}
+
+/// A `kv` key or `queue` topic pattern. Names split into segments at `/`
+/// (`kv`) or `.` (`queue`); each pattern segment is a literal, `*` (any
+/// one segment), or `p*` (one segment starting with `p`), and a final `>`
+/// matches the non-empty rest of the name. The pattern `*` on its own
+/// matches every name, as in version 1. Matching never backtracks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamePattern {
+    source: String,
+    separator: char,
+    /// `None` for the bare `*`.
+    segments: Option<Vec<Segment>>,
+    rest: bool,
+}
+
+impl NamePattern {
+    pub fn kv(pattern: &str) -> Result<Self, NamePatternError> {
+        Self::parse(pattern, KV_SEPARATOR)
+    }
+
+    pub fn queue(pattern: &str) -> Result<Self, NamePatternError> {
+        Self::parse(pattern, QUEUE_SEPARATOR)
+    }
+
+    fn parse(pattern: &str, separator: char) -> Result<Self, NamePatternError> {
+        let mut out = Self {
+            source: pattern.to_string(),
+            separator,
+            segments: None,
+            rest: false,
+        };
+        if pattern.is_empty() {
+            return Err(NamePatternError::Empty);
+        }
+        if pattern == "*" {
+            return Ok(out);
+        }
+        let parts: Vec<&str> = pattern.split(separator).collect();
+        let mut segments = Vec::with_capacity(parts.len());
+        for (index, part) in parts.iter().enumerate() {
+            if part.is_empty() {
+                return Err(NamePatternError::EmptySegment(index));
+            }
+            if *part == ">" {
+                if index + 1 != parts.len() {
+                    return Err(NamePatternError::MisplacedRest(index));
+                }
+                out.rest = true;
+                continue;
+            }
+            let segment = match part.strip_suffix('*') {
+                Some("") => Segment::Any,
+                Some(prefix) if !prefix.contains('*') => Segment::Prefix(prefix.to_string()),
+                None if !part.contains('*') => Segment::Literal(part.to_string()),
+                _ => return Err(NamePatternError::MisplacedWildcard(index)),
+            };
+            segments.push(segment);
+        }
+        out.segments = Some(segments);
+        Ok(out)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the pattern matches exactly one name, itself.
+    pub fn is_exact(&self) -> bool {
+        !self.rest
+            && self.segments.as_ref().is_some_and(|segments| {
+                segments
+                    .iter()
+                    .all(|segment| matches!(segment, Segment::Literal(_)))
+            })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let Some(segments) = &self.segments else {
+            return true;
+        };
+        let parts: Vec<&str> = name.split(self.separator).collect();
+        let arity_ok = if self.rest {
+            // The rest must be non-empty: more parts, and not one empty one.
+            parts.len() > segments.len()
+                && !(parts.len() == segments.len() + 1 && parts[segments.len()].is_empty())
+        } else {
+            parts.len() == segments.len()
+        };
+        arity_ok
+            && segments
+                .iter()
+                .zip(&parts)
+                .all(|(segment, part)| segment.matches(part))
+    }
+
+    /// Whether every name `inner` matches is also matched by `self`.
+    pub fn covers(&self, inner: &NamePattern) -> bool {
+        if self.separator != inner.separator {
+            return false;
+        }
+        let Some(outer_segments) = &self.segments else {
+            return true;
+        };
+        let Some(inner_segments) = &inner.segments else {
+            // Only a bare `>` matches every (non-empty) name.
+            return outer_segments.is_empty() && self.rest;
+        };
+        let arity_ok = match (self.rest, inner.rest) {
+            (false, false) => outer_segments.len() == inner_segments.len(),
+            (false, true) => false,
+            (true, true) => inner_segments.len() >= outer_segments.len(),
+            // Pattern segments are never empty, so any extra one is a
+            // non-empty rest.
+            (true, false) => inner_segments.len() > outer_segments.len(),
+        };
+        arity_ok
+            && outer_segments
+                .iter()
+                .zip(inner_segments)
+                .all(|(outer, inner)| outer.covers(inner))
+    }
+}
+
+impl fmt::Display for NamePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// [`NamePattern::covers`] over unparsed `kv` patterns; invalid patterns
+/// cover nothing.
+pub(crate) fn kv_covers(outer: &str, inner: &str) -> bool {
+    matches!((NamePattern::kv(outer), NamePattern::kv(inner)), (Ok(o), Ok(i)) if o.covers(&i))
+}
+
+pub(crate) fn queue_covers(outer: &str, inner: &str) -> bool {
+    matches!((NamePattern::queue(outer), NamePattern::queue(inner)), (Ok(o), Ok(i)) if o.covers(&i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_match_at_separator_boundaries() {
+        let cache = NamePattern::kv("cache/*").unwrap();
+        assert!(cache.matches("cache/a"));
+        assert!(!cache.matches("cache2/a"));
+        assert!(!cache.matches("cache/"));
+        assert!(!cache.matches("cache/a/b"));
+        let users = NamePattern::kv("cache/user-*").unwrap();
+        assert!(users.matches("cache/user-42"));
+        assert!(!users.matches("cache/user-"));
+        assert!(!users.matches("cache/users/1"));
+        let jobs = NamePattern::queue("jobs.>").unwrap();
+        assert!(jobs.matches("jobs.eu.created"));
+        assert!(!jobs.matches("jobs"));
+        assert!(!jobs.matches("jobs."));
+        assert!(!jobs.matches("jobsx.a"));
+        assert!(NamePattern::queue(">").unwrap().matches("anything"));
+        assert!(NamePattern::kv("*").unwrap().matches("a/b/c"));
+    }
+
+    #[test]
+    fn malformed_patterns_are_rejected() {
+        for (pattern, code) in [
+            ("", "empty_pattern"),
+            ("cache//x", "empty_segment"),
+            ("cache/", "empty_segment"),
+            ("ca*che/x", "misplaced_wildcard"),
+            ("cache/**", "misplaced_wildcard"),
+            ("cache/>/x", "misplaced_rest"),
+        ] {
+            assert_eq!(
+                NamePattern::kv(pattern).unwrap_err().code(),
+                code,
+                "{pattern}"
+            );
+        }
+        assert!(NamePattern::queue("cache/").is_ok());
+    }
+
+    #[test]
+    fn coverage_follows_matching() {
+        assert!(kv_covers("cache/>", "cache/user-*"));
+        assert!(kv_covers("cache/>", "cache/a/>"));
+        assert!(!kv_covers("cache/>", "cache/>/x"));
+        assert!(kv_covers("cache/*", "cache/user-*"));
+        assert!(kv_covers("cache/u*", "cache/user-*"));
+        assert!(!kv_covers("cache/user-*", "cache/u*"));
+        assert!(!kv_covers("cache/*", "cache/>"));
+        assert!(!kv_covers("cache/*/x", "cache/a/*"));
+        assert!(kv_covers(">", "*"));
+        assert!(kv_covers("*", "cache/>"));
+        assert!(!queue_covers("jobs.*", "jobs"));
+    }
+}
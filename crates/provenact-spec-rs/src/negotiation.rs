@@ -0,0 +1,396 @@
+//! Runtime/adapter compatibility negotiation (`spec/compatibility.md`).
+
+use crate::{downgrade_manifest, AnyManifest, EntrypointKind, EntrypointV1, ManifestFormatError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// `schema_version` token for documents that predate versioning (v0
+/// manifests and receipts carry no `schema_version` field).
+pub const UNVERSIONED_SCHEMA: &str = "v0";
+
+/// Adapter profile meaning "invoked directly, no adapter".
+pub const NO_ADAPTER_PROFILE: &str = "none";
+
+/// What a runtime supports, in preference order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeCapabilities {
+    pub runtime: String,
+    /// Host ABI version (semver).
+    pub host_abi: String,
+    pub manifest_schema_versions: Vec<String>,
+    pub receipt_schema_versions: Vec<String>,
+    /// Profiles as `<name>/v<major>.<minor>`; the first is the default.
+    pub runtime_profiles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adapter_profiles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entrypoint_kinds: Vec<EntrypointKind>,
+}
+
+/// Outcome of [`negotiate`]: what the runtime will use for this manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NegotiatedProfile {
+    pub manifest_schema_version: String,
+    pub receipt_schema_version: String,
+    /// Advertised runtime profile to record as receipt `runtime.profile`.
+    pub runtime_profile: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adapter_profile: Option<String>,
+    pub host_abi: String,
+    /// Fallbacks taken, e.g. a v1 manifest downgraded to v0.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub downgrades: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Incompatibility {
+    #[error("runtime capabilities are invalid: {0}")]
+    InvalidCapabilities(String),
+    #[error("invalid profile {0:?}, expected <name>/v<major>[.<minor>]")]
+    InvalidProfile(String),
+    #[error("manifest schema {version} is not supported (runtime supports {supported:?}){}", lossy_suffix(.lost))]
+    UnsupportedManifestSchema {
+        version: String,
+        supported: Vec<String>,
+        /// Fields that blocked a downgrade to a supported version.
+        lost: Vec<String>,
+    },
+    #[error("runtime emits no receipt schema")]
+    NoReceiptSchema,
+    #[error("entrypoint kind {kind} is not supported (runtime supports {supported:?})")]
+    UnsupportedEntrypoint {
+        kind: String,
+        supported: Vec<String>,
+    },
+    #[error("no runtime profile in {requested:?} is satisfied by {advertised:?}")]
+    NoCommonRuntimeProfile {
+        requested: Vec<String>,
+        advertised: Vec<String>,
+    },
+    #[error("no adapter profile in {requested:?} is satisfied by {advertised:?}")]
+    NoCommonAdapterProfile {
+        requested: Vec<String>,
+        advertised: Vec<String>,
+    },
+}
+
+fn lossy_suffix(lost: &[String]) -> String {
+    if lost.is_empty() {
+        String::new()
+    } else {
+        format!("; downgrade would lose {}", lost.join(", "))
+    }
+}
+
+impl Incompatibility {
+    /// Stable error code used by `test-vectors/compatibility/`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Incompatibility::InvalidCapabilities(_) => "invalid_capabilities",
+            Incompatibility::InvalidProfile(_) => "invalid_profile",
+            Incompatibility::UnsupportedManifestSchema { .. } => "unsupported_manifest_schema",
+            Incompatibility::NoReceiptSchema => "no_receipt_schema",
+            Incompatibility::UnsupportedEntrypoint { .. } => "unsupported_entrypoint",
+            Incompatibility::NoCommonRuntimeProfile { .. } => "no_common_runtime_profile",
+            Incompatibility::NoCommonAdapterProfile { .. } => "no_common_adapter_profile",
+        }
+    }
+}
+
+/// `<name>/v<major>[.<minor>]`; a missing minor is `0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub major: u64,
+    pub minor: u64,
+}
+
+impl Profile {
+    pub fn parse(profile: &str) -> Result<Self, Incompatibility> {
+        let invalid = || Incompatibility::InvalidProfile(profile.to_string());
+        let (name, version) = profile.split_once("/v").ok_or_else(invalid)?;
+        let name_ok = name
+            .bytes()
+            .next()
+            .is_some_and(|b| b.is_ascii_alphanumeric())
+            && name.bytes().all(|b| {
+                b.is_ascii_lowercase() || b.is_ascii_digit() || matches!(b, b'.' | b'_' | b'-')
+            });
+        let number = |s: &str| {
+            (!s.is_empty()
+                && s.bytes().all(|b| b.is_ascii_digit())
+                && (s == "0" || !s.starts_with('0')))
+            .then(|| s.parse::<u64>().ok())
+            .flatten()
+        };
+        let (major, minor) = match version.split_once('.') {
+            Some((major, minor)) => (number(major), number(minor)),
+            None => (number(version), Some(0)),
+        };
+        match (name_ok, major, minor) {
+            (true, Some(major), Some(minor)) => Ok(Self {
+                name: name.to_string(),
+                major,
+                minor,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// An advertised profile satisfies a request with the same name and
+    /// major version and at least the requested minor version.
+    pub fn satisfies(&self, requested: &Profile) -> bool {
+        self.name == requested.name
+            && self.major == requested.major
+            && self.minor >= requested.minor
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/v{}.{}", self.name, self.major, self.minor)
+    }
+}
+
+impl RuntimeCapabilities {
+    pub fn validate(&self) -> Result<(), Incompatibility> {
+        if semver::Version::parse(&self.host_abi).is_err() {
+            return Err(Incompatibility::InvalidCapabilities(format!(
+                "host_abi {:?} is not semver",
+                self.host_abi
+            )));
+        }
+        if self.runtime_profiles.is_empty() {
+            return Err(Incompatibility::InvalidCapabilities(
+                "no runtime profiles".to_string(),
+            ));
+        }
+        for profile in &self.runtime_profiles {
+            Profile::parse(profile)?;
+        }
+        for profile in &self.adapter_profiles {
+            if profile != NO_ADAPTER_PROFILE {
+                Profile::parse(profile)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Picks the first requested profile (manifest preference) satisfied by any
+/// advertised profile (runtime preference) and returns the advertised one.
+/// `none` is only meaningful for adapters.
+fn select_profile<'a>(
+    requested: &[String],
+    advertised: &'a [String],
+    adapter: bool,
+) -> Result<Option<&'a str>, Incompatibility> {
+    let advertised_parsed = advertised
+        .iter()
+        .filter(|p| *p != NO_ADAPTER_PROFILE)
+        .map(|p| Ok((p.as_str(), Profile::parse(p)?)))
+        .collect::<Result<Vec<_>, Incompatibility>>()?;
+    for want in requested {
+        if adapter && want == NO_ADAPTER_PROFILE {
+            return Ok(Some(NO_ADAPTER_PROFILE));
+        }
+        let want = Profile::parse(want)?;
+        if let Some((raw, _)) = advertised_parsed
+            .iter()
+            .find(|(_, have)| have.satisfies(&want))
+        {
+            return Ok(Some(raw));
+        }
+    }
+    Ok(None)
+}
+
+/// Negotiates how `runtime` will execute `manifest`.
+///
+/// A v1 manifest on a runtime without v1 support is downgraded to v0 when
+/// that loses nothing (`downgrade_manifest`). A manifest without runtime
+/// profiles gets the runtime's default (first) profile; a manifest without
+/// adapter profiles gets no adapter. Every fallback is listed in
+/// `downgrades`.
+pub fn negotiate(
+    runtime: &RuntimeCapabilities,
+    manifest: &AnyManifest,
+) -> Result<NegotiatedProfile, Incompatibility> {
+    runtime.validate()?;
+    let supports = |version: &str| {
+        runtime
+            .manifest_schema_versions
+            .iter()
+            .any(|v| v == version)
+    };
+    let mut downgrades = Vec::new();
+
+    let (manifest_schema_version, v1) = match manifest {
+        AnyManifest::V0(_) if supports(UNVERSIONED_SCHEMA) => {
+            (UNVERSIONED_SCHEMA.to_string(), None)
+        }
+        AnyManifest::V1(v1) if supports(&v1.schema_version) => {
+            (v1.schema_version.clone(), Some(v1))
+        }
+        AnyManifest::V1(v1) if supports(UNVERSIONED_SCHEMA) => match downgrade_manifest(v1) {
+            Ok(_) => {
+                downgrades.push(format!(
+                    "manifest: {} downgraded to {UNVERSIONED_SCHEMA}",
+                    v1.schema_version
+                ));
+                (UNVERSIONED_SCHEMA.to_string(), None)
+            }
+            Err(ManifestFormatError::LossyDowngrade(lost)) => {
+                return Err(Incompatibility::UnsupportedManifestSchema {
+                    version: v1.schema_version.clone(),
+                    supported: runtime.manifest_schema_versions.clone(),
+                    lost,
+                })
+            }
+            Err(err) => {
+                return Err(Incompatibility::UnsupportedManifestSchema {
+                    version: format!("{} ({err})", v1.schema_version),
+                    supported: runtime.manifest_schema_versions.clone(),
+                    lost: vec![],
+                })
+            }
+        },
+        other => {
+            return Err(Incompatibility::UnsupportedManifestSchema {
+                version: match other {
+                    AnyManifest::V0(_) => UNVERSIONED_SCHEMA.to_string(),
+                    AnyManifest::V1(v1) => v1.schema_version.clone(),
+                },
+                supported: runtime.manifest_schema_versions.clone(),
+                lost: vec![],
+            })
+        }
+    };
+
+    let receipt_schema_version = runtime
+        .receipt_schema_versions
+        .first()
+        .ok_or(Incompatibility::NoReceiptSchema)?
+        .clone();
+
+    if let Some(EntrypointV1::Descriptor(descriptor)) = v1.map(|m| &m.entrypoint) {
+        if !runtime.entrypoint_kinds.contains(&descriptor.kind) {
+            let name = |kind: &EntrypointKind| {
+                serde_json::to_value(kind)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default()
+            };
+            return Err(Incompatibility::UnsupportedEntrypoint {
+                kind: name(&descriptor.kind),
+                supported: runtime.entrypoint_kinds.iter().map(name).collect(),
+            });
+        }
+    }
+
+    let compatibility = v1.and_then(|m| m.compatibility.as_ref());
+    let requested_runtime = compatibility.map_or(&[][..], |c| &c.runtime_profiles[..]);
+    let runtime_profile = if requested_runtime.is_empty() {
+        downgrades.push("runtime_profile: none requested, using runtime default".to_string());
+        runtime.runtime_profiles[0].clone()
+    } else {
+        select_profile(requested_runtime, &runtime.runtime_profiles, false)?
+            .ok_or_else(|| Incompatibility::NoCommonRuntimeProfile {
+                requested: requested_runtime.to_vec(),
+                advertised: runtime.runtime_profiles.clone(),
+            })?
+            .to_string()
+    };
+
+    let requested_adapter = compatibility.map_or(&[][..], |c| &c.adapter_profiles[..]);
+    let adapter_profile = if requested_adapter.is_empty() {
+        None
+    } else {
+        Some(
+            select_profile(requested_adapter, &runtime.adapter_profiles, true)?
+                .ok_or_else(|| Incompatibility::NoCommonAdapterProfile {
+                    requested: requested_adapter.to_vec(),
+                    advertised: runtime.adapter_profiles.clone(),
+                })?
+                .to_string(),
+        )
+    };
+
+    Ok(NegotiatedProfile {
+        manifest_schema_version,
+        receipt_schema_version,
+        runtime_profile,
+        adapter_profile,
+        host_abi: runtime.host_abi.clone(),
+        downgrades,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NegotiationVector {
+    pub name: String,
+    pub runtime: RuntimeCapabilities,
+    pub cases: Vec<NegotiationCase>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NegotiationCase {
+    pub name: String,
+    pub manifest: serde_json::Value,
+    #[serde(default)]
+    pub expect: Option<NegotiatedProfile>,
+    #[serde(default)]
+    pub expect_error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    #[test]
+    fn profile_matching_requires_same_major_and_enough_minor() {
+        let have = Profile::parse("provenact-runtime/v1.3").unwrap();
+        assert!(have.satisfies(&Profile::parse("provenact-runtime/v1").unwrap()));
+        assert!(have.satisfies(&Profile::parse("provenact-runtime/v1.3").unwrap()));
+        assert!(!have.satisfies(&Profile::parse("provenact-runtime/v1.4").unwrap()));
+        assert!(!have.satisfies(&Profile::parse("provenact-runtime/v2").unwrap()));
+        assert!(!have.satisfies(&Profile::parse("other-runtime/v1").unwrap()));
+        for bad in [
+            "provenact-runtime",
+            "Runtime/v1",
+            "r/v01",
+            "r/v1.",
+            "/v1",
+            "r/v1.2.3",
+        ] {
+            assert!(Profile::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn negotiation_vectors_match() {
+        for raw in [
+            include_str!("../../../test-vectors/compatibility/v1-runtime.json"),
+            include_str!("../../../test-vectors/compatibility/v0-only-runtime.json"),
+        ] {
+            let vector: NegotiationVector = parse_json(raw).unwrap();
+            for case in vector.cases {
+                let manifest = AnyManifest::parse(&case.manifest.to_string()).unwrap();
+                match (
+                    negotiate(&vector.runtime, &manifest),
+                    &case.expect,
+                    &case.expect_error,
+                ) {
+                    (Ok(got), Some(expect), None) => assert_eq!(&got, expect, "{}", case.name),
+                    (Err(err), None, Some(code)) => assert_eq!(err.code(), code, "{}", case.name),
+                    (got, _, _) => panic!("{}: unexpected {got:?}", case.name),
+                }
+            }
+        }
+    }
+}
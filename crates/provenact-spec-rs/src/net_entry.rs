@@ -0,0 +1,295 @@
+//! Structured `net` ceiling entries in policy version 2: a URI prefix with
+//! allowed methods and request limits (`spec/policy/policy.md` "Net Rules").
+
+use crate::policy::normalize_net_prefix;
+use crate::{net_uri_within_prefix, normalize_uri_path};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// The first policy version whose `net` entries may be [`NetRule`]s.
+pub const NET_RULE_POLICY_VERSION: u64 = 2;
+
+/// One `capability_ceiling.net` entry. A plain prefix allows any method
+/// without limits, as in version 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NetEntry {
+    Prefix(String),
+    Rule(NetRule),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetRule {
+    pub prefix: String,
+    /// Allowed HTTP methods; empty allows any method.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Request limits of a `net` entry; `None` is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+impl NetLimits {
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether every request within `inner` is also within `self`.
+    pub fn covers(&self, inner: &NetLimits) -> bool {
+        let covers = |outer: Option<u64>, inner: Option<u64>| match (outer, inner) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(outer), Some(inner)) => inner <= outer,
+        };
+        covers(self.max_request_bytes, inner.max_request_bytes)
+            && covers(self.max_response_bytes, inner.max_response_bytes)
+            && covers(self.timeout_ms, inner.timeout_ms)
+    }
+
+    /// The looser of each limit: what a request gets when several entries
+    /// allow it.
+    pub(crate) fn loosest(self, other: NetLimits) -> NetLimits {
+        let max = |a: Option<u64>, b: Option<u64>| a.zip(b).map(|(a, b)| a.max(b));
+        NetLimits {
+            max_request_bytes: max(self.max_request_bytes, other.max_request_bytes),
+            max_response_bytes: max(self.max_response_bytes, other.max_response_bytes),
+            timeout_ms: max(self.timeout_ms, other.timeout_ms),
+        }
+    }
+
+    /// The tighter of each limit: what two entries both allow.
+    pub(crate) fn tightest(self, other: NetLimits) -> NetLimits {
+        let min = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        NetLimits {
+            max_request_bytes: min(self.max_request_bytes, other.max_request_bytes),
+            max_response_bytes: min(self.max_response_bytes, other.max_response_bytes),
+            timeout_ms: min(self.timeout_ms, other.timeout_ms),
+        }
+    }
+}
+
+/// An HTTP method as written in requests and rules: uppercase ASCII letters,
+/// compared exactly.
+pub fn is_valid_http_method(method: &str) -> bool {
+    !method.is_empty() && method.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+/// `normalize_net_prefix` validates; the canonical prefix keeps the URL
+/// serialization (lowercase host, default port omitted) with a normalized
+/// path.
+fn canonical_net_prefix(prefix: &str) -> Option<String> {
+    normalize_net_prefix(prefix).ok()?;
+    let mut url = Url::parse(prefix).ok()?;
+    let path = normalize_uri_path(url.path())?;
+    url.set_path(&path);
+    Some(url.to_string())
+}
+
+fn net_prefix_covers(outer: &str, inner: &str) -> bool {
+    match (Url::parse(outer), Url::parse(inner)) {
+        (Ok(outer), Ok(inner)) => net_uri_within_prefix(&inner, &outer),
+        _ => false,
+    }
+}
+
+impl From<&str> for NetEntry {
+    fn from(prefix: &str) -> Self {
+        Self::Prefix(prefix.to_string())
+    }
+}
+
+impl NetEntry {
+    /// A plain prefix when there are no methods and no limits.
+    pub fn new(prefix: String, methods: Vec<String>, limits: NetLimits) -> Self {
+        if methods.is_empty() && limits.is_unlimited() {
+            return Self::Prefix(prefix);
+        }
+        Self::Rule(NetRule {
+            prefix,
+            methods,
+            max_request_bytes: limits.max_request_bytes,
+            max_response_bytes: limits.max_response_bytes,
+            timeout_ms: limits.timeout_ms,
+        })
+    }
+
+    pub fn prefix(&self) -> &str {
+        match self {
+            Self::Prefix(prefix) => prefix,
+            Self::Rule(rule) => &rule.prefix,
+        }
+    }
+
+    pub fn methods(&self) -> &[String] {
+        match self {
+            Self::Prefix(_) => &[],
+            Self::Rule(rule) => &rule.methods,
+        }
+    }
+
+    pub fn limits(&self) -> NetLimits {
+        match self {
+            Self::Prefix(_) => NetLimits::default(),
+            Self::Rule(rule) => NetLimits {
+                max_request_bytes: rule.max_request_bytes,
+                max_response_bytes: rule.max_response_bytes,
+                timeout_ms: rule.timeout_ms,
+            },
+        }
+    }
+
+    /// A request without a method only matches entries that allow any
+    /// method.
+    pub fn allows_method(&self, method: Option<&str>) -> bool {
+        let methods = self.methods();
+        methods.is_empty() || method.is_some_and(|method| methods.iter().any(|m| m == method))
+    }
+
+    /// The entry with a canonical prefix and sorted, deduplicated methods;
+    /// `None` when it can never match. Invalid methods never match a
+    /// request, so they are dropped.
+    pub(crate) fn canonical(&self) -> Option<NetEntry> {
+        let prefix = canonical_net_prefix(self.prefix())?;
+        let mut methods: Vec<String> = self
+            .methods()
+            .iter()
+            .filter(|method| is_valid_http_method(method))
+            .cloned()
+            .collect();
+        if methods.is_empty() && !self.methods().is_empty() {
+            return None;
+        }
+        methods.sort();
+        methods.dedup();
+        Some(Self::new(prefix, methods, self.limits()))
+    }
+
+    /// Whether every request `inner` allows is also allowed by `self`, with
+    /// limits at least as loose.
+    pub(crate) fn covers(&self, inner: &NetEntry) -> bool {
+        let methods_covered = self.methods().is_empty()
+            || (!inner.methods().is_empty()
+                && inner.methods().iter().all(|m| self.methods().contains(m)));
+        methods_covered
+            && self.limits().covers(&inner.limits())
+            && net_prefix_covers(self.prefix(), inner.prefix())
+    }
+
+    /// One entry allowing what both allow when their prefixes nest: the
+    /// narrower prefix, the common methods, and the tighter limits. `None`
+    /// when the prefixes or methods are disjoint.
+    pub(crate) fn overlap(&self, other: &NetEntry) -> Option<NetEntry> {
+        let prefix = if net_prefix_covers(self.prefix(), other.prefix()) {
+            other.prefix()
+        } else if net_prefix_covers(other.prefix(), self.prefix()) {
+            self.prefix()
+        } else {
+            return None;
+        };
+        let methods: Vec<String> = match (self.methods(), other.methods()) {
+            ([], methods) | (methods, []) => methods.to_vec(),
+            (left, right) => {
+                let common: Vec<String> =
+                    left.iter().filter(|m| right.contains(m)).cloned().collect();
+                if common.is_empty() {
+                    return None;
+                }
+                common
+            }
+        };
+        Some(Self::new(
+            prefix.to_string(),
+            methods,
+            self.limits().tightest(other.limits()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(prefix: &str, methods: &[&str], max_request_bytes: Option<u64>) -> NetEntry {
+        NetEntry::new(
+            prefix.to_string(),
+            methods.iter().map(|m| m.to_string()).collect(),
+            NetLimits {
+                max_request_bytes,
+                ..NetLimits::default()
+            },
+        )
+    }
+
+    #[test]
+    fn plain_strings_and_rules_deserialize_side_by_side() {
+        let entries: Vec<NetEntry> = serde_json::from_value(serde_json::json!([
+            "https://a.test/v1",
+            {"prefix": "https://a.test/v2", "methods": ["GET"], "timeout_ms": 500}
+        ]))
+        .unwrap();
+        assert_eq!(entries[0], NetEntry::Prefix("https://a.test/v1".into()));
+        assert_eq!(entries[1].methods(), ["GET"]);
+        assert_eq!(entries[1].limits().timeout_ms, Some(500));
+        assert!(serde_json::from_value::<NetEntry>(
+            serde_json::json!({"prefix": "https://a.test", "verbs": ["GET"]})
+        )
+        .is_err());
+        assert_eq!(
+            rule("https://a.test", &[], None),
+            NetEntry::Prefix("https://a.test".into())
+        );
+    }
+
+    #[test]
+    fn methods_and_limits_narrow_coverage() {
+        let get = rule("https://a.test/v1", &["GET"], Some(1024));
+        let any = NetEntry::Prefix("https://a.test".into());
+        assert!(any.covers(&get));
+        assert!(!get.covers(&any));
+        assert!(rule("https://a.test", &["GET", "POST"], Some(2048)).covers(&get));
+        assert!(!rule("https://a.test", &["GET"], Some(512)).covers(&get));
+        assert!(get.allows_method(Some("GET")));
+        assert!(!get.allows_method(Some("POST")));
+        assert!(!get.allows_method(None));
+        assert!(any.allows_method(None));
+    }
+
+    #[test]
+    fn overlap_intersects_methods_and_tightens_limits() {
+        let left = rule("https://a.test", &["GET", "POST"], Some(4096));
+        let right = rule("https://a.test/v1", &["POST", "PUT"], None);
+        assert_eq!(
+            left.overlap(&right),
+            Some(rule("https://a.test/v1", &["POST"], Some(4096)))
+        );
+        assert_eq!(left.overlap(&rule("https://a.test", &["PUT"], None)), None);
+        assert_eq!(left.overlap(&rule("https://b.test", &[], None)), None);
+        assert_eq!(
+            NetLimits {
+                timeout_ms: Some(10),
+                ..NetLimits::default()
+            }
+            .loosest(NetLimits::default()),
+            NetLimits::default()
+        );
+    }
+}
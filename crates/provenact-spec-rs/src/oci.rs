@@ -0,0 +1,751 @@
+//! OCI registry references and OCI image layout transport for skill bundles
+//! (`spec/oci.md`).
+
+use crate::{sha256_prefixed, validate_sha256_prefixed, Manifest};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub const OCI_IMAGE_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+pub const OCI_IMAGE_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+pub const PROVENACT_SKILL_ARTIFACT_TYPE: &str = "application/vnd.provenact.skill.v1";
+pub const PROVENACT_MANIFEST_MEDIA_TYPE: &str = "application/vnd.provenact.skill.manifest.v1+json";
+pub const PROVENACT_WASM_MEDIA_TYPE: &str = "application/vnd.provenact.skill.wasm.v1";
+pub const PROVENACT_PROVENANCE_MEDIA_TYPE: &str =
+    "application/vnd.provenact.skill.provenance.v1+json";
+pub const PROVENACT_SIGNATURES_MEDIA_TYPE: &str =
+    "application/vnd.provenact.skill.signatures.v1+json";
+pub const PROVENACT_LOG_PROOF_MEDIA_TYPE: &str =
+    "application/vnd.provenact.skill.log-proof.v1+json";
+pub const OCI_REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+pub const OCI_TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+
+const OCI_LAYOUT_VERSION: &str = "1.0.0";
+
+/// Bundle files carried as layers, in layer order. `skill.wasm` is required;
+/// `manifest.json` travels as the config blob.
+const LAYER_FILES: [(&str, &str, bool); 4] = [
+    ("skill.wasm", PROVENACT_WASM_MEDIA_TYPE, true),
+    ("provenance.json", PROVENACT_PROVENANCE_MEDIA_TYPE, false),
+    ("signatures.json", PROVENACT_SIGNATURES_MEDIA_TYPE, false),
+    ("log-proof.json", PROVENACT_LOG_PROOF_MEDIA_TYPE, false),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OciReferenceError {
+    #[error("{reference:?}: missing registry host")]
+    MissingRegistry { reference: String },
+    #[error("{reference:?}: invalid registry host {registry:?}")]
+    InvalidRegistry { reference: String, registry: String },
+    #[error("{reference:?}: invalid repository {repository:?}")]
+    InvalidRepository {
+        reference: String,
+        repository: String,
+    },
+    #[error("{reference:?}: invalid tag {tag:?}")]
+    InvalidTag { reference: String, tag: String },
+    #[error("{reference:?}: invalid digest {digest:?}, expected sha256:<64 lowercase hex>")]
+    InvalidDigest { reference: String, digest: String },
+    #[error("{reference:?}: reference is not pinned to a digest")]
+    Unpinned { reference: String },
+}
+
+impl OciReferenceError {
+    /// Stable error code used by `test-vectors/oci/references.json`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            OciReferenceError::MissingRegistry { .. } => "missing_registry",
+            OciReferenceError::InvalidRegistry { .. } => "invalid_registry",
+            OciReferenceError::InvalidRepository { .. } => "invalid_repository",
+            OciReferenceError::InvalidTag { .. } => "invalid_tag",
+            OciReferenceError::InvalidDigest { .. } => "invalid_digest",
+            OciReferenceError::Unpinned { .. } => "unpinned",
+        }
+    }
+}
+
+/// `oci://<registry>/<repository>[:<tag>][@sha256:<hex>]`.
+///
+/// The digest, when present, is the digest of the OCI image manifest, not of
+/// `skill.wasm`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OciReference {
+    pub registry: String,
+    pub repository: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+impl OciReference {
+    /// Parses a reference with or without the `oci://` scheme. There is no
+    /// default registry: the first path component must be a host.
+    pub fn parse(reference: &str) -> Result<Self, OciReferenceError> {
+        let owned = || reference.to_string();
+        let rest = reference.strip_prefix("oci://").unwrap_or(reference);
+        let (rest, digest) = match rest.split_once('@') {
+            Some((rest, digest)) => {
+                if validate_sha256_prefixed(digest).is_err() {
+                    return Err(OciReferenceError::InvalidDigest {
+                        reference: owned(),
+                        digest: digest.to_string(),
+                    });
+                }
+                (rest, Some(digest.to_string()))
+            }
+            None => (rest, None),
+        };
+        let (registry, path) = rest
+            .split_once('/')
+            .filter(|(registry, _)| looks_like_host(registry))
+            .ok_or_else(|| OciReferenceError::MissingRegistry { reference: owned() })?;
+        if !valid_registry(registry) {
+            return Err(OciReferenceError::InvalidRegistry {
+                reference: owned(),
+                registry: registry.to_string(),
+            });
+        }
+        // A tag separator is a ':' after the last '/'.
+        let (repository, tag) = match path.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => (repository, Some(tag)),
+            _ => (path, None),
+        };
+        if !valid_repository(repository) {
+            return Err(OciReferenceError::InvalidRepository {
+                reference: owned(),
+                repository: repository.to_string(),
+            });
+        }
+        if let Some(tag) = tag.filter(|tag| !valid_tag(tag)) {
+            return Err(OciReferenceError::InvalidTag {
+                reference: owned(),
+                tag: tag.to_string(),
+            });
+        }
+        Ok(Self {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            tag: tag.map(str::to_string),
+            digest,
+        })
+    }
+
+    /// Parses a reference that MUST carry a digest. Tags are kept for
+    /// display but never used for resolution.
+    pub fn parse_pinned(reference: &str) -> Result<Self, OciReferenceError> {
+        let parsed = Self::parse(reference)?;
+        if parsed.digest.is_none() {
+            return Err(OciReferenceError::Unpinned {
+                reference: reference.to_string(),
+            });
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for OciReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "oci://{}/{}", self.registry, self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{tag}")?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{digest}")?;
+        }
+        Ok(())
+    }
+}
+
+fn looks_like_host(component: &str) -> bool {
+    component == "localhost" || component.contains('.') || component.contains(':')
+}
+
+fn valid_registry(registry: &str) -> bool {
+    let (host, port) = match registry.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (registry, None),
+    };
+    let host_ok = !host.is_empty()
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        });
+    let port_ok = port.is_none_or(|port| {
+        !port.is_empty() && port.len() <= 5 && port.bytes().all(|b| b.is_ascii_digit())
+    });
+    host_ok && port_ok
+}
+
+/// OCI distribution path components: lowercase alphanumerics joined by
+/// `.`, `_`, `__`, or runs of `-`.
+fn valid_repository(repository: &str) -> bool {
+    !repository.is_empty()
+        && repository.len() <= 255
+        && repository.split('/').all(|component| {
+            let bytes = component.as_bytes();
+            let mut i = 0;
+            loop {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_lowercase() || bytes[i].is_ascii_digit())
+                {
+                    i += 1;
+                }
+                if i == start {
+                    return false;
+                }
+                if i == bytes.len() {
+                    return true;
+                }
+                let sep = i;
+                while i < bytes.len() && matches!(bytes[i], b'.' | b'_' | b'-') {
+                    i += 1;
+                }
+                let separator = &component[sep..i];
+                let ok =
+                    matches!(separator, "." | "_" | "__") || separator.bytes().all(|b| b == b'-');
+                if !ok {
+                    return false;
+                }
+            }
+        })
+}
+
+fn valid_tag(tag: &str) -> bool {
+    let bytes = tag.as_bytes();
+    !bytes.is_empty()
+        && bytes.len() <= 128
+        && (bytes[0].is_ascii_alphanumeric() || bytes[0] == b'_')
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OciReferenceVector {
+    pub name: String,
+    pub cases: Vec<OciReferenceCase>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OciReferenceCase {
+    pub reference: String,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub expect: Option<OciReference>,
+    #[serde(default)]
+    pub expect_error: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// OCI content descriptor. Unknown fields are ignored so layouts written by
+/// other OCI tooling can be imported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OciDescriptor {
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OciImageManifest {
+    pub schema_version: u32,
+    pub media_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    pub config: OciDescriptor,
+    pub layers: Vec<OciDescriptor>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OciIndex {
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    pub manifests: Vec<OciDescriptor>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OciLayoutError {
+    #[error(transparent)]
+    Spec(#[from] crate::SpecError),
+    #[error("{path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{path}: {message}")]
+    InvalidDocument { path: PathBuf, message: String },
+    #[error("bundle is missing required file {0}")]
+    MissingFile(&'static str),
+    #[error("manifest.artifact={manifest} but skill.wasm is {actual}")]
+    ArtifactMismatch { manifest: String, actual: String },
+    #[error("no image manifest in index matches {0}")]
+    NoMatchingManifest(String),
+    #[error("index lists {0} image manifests; select one by tag or digest")]
+    AmbiguousIndex(usize),
+    #[error("blob {digest}: expected {expected_size} bytes with that digest, found {actual_size} bytes hashing to {actual}")]
+    BlobMismatch {
+        digest: String,
+        expected_size: u64,
+        actual: String,
+        actual_size: u64,
+    },
+    #[error("image manifest is not a Provenact skill: {0}")]
+    NotASkill(String),
+    #[error("invalid tag {0:?}")]
+    InvalidTag(String),
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> OciLayoutError + '_ {
+    move |source| OciLayoutError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Which image manifest of a layout's `index.json` to import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OciSelector {
+    /// The only manifest in the index.
+    Only,
+    Tag(String),
+    Digest(String),
+}
+
+impl From<&OciReference> for OciSelector {
+    /// Digest wins over tag, so pinned references never resolve by tag.
+    fn from(reference: &OciReference) -> Self {
+        match (&reference.digest, &reference.tag) {
+            (Some(digest), _) => OciSelector::Digest(digest.clone()),
+            (None, Some(tag)) => OciSelector::Tag(tag.clone()),
+            (None, None) => OciSelector::Only,
+        }
+    }
+}
+
+/// Digests preserved across an export or import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OciTransfer {
+    /// Digest of the OCI image manifest (what `@sha256:` references pin).
+    pub image_manifest_digest: String,
+    /// `manifest.artifact`, the digest of `skill.wasm`.
+    pub artifact: String,
+    /// Digest of the `manifest.json` bytes carried as the config blob.
+    pub manifest_digest: String,
+}
+
+fn read_bundle_file(bundle: &Path, file: &'static str) -> Result<Option<Vec<u8>>, OciLayoutError> {
+    let path = bundle.join(file);
+    match std::fs::read(&path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(source) => Err(OciLayoutError::Io { path, source }),
+    }
+}
+
+fn parse_manifest(bytes: &[u8], path: &Path) -> Result<Manifest, OciLayoutError> {
+    serde_json::from_slice(bytes).map_err(|e| OciLayoutError::InvalidDocument {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+fn check_artifact(manifest: &Manifest, wasm: &[u8]) -> Result<(), OciLayoutError> {
+    let actual = sha256_prefixed(wasm);
+    if actual != manifest.artifact {
+        return Err(OciLayoutError::ArtifactMismatch {
+            manifest: manifest.artifact.clone(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+fn blob_path(layout: &Path, digest: &str) -> Result<PathBuf, OciLayoutError> {
+    validate_sha256_prefixed(digest)?;
+    Ok(layout.join("blobs/sha256").join(&digest["sha256:".len()..]))
+}
+
+fn write_blob(
+    layout: &Path,
+    media_type: &str,
+    bytes: &[u8],
+    annotations: BTreeMap<String, String>,
+) -> Result<OciDescriptor, OciLayoutError> {
+    let digest = sha256_prefixed(bytes);
+    let path = blob_path(layout, &digest)?;
+    std::fs::write(&path, bytes).map_err(io_error(&path))?;
+    Ok(OciDescriptor {
+        media_type: media_type.to_string(),
+        digest,
+        size: bytes.len() as u64,
+        annotations,
+    })
+}
+
+fn read_blob(layout: &Path, descriptor: &OciDescriptor) -> Result<Vec<u8>, OciLayoutError> {
+    let path = blob_path(layout, &descriptor.digest)?;
+    let bytes = std::fs::read(&path).map_err(io_error(&path))?;
+    let actual = sha256_prefixed(&bytes);
+    if actual != descriptor.digest || bytes.len() as u64 != descriptor.size {
+        return Err(OciLayoutError::BlobMismatch {
+            digest: descriptor.digest.clone(),
+            expected_size: descriptor.size,
+            actual,
+            actual_size: bytes.len() as u64,
+        });
+    }
+    Ok(bytes)
+}
+
+fn to_json(value: &impl Serialize) -> Result<Vec<u8>, OciLayoutError> {
+    Ok(serde_json::to_vec(value).map_err(crate::SpecError::from)?)
+}
+
+/// Reads `index.json`; `None` when the layout has none yet.
+fn read_index(layout: &Path) -> Result<Option<OciIndex>, OciLayoutError> {
+    let index_path = layout.join("index.json");
+    let index_bytes = match std::fs::read(&index_path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(io_error(&index_path)(err)),
+    };
+    serde_json::from_slice(&index_bytes)
+        .map(Some)
+        .map_err(|e| OciLayoutError::InvalidDocument {
+            path: index_path,
+            message: e.to_string(),
+        })
+}
+
+/// Writes `bundle` as an OCI image layout at `layout`, tagged `tag` in
+/// `index.json` when given.
+///
+/// An existing layout keeps its other images: the export replaces only the
+/// index entry with the same tag (or, untagged, the same untagged image).
+///
+/// `manifest.json` bytes become the config blob unchanged and each bundle
+/// file becomes one layer titled with its file name, so both the artifact
+/// digest and the manifest hash survive the round trip. Output is
+/// deterministic for identical bundles.
+pub fn export_oci_layout(
+    bundle: &Path,
+    layout: &Path,
+    tag: Option<&str>,
+) -> Result<OciTransfer, OciLayoutError> {
+    let manifest_bytes = read_bundle_file(bundle, "manifest.json")?
+        .ok_or(OciLayoutError::MissingFile("manifest.json"))?;
+    let manifest = parse_manifest(&manifest_bytes, &bundle.join("manifest.json"))?;
+    if let Some(tag) = tag.filter(|tag| !valid_tag(tag)) {
+        return Err(OciLayoutError::InvalidTag(tag.to_string()));
+    }
+    let mut index = read_index(layout)?.unwrap_or(OciIndex {
+        schema_version: 2,
+        media_type: Some(OCI_IMAGE_INDEX_MEDIA_TYPE.to_string()),
+        manifests: Vec::new(),
+    });
+
+    let blobs = layout.join("blobs/sha256");
+    std::fs::create_dir_all(&blobs).map_err(io_error(&blobs))?;
+
+    let config = write_blob(
+        layout,
+        PROVENACT_MANIFEST_MEDIA_TYPE,
+        &manifest_bytes,
+        BTreeMap::new(),
+    )?;
+    let mut layers = Vec::new();
+    for (file, media_type, required) in LAYER_FILES {
+        let Some(bytes) = read_bundle_file(bundle, file)? else {
+            if required {
+                return Err(OciLayoutError::MissingFile(file));
+            }
+            continue;
+        };
+        if file == "skill.wasm" {
+            check_artifact(&manifest, &bytes)?;
+        }
+        let annotations = BTreeMap::from([(OCI_TITLE_ANNOTATION.to_string(), file.to_string())]);
+        layers.push(write_blob(layout, media_type, &bytes, annotations)?);
+    }
+
+    let image = OciImageManifest {
+        schema_version: 2,
+        media_type: OCI_IMAGE_MANIFEST_MEDIA_TYPE.to_string(),
+        artifact_type: Some(PROVENACT_SKILL_ARTIFACT_TYPE.to_string()),
+        config: config.clone(),
+        layers,
+        annotations: BTreeMap::new(),
+    };
+    let mut image_descriptor = write_blob(
+        layout,
+        OCI_IMAGE_MANIFEST_MEDIA_TYPE,
+        &to_json(&image)?,
+        BTreeMap::new(),
+    )?;
+    if let Some(tag) = tag {
+        image_descriptor
+            .annotations
+            .insert(OCI_REF_NAME_ANNOTATION.to_string(), tag.to_string());
+    }
+    let image_manifest_digest = image_descriptor.digest.clone();
+    index.manifests.retain(|existing| {
+        let existing_tag = existing.annotations.get(OCI_REF_NAME_ANNOTATION);
+        existing_tag.map(String::as_str) != tag
+            || (tag.is_none() && existing.digest != image_manifest_digest)
+    });
+    index.manifests.push(image_descriptor);
+    let index_path = layout.join("index.json");
+    std::fs::write(&index_path, to_json(&index)?).map_err(io_error(&index_path))?;
+    let marker = layout.join("oci-layout");
+    std::fs::write(
+        &marker,
+        to_json(&serde_json::json!({ "imageLayoutVersion": OCI_LAYOUT_VERSION }))?,
+    )
+    .map_err(io_error(&marker))?;
+
+    Ok(OciTransfer {
+        image_manifest_digest,
+        artifact: manifest.artifact,
+        manifest_digest: config.digest,
+    })
+}
+
+/// Reads the selected skill image from the OCI image layout at `layout`
+/// into the bundle directory `bundle`.
+///
+/// Every blob is checked against its descriptor digest and size, and
+/// `skill.wasm` against `manifest.artifact`, before any bundle file is
+/// written.
+pub fn import_oci_layout(
+    layout: &Path,
+    selector: &OciSelector,
+    bundle: &Path,
+) -> Result<OciTransfer, OciLayoutError> {
+    let index_path = layout.join("index.json");
+    let index = read_index(layout)?
+        .ok_or_else(|| io_error(&index_path)(std::io::Error::from(std::io::ErrorKind::NotFound)))?;
+
+    let candidates: Vec<&OciDescriptor> = index
+        .manifests
+        .iter()
+        .filter(|d| d.media_type == OCI_IMAGE_MANIFEST_MEDIA_TYPE)
+        .collect();
+    let descriptor = match selector {
+        OciSelector::Only => match candidates.as_slice() {
+            [only] => *only,
+            [] => return Err(OciLayoutError::NoMatchingManifest("<any>".to_string())),
+            many => return Err(OciLayoutError::AmbiguousIndex(many.len())),
+        },
+        OciSelector::Digest(digest) => candidates
+            .into_iter()
+            .find(|d| &d.digest == digest)
+            .ok_or_else(|| OciLayoutError::NoMatchingManifest(digest.clone()))?,
+        OciSelector::Tag(tag) => candidates
+            .into_iter()
+            .find(|d| d.annotations.get(OCI_REF_NAME_ANNOTATION) == Some(tag))
+            .ok_or_else(|| OciLayoutError::NoMatchingManifest(tag.clone()))?,
+    };
+
+    let image_bytes = read_blob(layout, descriptor)?;
+    let image: OciImageManifest =
+        serde_json::from_slice(&image_bytes).map_err(|e| OciLayoutError::InvalidDocument {
+            path: blob_path(layout, &descriptor.digest).unwrap_or_default(),
+            message: e.to_string(),
+        })?;
+    if image.artifact_type.as_deref() != Some(PROVENACT_SKILL_ARTIFACT_TYPE) {
+        return Err(OciLayoutError::NotASkill(format!(
+            "artifactType {:?}",
+            image.artifact_type
+        )));
+    }
+    if image.config.media_type != PROVENACT_MANIFEST_MEDIA_TYPE {
+        return Err(OciLayoutError::NotASkill(format!(
+            "config media type {}",
+            image.config.media_type
+        )));
+    }
+
+    let manifest_bytes = read_blob(layout, &image.config)?;
+    let manifest = parse_manifest(&manifest_bytes, Path::new("manifest.json"))?;
+    let mut files: Vec<(&'static str, Vec<u8>)> = vec![("manifest.json", manifest_bytes)];
+    for layer in &image.layers {
+        let (file, _, _) = LAYER_FILES
+            .iter()
+            .find(|(_, media_type, _)| *media_type == layer.media_type)
+            .ok_or_else(|| {
+                OciLayoutError::NotASkill(format!(
+                    "unexpected layer media type {}",
+                    layer.media_type
+                ))
+            })?;
+        if files.iter().any(|(seen, _)| seen == file) {
+            return Err(OciLayoutError::NotASkill(format!("duplicate {file} layer")));
+        }
+        files.push((file, read_blob(layout, layer)?));
+    }
+    for (file, _, required) in LAYER_FILES {
+        if required && !files.iter().any(|(seen, _)| *seen == file) {
+            return Err(OciLayoutError::MissingFile(file));
+        }
+    }
+    let wasm = files
+        .iter()
+        .find(|(file, _)| *file == "skill.wasm")
+        .map(|(_, bytes)| bytes.as_slice())
+        .unwrap_or_default();
+    check_artifact(&manifest, wasm)?;
+
+    std::fs::create_dir_all(bundle).map_err(io_error(bundle))?;
+    for (file, bytes) in &files {
+        let path = bundle.join(file);
+        std::fs::write(&path, bytes).map_err(io_error(&path))?;
+    }
+    Ok(OciTransfer {
+        image_manifest_digest: descriptor.digest.clone(),
+        artifact: manifest.artifact,
+        manifest_digest: image.config.digest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_manifest_hash, parse_json};
+
+    fn bundle_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-vectors/good/pack-sign-roundtrip")
+    }
+
+    #[test]
+    fn reference_vectors_match() {
+        let vector: OciReferenceVector =
+            parse_json(include_str!("../../../test-vectors/oci/references.json")).unwrap();
+        for case in vector.cases {
+            let parsed = if case.pinned {
+                OciReference::parse_pinned(&case.reference)
+            } else {
+                OciReference::parse(&case.reference)
+            };
+            match (parsed, &case.expect, &case.expect_error) {
+                (Ok(parsed), Some(expect), None) => {
+                    assert_eq!(&parsed, expect, "{}", case.reference);
+                    assert_eq!(OciReference::parse(&parsed.to_string()).unwrap(), parsed);
+                }
+                (Err(err), None, Some(code)) => assert_eq!(err.code(), code, "{}", case.reference),
+                (got, _, _) => panic!("{}: unexpected {got:?}", case.reference),
+            }
+        }
+    }
+
+    #[test]
+    fn export_import_round_trip_preserves_digests() {
+        let tmp = tempfile::tempdir().unwrap();
+        let layout = tmp.path().join("layout");
+        let exported = export_oci_layout(&bundle_dir(), &layout, Some("0.1.0")).unwrap();
+
+        // Identical bundles export to identical image manifests.
+        let again = export_oci_layout(&bundle_dir(), &tmp.path().join("again"), Some("0.1.0"));
+        assert_eq!(again.unwrap(), exported);
+
+        let out = tmp.path().join("bundle");
+        let reference = OciReference::parse_pinned(&format!(
+            "oci://registry.example.com/org/skill@{}",
+            exported.image_manifest_digest
+        ))
+        .unwrap();
+        let imported = import_oci_layout(&layout, &(&reference).into(), &out).unwrap();
+        assert_eq!(imported, exported);
+        for file in ["manifest.json", "skill.wasm", "signatures.json"] {
+            assert_eq!(
+                std::fs::read(out.join(file)).unwrap(),
+                std::fs::read(bundle_dir().join(file)).unwrap()
+            );
+        }
+        let manifest: Manifest =
+            parse_json(&std::fs::read_to_string(out.join("manifest.json")).unwrap()).unwrap();
+        let original: Manifest =
+            parse_json(&std::fs::read_to_string(bundle_dir().join("manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            compute_manifest_hash(&manifest).unwrap(),
+            compute_manifest_hash(&original).unwrap()
+        );
+
+        assert!(matches!(
+            import_oci_layout(&layout, &OciSelector::Tag("0.2.0".into()), &out),
+            Err(OciLayoutError::NoMatchingManifest(_))
+        ));
+    }
+
+    #[test]
+    fn export_keeps_other_images_and_replaces_same_tag() {
+        let tmp = tempfile::tempdir().unwrap();
+        let layout = tmp.path().join("layout");
+        let first = export_oci_layout(&bundle_dir(), &layout, Some("0.1.0")).unwrap();
+        export_oci_layout(&bundle_dir(), &layout, Some("latest")).unwrap();
+        export_oci_layout(&bundle_dir(), &layout, Some("latest")).unwrap();
+        export_oci_layout(&bundle_dir(), &layout, None).unwrap();
+        export_oci_layout(&bundle_dir(), &layout, None).unwrap();
+
+        let index = read_index(&layout).unwrap().unwrap();
+        let tags: Vec<Option<&String>> = index
+            .manifests
+            .iter()
+            .map(|d| d.annotations.get(OCI_REF_NAME_ANNOTATION))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                Some(&"0.1.0".to_string()),
+                Some(&"latest".to_string()),
+                None
+            ]
+        );
+        let out = tmp.path().join("bundle");
+        let imported = import_oci_layout(&layout, &OciSelector::Tag("0.1.0".into()), &out).unwrap();
+        assert_eq!(imported, first);
+
+        assert!(matches!(
+            export_oci_layout(&bundle_dir(), &tmp.path().join("bad"), Some("-bad tag")),
+            Err(OciLayoutError::InvalidTag(tag)) if tag == "-bad tag"
+        ));
+        assert!(!tmp.path().join("bad").exists());
+    }
+
+    #[test]
+    fn tampered_blob_is_rejected_before_writing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let layout = tmp.path().join("layout");
+        let exported = export_oci_layout(&bundle_dir(), &layout, None).unwrap();
+        let wasm = blob_path(&layout, &exported.artifact).unwrap();
+        std::fs::write(&wasm, b"tampered").unwrap();
+
+        let out = tmp.path().join("bundle");
+        assert!(matches!(
+            import_oci_layout(&layout, &OciSelector::Only, &out),
+            Err(OciLayoutError::BlobMismatch { .. })
+        ));
+        assert!(!out.exists());
+    }
+}
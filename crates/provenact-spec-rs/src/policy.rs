@@ -0,0 +1,292 @@
+//! Semantic policy checks beyond `spec/policy/policy.schema.json`
+//! (`spec/policy/policy.md`).
+
+use crate::{
+    normalize_fs_path, normalize_uri_path, validate_signer_rules, NamePattern, NetEntry, Policy,
+    SignerRuleError, NAME_PATTERN_POLICY_VERSION, NET_RULE_POLICY_VERSION,
+};
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::fmt;
+use url::Url;
+
+/// A semantic policy defect at a JSON pointer into the policy document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for PolicyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Canonical form of a `capability_ceiling.net` prefix, as compared by
+/// `net.http` evaluation: scheme, userinfo, host, effective port, and
+/// normalized path.
+pub(crate) fn normalize_net_prefix(value: &str) -> Result<String, String> {
+    let url = Url::parse(value).map_err(|e| format!("not a parseable URI: {e}"))?;
+    let Some(host) = url.host_str().filter(|_| url.has_authority()) else {
+        return Err("URI has no host".into());
+    };
+    if url.query().is_some() {
+        return Err("URI prefix must not have a query".into());
+    }
+    if url.fragment().is_some() {
+        return Err("URI prefix must not have a fragment".into());
+    }
+    let path =
+        normalize_uri_path(url.path()).ok_or_else(|| "URI path is not normalizable".to_string())?;
+    let userinfo = match (url.username(), url.password()) {
+        ("", None) => String::new(),
+        (user, None) => format!("{user}@"),
+        (user, Some(password)) => format!("{user}:{password}@"),
+    };
+    let port = url
+        .port_or_known_default()
+        .map_or_else(String::new, |port| format!(":{port}"));
+    Ok(format!("{}://{userinfo}{host}{port}{path}", url.scheme()))
+}
+
+/// Checks what the schema cannot: `net` prefixes parse with a host and no
+/// query or fragment, `fs` prefixes normalize, neither has duplicates after
+/// normalization, structured `net` entries appear only in version 2,
+/// version 2 `kv`/`queue` entries are valid [`NamePattern`]s, and
+/// `signer_rules` are consistent. Returns every issue.
+pub fn validate_policy(policy: &Policy) -> Result<(), Vec<PolicyIssue>> {
+    let mut issues = Vec::new();
+    let ceiling = &policy.capability_ceiling;
+    for (field, prefixes) in [("read", &ceiling.fs.read), ("write", &ceiling.fs.write)] {
+        check_normalized(
+            &format!("/capability_ceiling/fs/{field}"),
+            prefixes,
+            String::as_str,
+            |prefix| normalize_fs_path(prefix).ok_or_else(|| "path is not normalizable".into()),
+            &mut issues,
+        );
+    }
+    check_normalized(
+        "/capability_ceiling/net",
+        &ceiling.net,
+        NetEntry::prefix,
+        normalize_net_entry,
+        &mut issues,
+    );
+    if policy.version < NET_RULE_POLICY_VERSION {
+        for (index, entry) in ceiling.net.iter().enumerate() {
+            if let NetEntry::Rule(rule) = entry {
+                issues.push(PolicyIssue {
+                    path: format!("/capability_ceiling/net/{index}"),
+                    message: format!(
+                        "{:?}: structured entries need policy version {NET_RULE_POLICY_VERSION}",
+                        rule.prefix
+                    ),
+                });
+            }
+        }
+    }
+    if policy.version >= NAME_PATTERN_POLICY_VERSION {
+        let patterns = [
+            ("kv/read", &ceiling.kv.read),
+            ("kv/write", &ceiling.kv.write),
+            ("queue/publish", &ceiling.queue.publish),
+            ("queue/consume", &ceiling.queue.consume),
+        ];
+        for (field, entries) in patterns {
+            for (index, entry) in entries.iter().enumerate() {
+                let pattern = if field.starts_with("kv/") {
+                    NamePattern::kv(entry)
+                } else {
+                    NamePattern::queue(entry)
+                };
+                if let Err(err) = pattern {
+                    issues.push(PolicyIssue {
+                        path: format!("/capability_ceiling/{field}/{index}"),
+                        message: format!("{entry:?}: {err}"),
+                    });
+                }
+            }
+        }
+    }
+    if let Err(err) = validate_signer_rules(policy) {
+        let rule = match &err {
+            SignerRuleError::ZeroThreshold(rule)
+            | SignerRuleError::ThresholdExceedsSigners { rule, .. }
+            | SignerRuleError::DuplicateSigner { rule, .. }
+            | SignerRuleError::UnknownCapability { rule, .. } => *rule,
+        };
+        issues.push(PolicyIssue {
+            path: format!("/signer_rules/{rule}"),
+            message: err.to_string(),
+        });
+    }
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+/// A structured entry duplicates another only with the same methods and
+/// limits; its normalized form appends them to the normalized prefix.
+fn normalize_net_entry(entry: &NetEntry) -> Result<String, String> {
+    let prefix = normalize_net_prefix(entry.prefix())?;
+    let NetEntry::Rule(rule) = entry else {
+        return Ok(prefix);
+    };
+    let mut methods = rule.methods.clone();
+    methods.sort();
+    let limits = entry.limits();
+    let limit = |name: &str, value: Option<u64>| value.map(|v| format!(" {name}={v}"));
+    Ok(format!(
+        "{prefix} [{}]{}",
+        methods.join(","),
+        [
+            limit("max_request_bytes", limits.max_request_bytes),
+            limit("max_response_bytes", limits.max_response_bytes),
+            limit("timeout_ms", limits.timeout_ms),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<String>()
+    ))
+}
+
+fn check_normalized<T>(
+    path: &str,
+    entries: &[T],
+    written: impl Fn(&T) -> &str,
+    normalize: impl Fn(&T) -> Result<String, String>,
+    issues: &mut Vec<PolicyIssue>,
+) {
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let normalized = normalize(entry);
+        let entry = written(entry);
+        match normalized {
+            Ok(normalized) => match seen.entry(normalized) {
+                Entry::Vacant(slot) => {
+                    slot.insert(index);
+                }
+                Entry::Occupied(first) => issues.push(PolicyIssue {
+                    path: format!("{path}/{index}"),
+                    message: format!(
+                        "{entry:?} duplicates entry {} after normalization to {:?}",
+                        first.get(),
+                        first.key()
+                    ),
+                }),
+            },
+            Err(message) => issues.push(PolicyIssue {
+                path: format!("{path}/{index}"),
+                message: format!("{entry:?}: {message}"),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CapabilityCeiling, FsCeiling};
+
+    fn policy(fs_read: &[&str], net: &[&str]) -> Policy {
+        Policy {
+            version: 1,
+            trusted_signers: vec!["alice.dev".into()],
+            capability_ceiling: CapabilityCeiling {
+                fs: FsCeiling {
+                    read: fs_read.iter().map(|s| s.to_string()).collect(),
+                    write: Vec::new(),
+                },
+                net: net.iter().map(|&s| s.into()).collect(),
+                ..CapabilityCeiling::default()
+            },
+            sbom: None,
+            signer_rules: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn normalized_duplicates_and_bad_prefixes_are_reported() {
+        let issues = validate_policy(&policy(
+            &["/data", "/data/", "/tmp/../etc"],
+            &[
+                "https://api.example.com",
+                "https://API.example.com:443/",
+                "https://api.example.com/v1?key=1",
+                "mailto:ops@example.com",
+            ],
+        ))
+        .unwrap_err();
+        let paths: Vec<_> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/capability_ceiling/fs/read/1",
+                "/capability_ceiling/fs/read/2",
+                "/capability_ceiling/net/1",
+                "/capability_ceiling/net/2",
+                "/capability_ceiling/net/3",
+            ]
+        );
+        assert!(issues[2].message.contains("duplicates entry 0"));
+        assert!(issues[3].message.contains("query"));
+    }
+
+    #[test]
+    fn version_2_name_patterns_are_checked() {
+        let mut policy = policy(&[], &[]);
+        policy.capability_ceiling.kv.read = vec!["cache/*".into(), "cache//x".into()];
+        policy.capability_ceiling.queue.publish = vec!["jobs.>.x".into()];
+        assert_eq!(validate_policy(&policy), Ok(()));
+        policy.version = 2;
+        let issues = validate_policy(&policy).unwrap_err();
+        let paths: Vec<_> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/capability_ceiling/kv/read/1",
+                "/capability_ceiling/queue/publish/0"
+            ]
+        );
+    }
+
+    #[test]
+    fn net_rules_need_version_2_and_differ_by_methods() {
+        let mut policy = policy(&[], &["https://api.example.com/v1"]);
+        policy.capability_ceiling.net.extend(
+            serde_json::from_value::<Vec<NetEntry>>(serde_json::json!([
+                { "prefix": "https://API.example.com/v1/", "methods": ["GET"] },
+                { "prefix": "https://api.example.com/v1", "methods": ["POST"] },
+                { "prefix": "https://api.example.com:443/v1", "methods": ["POST"] },
+            ]))
+            .unwrap(),
+        );
+        let issues = validate_policy(&policy).unwrap_err();
+        let paths: Vec<_> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/capability_ceiling/net/3",
+                "/capability_ceiling/net/1",
+                "/capability_ceiling/net/2",
+                "/capability_ceiling/net/3",
+            ]
+        );
+        assert!(issues[0].message.contains("duplicates entry 2"));
+        policy.version = 2;
+        let issues = validate_policy(&policy).unwrap_err();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "/capability_ceiling/net/3");
+    }
+
+    #[test]
+    fn example_policy_is_semantically_valid() {
+        assert_eq!(
+            validate_policy(&policy(&["/data"], &["https://api.github.com"])),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,731 @@
+//! Semantic differences between two policies, classified by whether they
+//! grant more (widening) or less (narrowing).
+
+use crate::name_pattern::{kv_covers, queue_covers};
+use crate::{
+    is_valid_env_name, is_within_prefix, normalize_fs_path, NamePattern, NetEntry, Policy,
+    SbomPolicy, SignerRule, NAME_PATTERN_POLICY_VERSION,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeImpact {
+    /// The new policy allows something the old one denied.
+    Widening,
+    /// The new policy denies something the old one allowed.
+    Narrowing,
+    /// No decision changes, e.g. a prefix already covered by another entry.
+    Neutral,
+}
+
+impl ChangeImpact {
+    fn inverse(self) -> Self {
+        match self {
+            Self::Widening => Self::Narrowing,
+            Self::Narrowing => Self::Widening,
+            Self::Neutral => Self::Neutral,
+        }
+    }
+}
+
+impl fmt::Display for ChangeImpact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Widening => "widening",
+            Self::Narrowing => "narrowing",
+            Self::Neutral => "neutral",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One change at a JSON pointer. List entries are reported at the list's
+/// pointer with the entry as `old` (removed) or `new` (added).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyChange {
+    pub path: String,
+    pub change: ChangeKind,
+    pub impact: ChangeImpact,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+    /// The entry of the other policy that already covers this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub covered_by: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDiff {
+    pub changes: Vec<PolicyChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDiffVector {
+    pub name: String,
+    pub old: Policy,
+    pub new: Policy,
+    pub expect: PolicyDiff,
+}
+
+impl PolicyDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn with_impact(&self, impact: ChangeImpact) -> impl Iterator<Item = &PolicyChange> {
+        self.changes.iter().filter(move |c| c.impact == impact)
+    }
+
+    /// Any change grants more than before; such diffs warrant extra review.
+    pub fn is_widening(&self) -> bool {
+        self.with_impact(ChangeImpact::Widening).next().is_some()
+    }
+}
+
+impl fmt::Display for PolicyDiff {
+    /// One line per change: `+` added, `-` removed, `~` changed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "policy diff: {} widening, {} narrowing, {} neutral",
+            self.with_impact(ChangeImpact::Widening).count(),
+            self.with_impact(ChangeImpact::Narrowing).count(),
+            self.with_impact(ChangeImpact::Neutral).count()
+        )?;
+        for change in &self.changes {
+            let show =
+                |value: &Option<Value>| value.as_ref().map_or(String::new(), Value::to_string);
+            match change.change {
+                ChangeKind::Added => write!(f, "+ {} {}", change.path, show(&change.new))?,
+                ChangeKind::Removed => write!(f, "- {} {}", change.path, show(&change.old))?,
+                ChangeKind::Changed => write!(
+                    f,
+                    "~ {} {} -> {}",
+                    change.path,
+                    show(&change.old),
+                    show(&change.new)
+                )?,
+            }
+            write!(f, " [{}]", change.impact)?;
+            if let Some(covering) = &change.covered_by {
+                write!(f, " (covered by {covering})")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+struct Differ {
+    changes: Vec<PolicyChange>,
+}
+
+impl Differ {
+    fn push(
+        &mut self,
+        path: &str,
+        change: ChangeKind,
+        impact: ChangeImpact,
+        old: Option<Value>,
+        new: Option<Value>,
+    ) {
+        self.changes.push(PolicyChange {
+            path: path.to_string(),
+            change,
+            impact,
+            old,
+            new,
+            covered_by: None,
+        });
+    }
+
+    /// `grants` is the impact of adding an entry that matches requests the
+    /// old list did not. Entries are identified by `key` (`None` for entries
+    /// that never match anything), and `covers(outer, inner)` tells whether
+    /// every request matching `inner` also matches `outer`.
+    fn list<T: Serialize + PartialEq>(
+        &mut self,
+        path: &str,
+        old: &[T],
+        new: &[T],
+        grants: ChangeImpact,
+        key: impl Fn(&T) -> Option<String>,
+        covers: impl Fn(&T, &T) -> bool,
+    ) {
+        let keys =
+            |entries: &[T]| -> BTreeSet<Option<String>> { entries.iter().map(&key).collect() };
+        let (old_keys, new_keys) = (keys(old), keys(new));
+        for (entries, others, other_keys, change) in [
+            (old, new, &new_keys, ChangeKind::Removed),
+            (new, old, &old_keys, ChangeKind::Added),
+        ] {
+            for entry in entries {
+                let entry_key = key(entry);
+                if entry_key.is_some() && other_keys.contains(&entry_key) {
+                    continue;
+                }
+                if entry_key.is_none() && others.contains(entry) {
+                    continue;
+                }
+                let covered_by = entry_key
+                    .as_ref()
+                    .and_then(|_| others.iter().find(|other| covers(other, entry)))
+                    .map(|other| json!(other));
+                let impact = if entry_key.is_none() || covered_by.is_some() {
+                    ChangeImpact::Neutral
+                } else if change == ChangeKind::Added {
+                    grants
+                } else {
+                    grants.inverse()
+                };
+                let value = Some(json!(entry));
+                let (old, new) = match change {
+                    ChangeKind::Removed => (value, None),
+                    _ => (None, value),
+                };
+                self.changes.push(PolicyChange {
+                    path: path.to_string(),
+                    change,
+                    impact,
+                    old,
+                    new,
+                    covered_by,
+                });
+            }
+        }
+    }
+
+    fn exact_list(&mut self, path: &str, old: &[String], new: &[String], grants: ChangeImpact) {
+        self.list(path, old, new, grants, |s| Some(s.clone()), |a, b| a == b);
+    }
+
+    /// `grants` is the impact of turning the flag on.
+    fn flag(&mut self, path: &str, old: bool, new: bool, grants: ChangeImpact) {
+        if old != new {
+            let impact = if new { grants } else { grants.inverse() };
+            self.push(
+                path,
+                ChangeKind::Changed,
+                impact,
+                Some(json!(old)),
+                Some(json!(new)),
+            );
+        }
+    }
+
+    fn sbom(&mut self, old: Option<&SbomPolicy>, new: Option<&SbomPolicy>) {
+        let (old, new) = match (old, new) {
+            (None, None) => return,
+            (Some(old), None) => {
+                return self.push(
+                    "/sbom",
+                    ChangeKind::Removed,
+                    ChangeImpact::Widening,
+                    Some(json!(old)),
+                    None,
+                )
+            }
+            (None, Some(new)) => {
+                return self.push(
+                    "/sbom",
+                    ChangeKind::Added,
+                    ChangeImpact::Narrowing,
+                    None,
+                    Some(json!(new)),
+                )
+            }
+            (Some(old), Some(new)) => (old, new),
+        };
+        self.flag(
+            "/sbom/require_sbom",
+            old.require_sbom,
+            new.require_sbom,
+            ChangeImpact::Narrowing,
+        );
+        // An empty allowlist allows every license.
+        match (
+            old.allowed_licenses.is_empty(),
+            new.allowed_licenses.is_empty(),
+        ) {
+            (true, true) => {}
+            (false, false) => self.exact_list(
+                "/sbom/allowed_licenses",
+                &old.allowed_licenses,
+                &new.allowed_licenses,
+                ChangeImpact::Widening,
+            ),
+            (old_empty, _) => self.push(
+                "/sbom/allowed_licenses",
+                ChangeKind::Changed,
+                if old_empty {
+                    ChangeImpact::Narrowing
+                } else {
+                    ChangeImpact::Widening
+                },
+                Some(json!(old.allowed_licenses)),
+                Some(json!(new.allowed_licenses)),
+            ),
+        }
+        self.exact_list(
+            "/sbom/denied_licenses",
+            &old.denied_licenses,
+            &new.denied_licenses,
+            ChangeImpact::Narrowing,
+        );
+        for old_rule in &old.denied_packages {
+            if !new.denied_packages.iter().any(|r| r.name == old_rule.name) {
+                self.push(
+                    "/sbom/denied_packages",
+                    ChangeKind::Removed,
+                    ChangeImpact::Widening,
+                    Some(json!(old_rule)),
+                    None,
+                );
+            }
+        }
+        for (index, new_rule) in new.denied_packages.iter().enumerate() {
+            let Some(old_rule) = old.denied_packages.iter().find(|r| r.name == new_rule.name)
+            else {
+                self.push(
+                    "/sbom/denied_packages",
+                    ChangeKind::Added,
+                    ChangeImpact::Narrowing,
+                    None,
+                    Some(json!(new_rule)),
+                );
+                continue;
+            };
+            // An empty version list denies every version.
+            let path = format!("/sbom/denied_packages/{index}/versions");
+            match (old_rule.versions.is_empty(), new_rule.versions.is_empty()) {
+                (true, true) => {}
+                (false, false) => self.exact_list(
+                    &path,
+                    &old_rule.versions,
+                    &new_rule.versions,
+                    ChangeImpact::Narrowing,
+                ),
+                (old_all, _) => self.push(
+                    &path,
+                    ChangeKind::Changed,
+                    if old_all {
+                        ChangeImpact::Widening
+                    } else {
+                        ChangeImpact::Narrowing
+                    },
+                    Some(json!(old_rule.versions)),
+                    Some(json!(new_rule.versions)),
+                ),
+            }
+        }
+    }
+
+    /// Rules are requirements, so adding one narrows. Identical rules are
+    /// matched regardless of position; the rest are paired in order.
+    fn signer_rules(&mut self, old: &[SignerRule], new: &[SignerRule]) {
+        let unmatched = |rules: &[SignerRule], others: &[SignerRule]| -> Vec<usize> {
+            (0..rules.len())
+                .filter(|i| !others.contains(&rules[*i]))
+                .collect()
+        };
+        let (old_left, new_left) = (unmatched(old, new), unmatched(new, old));
+        for pair in 0..old_left.len().max(new_left.len()) {
+            match (old_left.get(pair), new_left.get(pair)) {
+                (Some(o), Some(n)) => self.signer_rule(&old[*o], &new[*n], *n),
+                (Some(o), None) => self.push(
+                    &format!("/signer_rules/{o}"),
+                    ChangeKind::Removed,
+                    ChangeImpact::Widening,
+                    Some(json!(old[*o])),
+                    None,
+                ),
+                (None, Some(n)) => self.push(
+                    &format!("/signer_rules/{n}"),
+                    ChangeKind::Added,
+                    ChangeImpact::Narrowing,
+                    None,
+                    Some(json!(new[*n])),
+                ),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn signer_rule(&mut self, old: &SignerRule, new: &SignerRule, index: usize) {
+        let path = format!("/signer_rules/{index}");
+        if old.threshold != new.threshold {
+            self.push(
+                &format!("{path}/threshold"),
+                ChangeKind::Changed,
+                if new.threshold > old.threshold {
+                    ChangeImpact::Narrowing
+                } else {
+                    ChangeImpact::Widening
+                },
+                Some(json!(old.threshold)),
+                Some(json!(new.threshold)),
+            );
+        }
+        self.exact_list(
+            &format!("{path}/signers"),
+            &old.signers,
+            &new.signers,
+            ChangeImpact::Widening,
+        );
+        // An empty capability list scopes the rule to every request.
+        let capabilities = format!("{path}/capabilities");
+        match (old.capabilities.is_empty(), new.capabilities.is_empty()) {
+            (true, true) => {}
+            (false, false) => self.exact_list(
+                &capabilities,
+                &old.capabilities,
+                &new.capabilities,
+                ChangeImpact::Narrowing,
+            ),
+            (old_all, _) => self.push(
+                &capabilities,
+                ChangeKind::Changed,
+                if old_all {
+                    ChangeImpact::Widening
+                } else {
+                    ChangeImpact::Narrowing
+                },
+                Some(json!(old.capabilities)),
+                Some(json!(new.capabilities)),
+            ),
+        }
+    }
+}
+
+fn fs_covers(outer: &str, inner: &str) -> bool {
+    match (normalize_fs_path(outer), normalize_fs_path(inner)) {
+        (Some(outer), Some(inner)) => is_within_prefix(&inner, &outer),
+        _ => false,
+    }
+}
+
+/// Rules that differ only in method order or prefix spelling share a key.
+fn net_key(entry: &NetEntry) -> Option<String> {
+    entry.canonical().map(|entry| json!(entry).to_string())
+}
+
+fn name_key(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn wildcard_covers(outer: &str, inner: &str) -> bool {
+    outer == "*" || outer == inner
+}
+
+/// Whether `policy` is version 2 and has a `kv`/`queue` pattern other than
+/// `*` that matches more than one name.
+fn has_wildcard_patterns(policy: &Policy) -> bool {
+    if policy.version < NAME_PATTERN_POLICY_VERSION {
+        return false;
+    }
+    let ceiling = &policy.capability_ceiling;
+    let wildcard = |pattern: Result<NamePattern, _>| {
+        pattern.is_ok_and(|pattern| pattern.as_str() != "*" && !pattern.is_exact())
+    };
+    [&ceiling.kv.read, &ceiling.kv.write]
+        .into_iter()
+        .flatten()
+        .any(|entry| wildcard(NamePattern::kv(entry)))
+        || [&ceiling.queue.publish, &ceiling.queue.consume]
+            .into_iter()
+            .flatten()
+            .any(|entry| wildcard(NamePattern::queue(entry)))
+}
+
+/// Compares what `old` and `new` allow, using the normalization and
+/// matching rules of [`crate::evaluate_capability`]. Entries that only
+/// change spelling (`/data/` vs `/data`) are not reported; entries already
+/// covered by another entry (`/tmp/sub` under `/tmp`) are neutral.
+pub fn diff_policies(old: &Policy, new: &Policy) -> PolicyDiff {
+    let mut differ = Differ {
+        changes: Vec::new(),
+    };
+    let (o, n) = (&old.capability_ceiling, &new.capability_ceiling);
+    if old.version != new.version {
+        // Version 2 reads `kv`/`queue` entries such as `cache/*` as patterns.
+        let impact = match (has_wildcard_patterns(old), has_wildcard_patterns(new)) {
+            (false, true) => ChangeImpact::Widening,
+            (true, false) => ChangeImpact::Narrowing,
+            _ => ChangeImpact::Neutral,
+        };
+        differ.push(
+            "/version",
+            ChangeKind::Changed,
+            impact,
+            Some(json!(old.version)),
+            Some(json!(new.version)),
+        );
+    }
+    differ.exact_list(
+        "/trusted_signers",
+        &old.trusted_signers,
+        &new.trusted_signers,
+        ChangeImpact::Widening,
+    );
+    for (field, old_list, new_list) in [
+        ("read", &o.fs.read, &n.fs.read),
+        ("write", &o.fs.write, &n.fs.write),
+    ] {
+        differ.list(
+            &format!("/capability_ceiling/fs/{field}"),
+            old_list,
+            new_list,
+            ChangeImpact::Widening,
+            |prefix| normalize_fs_path(prefix),
+            |outer, inner| fs_covers(outer, inner),
+        );
+    }
+    differ.list(
+        "/capability_ceiling/net",
+        &o.net,
+        &n.net,
+        ChangeImpact::Widening,
+        net_key,
+        NetEntry::covers,
+    );
+    differ.list(
+        "/capability_ceiling/env",
+        &o.env,
+        &n.env,
+        ChangeImpact::Widening,
+        |name| is_valid_env_name(name).then(|| name.to_string()),
+        |a, b| a == b,
+    );
+    let patterns = [old, new]
+        .iter()
+        .all(|policy| policy.version >= NAME_PATTERN_POLICY_VERSION);
+    let kv_covers: fn(&str, &str) -> bool = if patterns { kv_covers } else { wildcard_covers };
+    let queue_covers: fn(&str, &str) -> bool = if patterns {
+        queue_covers
+    } else {
+        wildcard_covers
+    };
+    for (path, old_list, new_list, covers) in [
+        ("kv/read", &o.kv.read, &n.kv.read, kv_covers),
+        ("kv/write", &o.kv.write, &n.kv.write, kv_covers),
+        (
+            "queue/publish",
+            &o.queue.publish,
+            &n.queue.publish,
+            queue_covers,
+        ),
+        (
+            "queue/consume",
+            &o.queue.consume,
+            &n.queue.consume,
+            queue_covers,
+        ),
+    ] {
+        differ.list(
+            &format!("/capability_ceiling/{path}"),
+            old_list,
+            new_list,
+            ChangeImpact::Widening,
+            |name| name_key(name),
+            |outer, inner| covers(outer, inner),
+        );
+    }
+    for (field, old_flag, new_flag) in [
+        ("exec", o.exec, n.exec),
+        ("time", o.time, n.time),
+        ("random", o.random, n.random),
+    ] {
+        differ.flag(
+            &format!("/capability_ceiling/{field}"),
+            old_flag,
+            new_flag,
+            ChangeImpact::Widening,
+        );
+    }
+    differ.sbom(old.sbom.as_ref(), new.sbom.as_ref());
+    differ.signer_rules(&old.signer_rules, &new.signer_rules);
+    PolicyDiff {
+        changes: differ.changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(value: Value) -> Policy {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn impacts(diff: &PolicyDiff) -> Vec<(&str, ChangeKind, ChangeImpact)> {
+        diff.changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.change, c.impact))
+            .collect()
+    }
+
+    #[test]
+    fn covered_prefixes_are_neutral_and_respelled_ones_are_ignored() {
+        let old = policy(json!({
+            "version": 1,
+            "trusted_signers": ["alice.dev"],
+            "capability_ceiling": {
+                "fs": { "write": ["/tmp"] },
+                "net": ["https://api.example.com/v1/"],
+                "kv": { "read": ["*"] }
+            }
+        }));
+        let new = policy(json!({
+            "version": 1,
+            "trusted_signers": ["alice.dev"],
+            "capability_ceiling": {
+                "fs": { "write": ["/tmp/", "/tmp/sub"] },
+                "net": ["https://API.example.com:443/v1"],
+                "kv": { "read": ["*", "token"] }
+            }
+        }));
+        let diff = diff_policies(&old, &new);
+        assert_eq!(
+            impacts(&diff),
+            [
+                (
+                    "/capability_ceiling/fs/write",
+                    ChangeKind::Added,
+                    ChangeImpact::Neutral
+                ),
+                (
+                    "/capability_ceiling/kv/read",
+                    ChangeKind::Added,
+                    ChangeImpact::Neutral
+                ),
+            ]
+        );
+        assert_eq!(diff.changes[0].covered_by, Some(json!("/tmp")));
+        assert!(!diff.is_widening());
+    }
+
+    #[test]
+    fn version_2_patterns_widen_and_cover() {
+        let v1 = policy(json!({
+            "version": 1,
+            "trusted_signers": ["alice.dev"],
+            "capability_ceiling": { "queue": { "publish": ["jobs.>"] } }
+        }));
+        let v2 = policy(json!({
+            "version": 2,
+            "trusted_signers": ["alice.dev"],
+            "capability_ceiling": { "queue": { "publish": ["jobs.>", "jobs.eu.*"] } }
+        }));
+        let diff = diff_policies(&v1, &v2);
+        assert_eq!(
+            impacts(&diff),
+            [
+                ("/version", ChangeKind::Changed, ChangeImpact::Widening),
+                (
+                    "/capability_ceiling/queue/publish",
+                    ChangeKind::Added,
+                    ChangeImpact::Widening
+                ),
+            ]
+        );
+        let narrowed = policy(json!({
+            "version": 2,
+            "trusted_signers": ["alice.dev"],
+            "capability_ceiling": { "queue": { "publish": ["jobs.>", "jobs.us.created"] } }
+        }));
+        let diff = diff_policies(&v2, &narrowed);
+        assert_eq!(
+            impacts(&diff),
+            [
+                (
+                    "/capability_ceiling/queue/publish",
+                    ChangeKind::Removed,
+                    ChangeImpact::Neutral
+                ),
+                (
+                    "/capability_ceiling/queue/publish",
+                    ChangeKind::Added,
+                    ChangeImpact::Neutral
+                ),
+            ]
+        );
+        assert_eq!(diff.changes[1].covered_by, Some(json!("jobs.>")));
+    }
+
+    #[test]
+    fn signer_and_flag_changes_are_classified() {
+        let old = policy(json!({
+            "version": 1,
+            "trusted_signers": ["alice.dev", "bob.dev"],
+            "capability_ceiling": { "fs": { "read": ["/data/in"] } },
+            "signer_rules": [
+                { "threshold": 2, "signers": ["alice.dev", "bob.dev"] },
+                { "threshold": 1, "signers": ["sec"], "capabilities": ["exec"] }
+            ]
+        }));
+        let new = policy(json!({
+            "version": 1,
+            "trusted_signers": ["alice.dev", "carol.dev"],
+            "capability_ceiling": { "fs": { "read": ["/data"] }, "exec": true },
+            "signer_rules": [
+                { "threshold": 1, "signers": ["sec"], "capabilities": ["exec"] },
+                { "threshold": 1, "signers": ["alice.dev", "bob.dev"] }
+            ]
+        }));
+        let diff = diff_policies(&old, &new);
+        assert_eq!(
+            impacts(&diff),
+            [
+                (
+                    "/trusted_signers",
+                    ChangeKind::Removed,
+                    ChangeImpact::Narrowing
+                ),
+                (
+                    "/trusted_signers",
+                    ChangeKind::Added,
+                    ChangeImpact::Widening
+                ),
+                (
+                    "/capability_ceiling/fs/read",
+                    ChangeKind::Removed,
+                    ChangeImpact::Neutral
+                ),
+                (
+                    "/capability_ceiling/fs/read",
+                    ChangeKind::Added,
+                    ChangeImpact::Widening
+                ),
+                (
+                    "/capability_ceiling/exec",
+                    ChangeKind::Changed,
+                    ChangeImpact::Widening
+                ),
+                (
+                    "/signer_rules/1/threshold",
+                    ChangeKind::Changed,
+                    ChangeImpact::Widening
+                ),
+            ]
+        );
+        assert!(diff.is_widening());
+        assert!(diff
+            .to_string()
+            .starts_with("policy diff: 4 widening, 1 narrowing, 1 neutral\n"));
+    }
+}
@@ -0,0 +1,575 @@
+//! Policy lint rules for legal but over-broad policies
+//! (`docs/policies/README.md`).
+
+use crate::{
+    is_within_prefix, normalize_fs_path, normalize_uri_path, NetEntry, Policy, TrustAnchors,
+    NAME_PATTERN_POLICY_VERSION,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    FsRootWrite,
+    KvWildcard,
+    QueueWildcard,
+    ExecEnabled,
+    NetPlainHttp,
+    NetUserinfo,
+    NetHostWide,
+    RedundantPrefix,
+    SignerWithoutKey,
+}
+
+impl LintRule {
+    pub const ALL: [Self; 9] = [
+        Self::FsRootWrite,
+        Self::KvWildcard,
+        Self::QueueWildcard,
+        Self::ExecEnabled,
+        Self::NetPlainHttp,
+        Self::NetUserinfo,
+        Self::NetHostWide,
+        Self::RedundantPrefix,
+        Self::SignerWithoutKey,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::FsRootWrite => "fs-root-write",
+            Self::KvWildcard => "kv-wildcard",
+            Self::QueueWildcard => "queue-wildcard",
+            Self::ExecEnabled => "exec-enabled",
+            Self::NetPlainHttp => "net-plain-http",
+            Self::NetUserinfo => "net-userinfo",
+            Self::NetHostWide => "net-host-wide",
+            Self::RedundantPrefix => "redundant-prefix",
+            Self::SignerWithoutKey => "signer-without-key",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::FsRootWrite => "fs.write allows the whole filesystem",
+            Self::KvWildcard => "kv allowlist allows every key",
+            Self::QueueWildcard => "queue allowlist allows every topic",
+            Self::ExecEnabled => "exec is enabled",
+            Self::NetPlainHttp => "net prefix uses plain http",
+            Self::NetUserinfo => "net prefix carries userinfo credentials",
+            Self::NetHostWide => "net prefix allows every path on a host",
+            Self::RedundantPrefix => "prefix is covered by another entry of the same list",
+            Self::SignerWithoutKey => "signer has no key in the trust anchors",
+        }
+    }
+
+    pub fn default_severity(self) -> LintSeverity {
+        match self {
+            Self::FsRootWrite | Self::NetUserinfo => LintSeverity::Error,
+            Self::KvWildcard
+            | Self::QueueWildcard
+            | Self::ExecEnabled
+            | Self::NetPlainHttp
+            | Self::SignerWithoutKey => LintSeverity::Warning,
+            Self::NetHostWide | Self::RedundantPrefix => LintSeverity::Note,
+        }
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Ordered from quietest to loudest; `Off` findings are not reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Off,
+    Note,
+    Warning,
+    Error,
+}
+
+impl LintSeverity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+
+    /// SARIF 2.1.0 `level`.
+    pub fn sarif_level(self) -> &'static str {
+        match self {
+            Self::Off => "none",
+            other => other.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for LintSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Per-rule severity overrides, e.g. `{"rules": {"exec-enabled": "error"}}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub rules: BTreeMap<LintRule, LintSeverity>,
+}
+
+impl LintConfig {
+    pub fn with_severity(mut self, rule: LintRule, severity: LintSeverity) -> Self {
+        self.rules.insert(rule, severity);
+        self
+    }
+
+    pub fn severity(&self, rule: LintRule) -> LintSeverity {
+        self.rules
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    pub severity: LintSeverity,
+    /// JSON pointer into the policy document.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}: {}",
+            self.severity, self.rule, self.path, self.message
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicyLintReport {
+    pub findings: Vec<LintFinding>,
+}
+
+impl PolicyLintReport {
+    pub fn max_severity(&self) -> Option<LintSeverity> {
+        self.findings.iter().map(|finding| finding.severity).max()
+    }
+
+    /// Whether any finding is at or above `threshold`, for CI gating.
+    pub fn fails_at(&self, threshold: LintSeverity) -> bool {
+        threshold != LintSeverity::Off && self.max_severity() >= Some(threshold)
+    }
+
+    /// SARIF 2.1.0 log with one run. Findings are located by JSON pointer as
+    /// a logical location within `artifact_uri`.
+    pub fn to_sarif(&self, artifact_uri: &str, config: &LintConfig) -> Value {
+        let rules: Vec<Value> = LintRule::ALL
+            .iter()
+            .map(|rule| {
+                json!({
+                    "id": rule.as_str(),
+                    "shortDescription": { "text": rule.description() },
+                    "defaultConfiguration": { "level": config.severity(*rule).sarif_level() },
+                })
+            })
+            .collect();
+        let results: Vec<Value> = self
+            .findings
+            .iter()
+            .map(|finding| {
+                json!({
+                    "ruleId": finding.rule.as_str(),
+                    "level": finding.severity.sarif_level(),
+                    "message": { "text": finding.message },
+                    "locations": [{
+                        "physicalLocation": { "artifactLocation": { "uri": artifact_uri } },
+                        "logicalLocations": [{ "fullyQualifiedName": finding.path, "kind": "member" }],
+                    }],
+                })
+            })
+            .collect();
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": { "driver": { "name": "provenact-policy-lint", "rules": rules } },
+                "results": results,
+            }],
+        })
+    }
+}
+
+impl fmt::Display for PolicyLintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.findings.is_empty() {
+            return f.write_str("no findings");
+        }
+        for (index, finding) in self.findings.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{finding}")?;
+        }
+        Ok(())
+    }
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    findings: Vec<LintFinding>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: LintRule, path: String, message: String) {
+        let severity = self.config.severity(rule);
+        if severity != LintSeverity::Off {
+            self.findings.push(LintFinding {
+                rule,
+                severity,
+                path,
+                message,
+            });
+        }
+    }
+
+    /// Reports entries matched by another entry of the same list. Exact
+    /// duplicates are reported once, on the later entry.
+    fn redundant<T>(&mut self, path: &str, entries: &[Option<T>], covers: impl Fn(&T, &T) -> bool) {
+        for (index, entry) in entries.iter().enumerate() {
+            let Some(entry) = entry else { continue };
+            let covering = entries.iter().enumerate().find(|(other_index, other)| {
+                *other_index != index
+                    && other.as_ref().is_some_and(|other| {
+                        covers(other, entry) && (*other_index < index || !covers(entry, other))
+                    })
+            });
+            if let Some((other_index, _)) = covering {
+                self.report(
+                    LintRule::RedundantPrefix,
+                    format!("{path}/{index}"),
+                    format!("covered by {path}/{other_index}"),
+                );
+            }
+        }
+    }
+}
+
+/// Lints `policy` with per-rule severities from `config`. `signer-without-key`
+/// only runs when `anchors` are given.
+pub fn lint_policy(
+    policy: &Policy,
+    config: &LintConfig,
+    anchors: Option<&TrustAnchors>,
+) -> PolicyLintReport {
+    let mut linter = Linter {
+        config,
+        findings: Vec::new(),
+    };
+    let ceiling = &policy.capability_ceiling;
+
+    for (field, prefixes) in [("read", &ceiling.fs.read), ("write", &ceiling.fs.write)] {
+        let path = format!("/capability_ceiling/fs/{field}");
+        let normalized: Vec<Option<String>> =
+            prefixes.iter().map(|p| normalize_fs_path(p)).collect();
+        if field == "write" {
+            for (index, prefix) in normalized.iter().enumerate() {
+                if prefix.as_deref() == Some("/") {
+                    linter.report(
+                        LintRule::FsRootWrite,
+                        format!("{path}/{index}"),
+                        format!("{:?} allows writing anywhere", prefixes[index]),
+                    );
+                }
+            }
+        }
+        linter.redundant(&path, &normalized, |outer, inner| {
+            is_within_prefix(inner, outer)
+        });
+    }
+
+    let urls: Vec<Option<Url>> = ceiling
+        .net
+        .iter()
+        .map(|entry| Url::parse(entry.prefix()).ok())
+        .collect();
+    for (index, url) in urls.iter().enumerate() {
+        let Some(url) = url else { continue };
+        let path = format!("/capability_ceiling/net/{index}");
+        let entry = ceiling.net[index].prefix();
+        if url.scheme() == "http" {
+            linter.report(
+                LintRule::NetPlainHttp,
+                path.clone(),
+                format!("{entry:?} is not encrypted in transit"),
+            );
+        }
+        if !url.username().is_empty() || url.password().is_some() {
+            linter.report(
+                LintRule::NetUserinfo,
+                path.clone(),
+                format!("{entry:?} embeds credentials in the policy"),
+            );
+        }
+        if url.has_host() && normalize_uri_path(url.path()).as_deref() == Some("/") {
+            linter.report(
+                LintRule::NetHostWide,
+                path,
+                format!(
+                    "{entry:?} allows every path on {}",
+                    url.host_str().unwrap_or_default()
+                ),
+            );
+        }
+    }
+    let entries: Vec<Option<&NetEntry>> = ceiling
+        .net
+        .iter()
+        .zip(&urls)
+        .map(|(entry, url)| url.as_ref().map(|_| entry))
+        .collect();
+    linter.redundant("/capability_ceiling/net", &entries, |outer, inner| {
+        outer.covers(inner)
+    });
+
+    for (rule, path, entries) in [
+        (LintRule::KvWildcard, "kv/read", &ceiling.kv.read),
+        (LintRule::KvWildcard, "kv/write", &ceiling.kv.write),
+        (
+            LintRule::QueueWildcard,
+            "queue/publish",
+            &ceiling.queue.publish,
+        ),
+        (
+            LintRule::QueueWildcard,
+            "queue/consume",
+            &ceiling.queue.consume,
+        ),
+    ] {
+        // Version 2 patterns also allow every name with a bare `>`.
+        let matches_all = |entry: &str| {
+            entry == "*" || (policy.version >= NAME_PATTERN_POLICY_VERSION && entry == ">")
+        };
+        for (index, entry) in entries.iter().enumerate() {
+            if matches_all(entry) {
+                linter.report(
+                    rule,
+                    format!("/capability_ceiling/{path}/{index}"),
+                    format!("{entry:?} allows every name"),
+                );
+            }
+        }
+    }
+
+    if ceiling.exec {
+        linter.report(
+            LintRule::ExecEnabled,
+            "/capability_ceiling/exec".to_string(),
+            "skills may spawn processes".to_string(),
+        );
+    }
+
+    if let Some(anchors) = anchors {
+        let signers = policy
+            .trusted_signers
+            .iter()
+            .enumerate()
+            .map(|(index, signer)| (format!("/trusted_signers/{index}"), signer))
+            .chain(
+                policy
+                    .signer_rules
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(rule, r)| {
+                        r.signers.iter().enumerate().map(move |(index, signer)| {
+                            (format!("/signer_rules/{rule}/signers/{index}"), signer)
+                        })
+                    }),
+            );
+        for (path, signer) in signers {
+            if anchors.keys_for(signer).next().is_none() {
+                linter.report(
+                    LintRule::SignerWithoutKey,
+                    path,
+                    format!("no trust anchor key for signer {signer:?}"),
+                );
+            }
+        }
+    }
+
+    PolicyLintReport {
+        findings: linter.findings,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyLintVector {
+    pub name: String,
+    pub cases: Vec<PolicyLintCase>,
+}
+
+/// `trust_anchors` is an inline trust-anchors document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyLintCase {
+    pub name: String,
+    pub policy: Policy,
+    #[serde(default)]
+    pub config: LintConfig,
+    #[serde(default)]
+    pub trust_anchors: Option<Value>,
+    pub expect: Vec<ExpectedLintFinding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedLintFinding {
+    pub rule: LintRule,
+    pub severity: LintSeverity,
+    pub path: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(ceiling: Value) -> Policy {
+        serde_json::from_value(json!({
+            "version": 1,
+            "trusted_signers": ["alice.dev"],
+            "capability_ceiling": ceiling,
+        }))
+        .unwrap()
+    }
+
+    fn rules(report: &PolicyLintReport) -> Vec<(LintRule, &str)> {
+        report
+            .findings
+            .iter()
+            .map(|f| (f.rule, f.path.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn paranoid_template_is_clean() {
+        let policy: Policy =
+            serde_json::from_str(include_str!("../../../docs/policies/policy.paranoid.json"))
+                .unwrap();
+        let report = lint_policy(&policy, &LintConfig::default(), None);
+        assert_eq!(report.findings, []);
+        assert_eq!(report.to_string(), "no findings");
+    }
+
+    #[test]
+    fn broad_policy_reports_each_rule() {
+        let report = lint_policy(
+            &policy(json!({
+                "fs": { "write": ["/tmp", "//", "/tmp/cache"] },
+                "net": ["http://ops:pw@example.com/", "http://example.com/api"],
+                "kv": { "write": ["*"] },
+                "exec": true,
+            })),
+            &LintConfig::default(),
+            None,
+        );
+        assert_eq!(
+            rules(&report),
+            [
+                (LintRule::FsRootWrite, "/capability_ceiling/fs/write/1"),
+                (LintRule::RedundantPrefix, "/capability_ceiling/fs/write/0"),
+                (LintRule::RedundantPrefix, "/capability_ceiling/fs/write/2"),
+                (LintRule::NetPlainHttp, "/capability_ceiling/net/0"),
+                (LintRule::NetUserinfo, "/capability_ceiling/net/0"),
+                (LintRule::NetHostWide, "/capability_ceiling/net/0"),
+                (LintRule::NetPlainHttp, "/capability_ceiling/net/1"),
+                (LintRule::KvWildcard, "/capability_ceiling/kv/write/0"),
+                (LintRule::ExecEnabled, "/capability_ceiling/exec"),
+            ]
+        );
+        assert!(report.fails_at(LintSeverity::Error));
+    }
+
+    #[test]
+    fn severities_are_configurable() {
+        let config = LintConfig::default()
+            .with_severity(LintRule::ExecEnabled, LintSeverity::Error)
+            .with_severity(LintRule::KvWildcard, LintSeverity::Off);
+        let report = lint_policy(
+            &policy(json!({ "kv": { "read": ["*"] }, "exec": true })),
+            &config,
+            None,
+        );
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].severity, LintSeverity::Error);
+        assert!(!report.fails_at(LintSeverity::Off));
+        let config: LintConfig =
+            serde_json::from_str(r#"{"rules": {"net-host-wide": "off"}}"#).unwrap();
+        assert_eq!(config.severity(LintRule::NetHostWide), LintSeverity::Off);
+    }
+
+    #[test]
+    fn bare_rest_pattern_is_a_wildcard_in_version_2() {
+        let mut policy = policy(json!({ "queue": { "publish": [">", "jobs.>"] } }));
+        let config = LintConfig::default();
+        assert!(lint_policy(&policy, &config, None).findings.is_empty());
+        policy.version = 2;
+        let report = lint_policy(&policy, &config, None);
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].rule, LintRule::QueueWildcard);
+        assert_eq!(
+            report.findings[0].path,
+            "/capability_ceiling/queue/publish/0"
+        );
+    }
+
+    #[test]
+    fn net_rules_are_redundant_only_when_methods_are_covered() {
+        let mut policy = policy(json!({ "net": [
+            { "prefix": "https://api.example.com/v1", "methods": ["GET"] },
+            { "prefix": "https://api.example.com/v1/users", "methods": ["GET", "POST"] },
+            { "prefix": "https://api.example.com/v1/items", "methods": ["GET"] },
+        ] }));
+        policy.version = 2;
+        let report = lint_policy(&policy, &LintConfig::default(), None);
+        assert_eq!(
+            rules(&report),
+            [(LintRule::RedundantPrefix, "/capability_ceiling/net/2")]
+        );
+    }
+
+    #[test]
+    fn sarif_lists_rules_and_results() {
+        let config = LintConfig::default();
+        let report = lint_policy(&policy(json!({ "exec": true })), &config, None);
+        let sarif = report.to_sarif("policy.yaml", &config);
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(
+            run["tool"]["driver"]["rules"].as_array().unwrap().len(),
+            LintRule::ALL.len()
+        );
+        assert_eq!(run["results"][0]["ruleId"], "exec-enabled");
+        assert_eq!(run["results"][0]["level"], "warning");
+        assert_eq!(
+            run["results"][0]["locations"][0]["logicalLocations"][0]["fullyQualifiedName"],
+            "/capability_ceiling/exec"
+        );
+    }
+}
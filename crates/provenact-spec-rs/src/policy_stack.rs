@@ -0,0 +1,689 @@
+//! Layered policies (org, team, run) combined by intersection
+//! (`spec/v0.md` section 5, `spec/policy/policy.md`).
+
+use crate::name_pattern::{kv_covers, queue_covers};
+use crate::{
+    compute_policy_hash, is_valid_env_name, is_within_prefix, normalize_fs_path, CapabilityCeiling,
+    FsCeiling, KvCeiling, NamePattern, NetEntry, Policy, QueueCeiling, SbomPolicy, SignerRule,
+    SpecError, NAME_PATTERN_POLICY_VERSION,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::borrow::Borrow;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyLayer {
+    pub name: String,
+    pub policy: Policy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PolicyStackError {
+    #[error("policy stack has no layers")]
+    Empty,
+    #[error("layer {layer} has unsupported policy version {version}")]
+    UnsupportedVersion { layer: String, version: u64 },
+    #[error("layer {layer} has policy version {version}, but layer {first} has {expected}")]
+    MixedVersions {
+        layer: String,
+        version: u64,
+        first: String,
+        expected: u64,
+    },
+    #[error("layer name {0} is used more than once")]
+    DuplicateLayer(String),
+    #[error("sbom.allowed_licenses of layers {} have no license in common", .layers.join(", "))]
+    DisjointLicenseAllowlists { layers: Vec<String> },
+}
+
+impl PolicyStackError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Empty => "empty_stack",
+            Self::UnsupportedVersion { .. } => "unsupported_version",
+            Self::MixedVersions { .. } => "mixed_versions",
+            Self::DuplicateLayer(_) => "duplicate_layer",
+            Self::DisjointLicenseAllowlists { .. } => "disjoint_license_allowlists",
+        }
+    }
+}
+
+/// A permission some layer grants that the effective policy does not fully
+/// grant. `value` is the normalized entry, or `true` for flags.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PermissionCut {
+    pub path: String,
+    pub value: Value,
+    pub granted_by: Vec<String>,
+    /// Layers that do not grant all of `value`.
+    pub cut_by: Vec<String>,
+    /// Effective entries that still grant part of `value`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub narrowed_to: Vec<Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EffectivePolicy {
+    pub policy: Policy,
+    pub cuts: Vec<PermissionCut>,
+}
+
+impl EffectivePolicy {
+    pub fn policy_hash(&self) -> Result<String, SpecError> {
+        compute_policy_hash(&self.policy)
+    }
+
+    /// Permissions that `layer` grants but other layers cut.
+    pub fn cuts_of<'a>(&'a self, layer: &'a str) -> impl Iterator<Item = &'a PermissionCut> + 'a {
+        self.cuts
+            .iter()
+            .filter(move |cut| cut.granted_by.iter().any(|name| name == layer))
+    }
+}
+
+/// Ordered policy layers, typically org, then team, then run. No layer can
+/// grant more than any other allows, so an org layer caps every team layer.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyStack {
+    layers: Vec<PolicyLayer>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyStackVector {
+    pub name: String,
+    pub cases: Vec<PolicyStackCase>,
+}
+
+/// `expect` is the effective policy document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyStackCase {
+    pub name: String,
+    pub layers: Vec<PolicyLayer>,
+    #[serde(default)]
+    pub expect: Option<Value>,
+    #[serde(default)]
+    pub expect_cuts: Vec<PermissionCut>,
+    #[serde(default)]
+    pub expect_error: Option<String>,
+}
+
+/// Entry semantics of one allowlist of `E`s: `key` normalizes an entry
+/// (`None` when it can never match), `covers(outer, inner)` compares
+/// normalized keys, and `overlap` is the one entry matching what two keys
+/// both match when neither covers the other, if there is one.
+struct ListKind<E: ?Sized, K> {
+    key: fn(&E) -> Option<K>,
+    covers: fn(&E, &E) -> bool,
+    overlap: fn(&E, &E) -> Option<K>,
+}
+
+type NameKind = ListKind<str, String>;
+
+const FS: NameKind = ListKind {
+    key: normalize_fs_path,
+    covers: fs_covers,
+    overlap: no_overlap,
+};
+const NET: ListKind<NetEntry, NetEntry> = ListKind {
+    key: NetEntry::canonical,
+    covers: NetEntry::covers,
+    overlap: NetEntry::overlap,
+};
+const ENV: NameKind = ListKind {
+    key: env_key,
+    covers: exact_covers,
+    overlap: no_overlap,
+};
+const NAMES: NameKind = ListKind {
+    key: name_key,
+    covers: wildcard_covers,
+    overlap: no_overlap,
+};
+const KV_PATTERNS: NameKind = ListKind {
+    key: kv_pattern_key,
+    covers: kv_covers,
+    overlap: no_overlap,
+};
+const QUEUE_PATTERNS: NameKind = ListKind {
+    key: queue_pattern_key,
+    covers: queue_covers,
+    overlap: no_overlap,
+};
+const SIGNERS: NameKind = ListKind {
+    key: name_key,
+    covers: exact_covers,
+    overlap: no_overlap,
+};
+
+fn fs_covers(outer: &str, inner: &str) -> bool {
+    is_within_prefix(inner, outer)
+}
+
+fn env_key(name: &str) -> Option<String> {
+    is_valid_env_name(name).then(|| name.to_string())
+}
+
+fn name_key(name: &str) -> Option<String> {
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn kv_pattern_key(pattern: &str) -> Option<String> {
+    NamePattern::kv(pattern).ok().map(|_| pattern.to_string())
+}
+
+fn queue_pattern_key(pattern: &str) -> Option<String> {
+    NamePattern::queue(pattern)
+        .ok()
+        .map(|_| pattern.to_string())
+}
+
+fn exact_covers(outer: &str, inner: &str) -> bool {
+    outer == inner
+}
+
+fn wildcard_covers(outer: &str, inner: &str) -> bool {
+    outer == "*" || outer == inner
+}
+
+fn no_overlap(_: &str, _: &str) -> Option<String> {
+    None
+}
+
+impl<E: ?Sized, K: Borrow<E> + Clone + PartialEq> ListKind<E, K> {
+    fn keys(&self, entries: &[K]) -> Vec<K> {
+        entries
+            .iter()
+            .filter_map(|entry| (self.key)(entry.borrow()))
+            .collect()
+    }
+
+    fn covers(&self, outer: &K, inner: &K) -> bool {
+        (self.covers)(outer.borrow(), inner.borrow())
+    }
+
+    fn covered(&self, entries: &[K], key: &K) -> bool {
+        entries.iter().any(|entry| self.covers(entry, key))
+    }
+
+    /// Keeps the first of equal keys and drops keys covered by another.
+    fn minimize(&self, keys: Vec<K>) -> Vec<K> {
+        let mut out: Vec<K> = Vec::new();
+        for (index, key) in keys.iter().enumerate() {
+            let redundant = keys.iter().enumerate().any(|(other_index, other)| {
+                other_index != index
+                    && self.covers(other, key)
+                    && (other != key || other_index < index)
+            });
+            if !redundant {
+                out.push(key.clone());
+            }
+        }
+        out
+    }
+
+    /// Entries matching what both lists match: the narrower of each
+    /// overlapping pair, or their overlap when neither is narrower.
+    fn intersect(&self, left: &[K], right: &[K]) -> Vec<K> {
+        let mut out = Vec::new();
+        for a in left {
+            for b in right {
+                if self.covers(a, b) {
+                    out.push(b.clone());
+                } else if self.covers(b, a) {
+                    out.push(a.clone());
+                } else if let Some(overlap) = (self.overlap)(a.borrow(), b.borrow()) {
+                    out.push(overlap);
+                }
+            }
+        }
+        self.minimize(out)
+    }
+}
+
+struct Composer<'a> {
+    layers: &'a [PolicyLayer],
+    cuts: Vec<PermissionCut>,
+}
+
+impl Composer<'_> {
+    fn list<E: ?Sized, K: Borrow<E> + Clone + PartialEq + Serialize>(
+        &mut self,
+        path: &str,
+        kind: &ListKind<E, K>,
+        select: impl Fn(&Policy) -> &Vec<K>,
+    ) -> Vec<K> {
+        let per_layer: Vec<Vec<K>> = self
+            .layers
+            .iter()
+            .map(|layer| kind.keys(select(&layer.policy)))
+            .collect();
+        let mut effective = kind.minimize(per_layer[0].clone());
+        for keys in &per_layer[1..] {
+            effective = kind.intersect(&effective, keys);
+        }
+        for (layer, keys) in self.layers.iter().zip(&per_layer) {
+            for key in keys {
+                if kind.covered(&effective, key) {
+                    continue;
+                }
+                let cut_by = self
+                    .layers
+                    .iter()
+                    .zip(&per_layer)
+                    .filter(|(_, other)| !kind.covered(other, key))
+                    .map(|(other, _)| other.name.clone())
+                    .collect();
+                let narrowed_to = effective
+                    .iter()
+                    .filter(|entry| kind.covers(key, entry))
+                    .map(|entry| json!(entry))
+                    .collect();
+                self.cut(path, json!(key), &layer.name, cut_by, narrowed_to);
+            }
+        }
+        effective
+    }
+
+    fn flag(&mut self, path: &str, select: impl Fn(&Policy) -> bool) -> bool {
+        let effective = self.layers.iter().all(|layer| select(&layer.policy));
+        if !effective {
+            let cut_by: Vec<String> = self
+                .layers
+                .iter()
+                .filter(|layer| !select(&layer.policy))
+                .map(|layer| layer.name.clone())
+                .collect();
+            for layer in self.layers.iter().filter(|layer| select(&layer.policy)) {
+                self.cut(
+                    path,
+                    Value::Bool(true),
+                    &layer.name,
+                    cut_by.clone(),
+                    Vec::new(),
+                );
+            }
+        }
+        effective
+    }
+
+    fn cut(
+        &mut self,
+        path: &str,
+        value: Value,
+        layer: &str,
+        cut_by: Vec<String>,
+        narrowed_to: Vec<Value>,
+    ) {
+        if let Some(cut) = self
+            .cuts
+            .iter_mut()
+            .find(|cut| cut.path == path && cut.value == value)
+        {
+            cut.granted_by.push(layer.to_string());
+            return;
+        }
+        self.cuts.push(PermissionCut {
+            path: path.to_string(),
+            value,
+            granted_by: vec![layer.to_string()],
+            cut_by,
+            narrowed_to,
+        });
+    }
+}
+
+/// SBOM checks only restrict, so every layer's checks apply.
+fn combine_sbom(layers: &[PolicyLayer]) -> Result<Option<SbomPolicy>, PolicyStackError> {
+    let sboms: Vec<(&str, &SbomPolicy)> = layers
+        .iter()
+        .filter_map(|layer| Some((layer.name.as_str(), layer.policy.sbom.as_ref()?)))
+        .collect();
+    if sboms.is_empty() {
+        return Ok(None);
+    }
+    let mut combined = SbomPolicy {
+        require_sbom: sboms.iter().any(|(_, sbom)| sbom.require_sbom),
+        ..SbomPolicy::default()
+    };
+    // An empty allowlist allows every license.
+    let allowlists: Vec<(&str, &Vec<String>)> = sboms
+        .iter()
+        .filter(|(_, sbom)| !sbom.allowed_licenses.is_empty())
+        .map(|(name, sbom)| (*name, &sbom.allowed_licenses))
+        .collect();
+    if let Some(((_, first), rest)) = allowlists.split_first() {
+        combined.allowed_licenses = first
+            .iter()
+            .filter(|license| rest.iter().all(|(_, list)| list.contains(license)))
+            .cloned()
+            .collect();
+        if combined.allowed_licenses.is_empty() {
+            return Err(PolicyStackError::DisjointLicenseAllowlists {
+                layers: allowlists
+                    .iter()
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+            });
+        }
+    }
+    for (_, sbom) in &sboms {
+        for license in &sbom.denied_licenses {
+            if !combined.denied_licenses.contains(license) {
+                combined.denied_licenses.push(license.clone());
+            }
+        }
+        for rule in &sbom.denied_packages {
+            match combined
+                .denied_packages
+                .iter_mut()
+                .find(|existing| existing.name == rule.name)
+            {
+                None => combined.denied_packages.push(rule.clone()),
+                // An empty version list denies every version.
+                Some(existing) if existing.versions.is_empty() => {}
+                Some(existing) if rule.versions.is_empty() => existing.versions.clear(),
+                Some(existing) => {
+                    for version in &rule.versions {
+                        if !existing.versions.contains(version) {
+                            existing.versions.push(version.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(Some(combined))
+}
+
+impl PolicyStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a layer below the existing ones.
+    pub fn push(&mut self, name: impl Into<String>, policy: Policy) -> &mut Self {
+        self.layers.push(PolicyLayer {
+            name: name.into(),
+            policy,
+        });
+        self
+    }
+
+    pub fn layers(&self) -> &[PolicyLayer] {
+        &self.layers
+    }
+
+    /// Intersects the layers: `fs` and `net` prefixes narrow to their
+    /// normalized overlap, `env`/`kv`/`queue` entries and trusted signers to
+    /// those every layer allows (`"*"` allows any name), and flags to those
+    /// every layer enables. `signer_rules` and `sbom` checks only restrict,
+    /// so all of them apply. Entries that can never match are dropped.
+    ///
+    /// Layers must share a policy version. In version 2, a `kv`/`queue`
+    /// pattern survives where another layer's pattern covers it; patterns
+    /// that only partly overlap (`a/*/x` and `a/b/*`) are cut. Structured
+    /// `net` entries with nested prefixes also keep only their common methods
+    /// and the tighter of each limit.
+    pub fn effective(&self) -> Result<EffectivePolicy, PolicyStackError> {
+        if self.layers.is_empty() {
+            return Err(PolicyStackError::Empty);
+        }
+        let first = &self.layers[0];
+        for (index, layer) in self.layers.iter().enumerate() {
+            if !(1..=NAME_PATTERN_POLICY_VERSION).contains(&layer.policy.version) {
+                return Err(PolicyStackError::UnsupportedVersion {
+                    layer: layer.name.clone(),
+                    version: layer.policy.version,
+                });
+            }
+            if layer.policy.version != first.policy.version {
+                return Err(PolicyStackError::MixedVersions {
+                    layer: layer.name.clone(),
+                    version: layer.policy.version,
+                    first: first.name.clone(),
+                    expected: first.policy.version,
+                });
+            }
+            if self.layers[..index].iter().any(|l| l.name == layer.name) {
+                return Err(PolicyStackError::DuplicateLayer(layer.name.clone()));
+            }
+        }
+        let sbom = combine_sbom(&self.layers)?;
+        let mut composer = Composer {
+            layers: &self.layers,
+            cuts: Vec::new(),
+        };
+        let version = first.policy.version;
+        let (kv, queue) = if version >= NAME_PATTERN_POLICY_VERSION {
+            (&KV_PATTERNS, &QUEUE_PATTERNS)
+        } else {
+            (&NAMES, &NAMES)
+        };
+        let trusted_signers = composer.list("/trusted_signers", &SIGNERS, |p| &p.trusted_signers);
+        let capability_ceiling = CapabilityCeiling {
+            fs: FsCeiling {
+                read: composer.list("/capability_ceiling/fs/read", &FS, |p| {
+                    &p.capability_ceiling.fs.read
+                }),
+                write: composer.list("/capability_ceiling/fs/write", &FS, |p| {
+                    &p.capability_ceiling.fs.write
+                }),
+            },
+            net: composer.list("/capability_ceiling/net", &NET, |p| {
+                &p.capability_ceiling.net
+            }),
+            env: composer.list("/capability_ceiling/env", &ENV, |p| {
+                &p.capability_ceiling.env
+            }),
+            kv: KvCeiling {
+                read: composer.list("/capability_ceiling/kv/read", kv, |p| {
+                    &p.capability_ceiling.kv.read
+                }),
+                write: composer.list("/capability_ceiling/kv/write", kv, |p| {
+                    &p.capability_ceiling.kv.write
+                }),
+            },
+            queue: QueueCeiling {
+                publish: composer.list("/capability_ceiling/queue/publish", queue, |p| {
+                    &p.capability_ceiling.queue.publish
+                }),
+                consume: composer.list("/capability_ceiling/queue/consume", queue, |p| {
+                    &p.capability_ceiling.queue.consume
+                }),
+            },
+            exec: composer.flag("/capability_ceiling/exec", |p| p.capability_ceiling.exec),
+            time: composer.flag("/capability_ceiling/time", |p| p.capability_ceiling.time),
+            random: composer.flag("/capability_ceiling/random", |p| {
+                p.capability_ceiling.random
+            }),
+        };
+        let mut signer_rules: Vec<SignerRule> = Vec::new();
+        for rule in self.layers.iter().flat_map(|l| &l.policy.signer_rules) {
+            if !signer_rules.contains(rule) {
+                signer_rules.push(rule.clone());
+            }
+        }
+        Ok(EffectivePolicy {
+            policy: Policy {
+                version,
+                trusted_signers,
+                capability_ceiling,
+                sbom,
+                signer_rules,
+            },
+            cuts: composer.cuts,
+        })
+    }
+}
+
+impl FromIterator<PolicyLayer> for PolicyStack {
+    fn from_iter<I: IntoIterator<Item = PolicyLayer>>(iter: I) -> Self {
+        Self {
+            layers: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluate_capability, validate_policy, Capability};
+    use serde_json::json;
+
+    fn policy(value: Value) -> Policy {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn org_and_team() -> PolicyStack {
+        let mut stack = PolicyStack::new();
+        stack
+            .push(
+                "org",
+                policy(json!({
+                    "version": 1,
+                    "trusted_signers": ["alice.dev", "bob.dev"],
+                    "capability_ceiling": {
+                        "fs": { "read": ["/data/team-a", "/srv"] },
+                        "net": ["https://API.example.com:443/v1/"],
+                        "kv": { "read": ["*"] },
+                        "exec": false
+                    }
+                })),
+            )
+            .push(
+                "team",
+                policy(json!({
+                    "version": 1,
+                    "trusted_signers": ["bob.dev", "carol.dev"],
+                    "capability_ceiling": {
+                        "fs": { "read": ["/data", "/srv/cache/"] },
+                        "net": ["https://api.example.com"],
+                        "kv": { "read": ["token"] },
+                        "exec": true
+                    }
+                })),
+            );
+        stack
+    }
+
+    #[test]
+    fn team_grants_are_capped_by_org() {
+        let effective = org_and_team().effective().unwrap();
+        let ceiling = &effective.policy.capability_ceiling;
+        assert_eq!(effective.policy.trusted_signers, ["bob.dev"]);
+        assert_eq!(ceiling.fs.read, ["/data/team-a", "/srv/cache"]);
+        assert_eq!(ceiling.net, [NetEntry::from("https://api.example.com/v1")]);
+        assert_eq!(ceiling.kv.read, ["token"]);
+        assert!(!ceiling.exec);
+        assert_eq!(validate_policy(&effective.policy), Ok(()));
+
+        let team_cuts: Vec<_> = effective
+            .cuts_of("team")
+            .map(|cut| (cut.path.as_str(), cut.value.clone(), cut.cut_by.clone()))
+            .collect();
+        assert_eq!(
+            team_cuts,
+            [
+                (
+                    "/trusted_signers",
+                    json!("carol.dev"),
+                    vec!["org".to_string()]
+                ),
+                (
+                    "/capability_ceiling/fs/read",
+                    json!("/data"),
+                    vec!["org".to_string()]
+                ),
+                (
+                    "/capability_ceiling/net",
+                    json!("https://api.example.com/"),
+                    vec!["org".to_string()]
+                ),
+                (
+                    "/capability_ceiling/exec",
+                    json!(true),
+                    vec!["org".to_string()]
+                ),
+            ]
+        );
+        let fs_cut = effective.cuts_of("team").nth(1).unwrap();
+        assert_eq!(fs_cut.narrowed_to, ["/data/team-a"]);
+
+        let request = |kind: &str, value: &str| Capability {
+            kind: kind.to_string(),
+            value: value.to_string(),
+            method: None,
+        };
+        assert!(evaluate_capability(
+            &effective.policy,
+            &request("fs.read", "/srv/cache/x")
+        ));
+        assert!(!evaluate_capability(
+            &effective.policy,
+            &request("fs.read", "/data/team-b")
+        ));
+    }
+
+    #[test]
+    fn effective_policy_hash_is_order_independent_for_ceilings() {
+        let forward = org_and_team().effective().unwrap();
+        let reversed: PolicyStack = org_and_team().layers().iter().rev().cloned().collect();
+        let reversed = reversed.effective().unwrap();
+        assert_eq!(
+            serde_json::to_value(&forward.policy.capability_ceiling.fs).unwrap(),
+            serde_json::to_value(&reversed.policy.capability_ceiling.fs).unwrap()
+        );
+        assert!(forward.policy_hash().unwrap().starts_with("sha256:"));
+    }
+
+    #[test]
+    fn invalid_stacks_are_rejected() {
+        assert_eq!(
+            PolicyStack::new().effective().unwrap_err(),
+            PolicyStackError::Empty
+        );
+        let mut stack = org_and_team();
+        stack.push("org", org_and_team().layers()[0].policy.clone());
+        assert_eq!(stack.effective().unwrap_err().code(), "duplicate_layer");
+        let mut stack = org_and_team();
+        let mut run = org_and_team().layers()[1].policy.clone();
+        run.version = 2;
+        stack.push("run", run);
+        assert_eq!(stack.effective().unwrap_err().code(), "mixed_versions");
+    }
+
+    #[test]
+    fn version_2_name_patterns_intersect_by_coverage() {
+        let layer = |kv: Value| {
+            policy(json!({
+                "version": 2,
+                "trusted_signers": ["alice.dev"],
+                "capability_ceiling": { "kv": { "read": kv } }
+            }))
+        };
+        let mut stack = PolicyStack::new();
+        stack
+            .push("org", layer(json!(["cache/>", "a/*/x"])))
+            .push("team", layer(json!(["cache/user-*", "cache2/x", "a/b/*"])));
+        let effective = stack.effective().unwrap();
+        assert_eq!(effective.policy.version, 2);
+        assert_eq!(
+            effective.policy.capability_ceiling.kv.read,
+            ["cache/user-*"]
+        );
+        let cut: Vec<_> = effective.cuts.iter().map(|cut| cut.value.clone()).collect();
+        assert_eq!(
+            cut,
+            [
+                json!("cache/>"),
+                json!("a/*/x"),
+                json!("cache2/x"),
+                json!("a/b/*")
+            ]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Draft v1 execution receipts
+//! (`spec/execution-receipt.v1.experimental.schema.json`).
+
+use serde::{Deserialize, Serialize};
+
+pub const RECEIPT_V1_SCHEMA_VERSION: &str = "1.0.0-draft";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExecutionReceiptV1 {
+    pub schema_version: String,
+    pub artifact: String,
+    pub manifest_hash: String,
+    pub policy_hash: String,
+    pub bundle_hash: String,
+    pub inputs_hash: String,
+    pub outputs_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inputs_schema_digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs_schema_digest: Option<String>,
+    pub runtime_version_digest: String,
+    pub result_digest: String,
+    pub caps_requested: Vec<String>,
+    pub caps_granted: Vec<String>,
+    pub caps_used: Vec<String>,
+    pub result: ReceiptResult,
+    pub runtime: ReceiptRuntime,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub timestamp_strategy: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attestations: Vec<ReceiptAttestation>,
+    pub receipt_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReceiptResult {
+    pub status: ReceiptStatus,
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptStatus {
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReceiptRuntime {
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReceiptAttestation {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub value: String,
+}
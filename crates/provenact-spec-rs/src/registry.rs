@@ -0,0 +1,895 @@
+//! Snapshot-based artifact resolution (`spec/registry/registry.md`).
+
+use crate::signatures::display_rejected;
+use crate::{
+    sha256_prefixed, verify_snapshot_hash, RegistrySnapshot, RejectedSignature, SignatureEntry,
+    SignatureRejection, SnapshotEntry, SpecError, TrustAnchors,
+};
+use crate::{
+    verify_snapshot_v1_hash, AnySnapshot, RegistrySnapshotV1, SnapshotFormatError, VersionQuery,
+    Yank, REGISTRY_SNAPSHOT_V1,
+};
+use md5::{Digest, Md5};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("{location}: not found")]
+    NotFound { location: String },
+    #[error("{location}: {source}")]
+    Io {
+        location: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{location}: {message}")]
+    Transport { location: String, message: String },
+}
+
+/// Source of artifact bytes for snapshot entries.
+///
+/// Fetchers are untrusted transport: the resolver checks every byte they
+/// return against the snapshot.
+pub trait ArtifactFetcher {
+    fn fetch(&self, name: &str, entry: &SnapshotEntry) -> Result<Vec<u8>, FetchError>;
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, FetchError> {
+    let location = path.display().to_string();
+    std::fs::read(path).map_err(|source| {
+        if source.kind() == std::io::ErrorKind::NotFound {
+            FetchError::NotFound { location }
+        } else {
+            FetchError::Io { location, source }
+        }
+    })
+}
+
+/// Reads `<dir>/<name>`.
+#[derive(Debug, Clone)]
+pub struct LocalDirFetcher {
+    dir: PathBuf,
+}
+
+impl LocalDirFetcher {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl ArtifactFetcher for LocalDirFetcher {
+    fn fetch(&self, name: &str, _entry: &SnapshotEntry) -> Result<Vec<u8>, FetchError> {
+        read_file(&self.dir.join(name))
+    }
+}
+
+fn join_name(base: &Url, name: &str) -> Result<Url, FetchError> {
+    let mut url = base.clone();
+    url.path_segments_mut()
+        .map_err(|_| FetchError::Transport {
+            location: base.to_string(),
+            message: "base URL cannot carry a path".to_string(),
+        })?
+        .pop_if_empty()
+        .push(name);
+    Ok(url)
+}
+
+/// Reads `<base>/<name>` from a `file://` base URL.
+#[derive(Debug, Clone)]
+pub struct FileUrlFetcher {
+    base: Url,
+}
+
+impl FileUrlFetcher {
+    pub fn new(base: Url) -> Result<Self, FetchError> {
+        if base.scheme() != "file" {
+            return Err(FetchError::Transport {
+                location: base.to_string(),
+                message: "expected a file:// URL".to_string(),
+            });
+        }
+        Ok(Self { base })
+    }
+}
+
+impl ArtifactFetcher for FileUrlFetcher {
+    fn fetch(&self, name: &str, _entry: &SnapshotEntry) -> Result<Vec<u8>, FetchError> {
+        let url = join_name(&self.base, name)?;
+        let path = url.to_file_path().map_err(|_| FetchError::Transport {
+            location: url.to_string(),
+            message: "not a local file path".to_string(),
+        })?;
+        read_file(&path)
+    }
+}
+
+/// Minimal HTTP GET transport, so callers can plug in their own client.
+pub trait HttpClient {
+    fn get(&self, url: &Url) -> Result<Vec<u8>, FetchError>;
+}
+
+/// Fetches `<base>/<name>` over an `http`/`https` base URL.
+#[derive(Debug, Clone)]
+pub struct HttpFetcher<C> {
+    base: Url,
+    client: C,
+}
+
+impl<C: HttpClient> HttpFetcher<C> {
+    pub fn new(base: Url, client: C) -> Result<Self, FetchError> {
+        if !matches!(base.scheme(), "http" | "https") {
+            return Err(FetchError::Transport {
+                location: base.to_string(),
+                message: "expected an http:// or https:// URL".to_string(),
+            });
+        }
+        Ok(Self { base, client })
+    }
+}
+
+impl<C: HttpClient> ArtifactFetcher for HttpFetcher<C> {
+    fn fetch(&self, name: &str, _entry: &SnapshotEntry) -> Result<Vec<u8>, FetchError> {
+        self.client.get(&join_name(&self.base, name)?)
+    }
+}
+
+/// In-memory fetcher keyed by entry name.
+impl ArtifactFetcher for BTreeMap<String, Vec<u8>> {
+    fn fetch(&self, name: &str, _entry: &SnapshotEntry) -> Result<Vec<u8>, FetchError> {
+        self.get(name).cloned().ok_or_else(|| FetchError::NotFound {
+            location: name.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResolveError {
+    #[error("registry snapshot rejected: {0}")]
+    Snapshot(#[source] SpecError),
+    #[error("no snapshot entry named {0}")]
+    UnknownEntry(String),
+    #[error("invalid snapshot entry name {0:?}")]
+    InvalidEntryName(String),
+    #[error("fetching {name} failed: {source}")]
+    Fetch {
+        name: String,
+        #[source]
+        source: FetchError,
+    },
+    #[error("md5 mismatch for {name}: expected={expected} actual={actual}")]
+    Md5Mismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("sha256 mismatch for {name}: expected={expected} actual={actual}")]
+    Sha256Mismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("registry snapshot has no trusted signature: {}", display_rejected(.0))]
+    UntrustedSnapshot(Vec<RejectedSignature>),
+    #[error("registry snapshot rollback: timestamp {offered} is older than accepted {accepted}")]
+    Rollback { accepted: u64, offered: u64 },
+    #[error("registry snapshot conflicts with accepted snapshot at timestamp {timestamp}")]
+    ConflictingSnapshot { timestamp: u64 },
+    #[error(
+        "registry snapshot is stale: timestamp {timestamp} is more than {max_age}s before {now}"
+    )]
+    Frozen {
+        timestamp: u64,
+        now: u64,
+        max_age: u64,
+    },
+    #[error(
+        "registry snapshot timestamp {timestamp} is more than {MAX_SNAPSHOT_CLOCK_SKEW}s after {now}"
+    )]
+    FutureTimestamp { timestamp: u64, now: u64 },
+    #[error("snapshot state {path}: {message}")]
+    State { path: PathBuf, message: String },
+    #[error("registry snapshot rejected: {0}")]
+    InvalidVersionedSnapshot(#[source] SnapshotFormatError),
+    #[error("no skill named {0} in snapshot")]
+    UnknownSkill(String),
+    #[error("no version of {name} matches {query}")]
+    NoMatchingVersion { name: String, query: String },
+}
+
+impl ResolveError {
+    /// Stable machine-readable code, as used in `test-vectors/registry/resolve/`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ResolveError::Snapshot(_) => "invalid_snapshot",
+            ResolveError::UnknownEntry(_) => "unknown_entry",
+            ResolveError::InvalidEntryName(_) => "invalid_entry_name",
+            ResolveError::Fetch { .. } => "fetch_failed",
+            ResolveError::Md5Mismatch { .. } => "md5_mismatch",
+            ResolveError::Sha256Mismatch { .. } => "sha256_mismatch",
+            ResolveError::UntrustedSnapshot(_) => "bad_signature",
+            ResolveError::Rollback { .. } => "rollback",
+            ResolveError::ConflictingSnapshot { .. } => "conflicting_snapshot",
+            ResolveError::Frozen { .. } => "freeze",
+            ResolveError::FutureTimestamp { .. } => "future_timestamp",
+            ResolveError::State { .. } => "invalid_state",
+            ResolveError::InvalidVersionedSnapshot(_) => "invalid_snapshot",
+            ResolveError::UnknownSkill(_) => "unknown_skill",
+            ResolveError::NoMatchingVersion { .. } => "no_matching_version",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotResolveVector {
+    pub name: String,
+    pub snapshot: RegistrySnapshot,
+    pub artifacts: String,
+    pub cases: Vec<SnapshotResolveCase>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotResolveCase {
+    pub entry: String,
+    pub expect: String,
+}
+
+/// Registry snapshot, in either format, with detached signatures over its
+/// `snapshot_hash` (`spec/registry/signed-snapshot.schema.json`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignedSnapshot {
+    pub snapshot: AnySnapshot,
+    pub signatures: Vec<SignatureEntry>,
+}
+
+/// Last accepted snapshot, persisted between resolutions to detect rollback.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotState {
+    pub timestamp: u64,
+    pub snapshot_hash: String,
+}
+
+impl SnapshotState {
+    /// Reads recorded state; a missing file means no snapshot was accepted yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>, ResolveError> {
+        let path = path.as_ref();
+        let state_error = |message: String| ResolveError::State {
+            path: path.to_path_buf(),
+            message,
+        };
+        let raw = match std::fs::read(path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(state_error(err.to_string())),
+        };
+        serde_json::from_slice(&raw)
+            .map(Some)
+            .map_err(|err| state_error(err.to_string()))
+    }
+
+    /// Replaces recorded state via write-then-rename, so an interrupted save
+    /// leaves the previous state intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ResolveError> {
+        let path = path.as_ref();
+        let state_error = |message: String| ResolveError::State {
+            path: path.to_path_buf(),
+            message,
+        };
+        let mut raw =
+            serde_json::to_vec_pretty(self).map_err(|err| state_error(err.to_string()))?;
+        raw.push(b'\n');
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, raw).map_err(|err| state_error(err.to_string()))?;
+        std::fs::rename(&tmp, path).map_err(|err| state_error(err.to_string()))
+    }
+}
+
+/// Seconds a signed snapshot's `timestamp` may be ahead of the verifier's
+/// clock. A later timestamp would pass the freeze check indefinitely.
+pub const MAX_SNAPSHOT_CLOCK_SKEW: u64 = 300;
+
+/// Trust configuration for signed snapshots.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotTrust<'a> {
+    pub anchors: &'a TrustAnchors,
+    /// Trust-anchor signer identity of the registry.
+    pub registry_signer: &'a str,
+    /// Maximum accepted age in seconds of `snapshot.timestamp` relative to now.
+    pub max_age: u64,
+}
+
+/// Verifies a signed snapshot at UNIX time `now` against the registry trust
+/// anchor and the previously accepted state, and returns the state to record.
+///
+/// Checks run in order: snapshot hash, registry signature, rollback against
+/// `previous`, then freshness.
+pub fn verify_signed_snapshot(
+    signed: &SignedSnapshot,
+    trust: &SnapshotTrust<'_>,
+    previous: Option<&SnapshotState>,
+    now: u64,
+) -> Result<SnapshotState, ResolveError> {
+    let snapshot = &signed.snapshot;
+    match snapshot {
+        AnySnapshot::V0(snapshot) => {
+            verify_snapshot_hash(snapshot).map_err(ResolveError::Snapshot)?
+        }
+        AnySnapshot::V1(snapshot) => {
+            verify_snapshot_v1_hash(snapshot).map_err(ResolveError::InvalidVersionedSnapshot)?
+        }
+    }
+
+    let mut rejected = Vec::new();
+    let mut trusted = false;
+    for entry in &signed.signatures {
+        let result = if entry.signer != trust.registry_signer {
+            Err(SignatureRejection::UnknownSigner)
+        } else if entry.algorithm != "ed25519" {
+            Err(SignatureRejection::UnsupportedAlgorithm(
+                entry.algorithm.clone(),
+            ))
+        } else {
+            trust
+                .anchors
+                .verify(
+                    &entry.signer,
+                    snapshot.snapshot_hash().as_bytes(),
+                    &entry.signature,
+                    now,
+                )
+                .map(|_| ())
+        };
+        match result {
+            Ok(()) => trusted = true,
+            Err(reason) => rejected.push(RejectedSignature {
+                signer: entry.signer.clone(),
+                reason,
+            }),
+        }
+    }
+    if !trusted {
+        return Err(ResolveError::UntrustedSnapshot(rejected));
+    }
+
+    let timestamp = snapshot.timestamp();
+    if let Some(previous) = previous {
+        if timestamp < previous.timestamp {
+            return Err(ResolveError::Rollback {
+                accepted: previous.timestamp,
+                offered: timestamp,
+            });
+        }
+        if timestamp == previous.timestamp && snapshot.snapshot_hash() != previous.snapshot_hash {
+            return Err(ResolveError::ConflictingSnapshot { timestamp });
+        }
+    }
+    if timestamp > now.saturating_add(MAX_SNAPSHOT_CLOCK_SKEW) {
+        return Err(ResolveError::FutureTimestamp { timestamp, now });
+    }
+    if now.saturating_sub(timestamp) > trust.max_age {
+        return Err(ResolveError::Frozen {
+            timestamp,
+            now,
+            max_age: trust.max_age,
+        });
+    }
+
+    Ok(SnapshotState {
+        timestamp,
+        snapshot_hash: snapshot.snapshot_hash().to_string(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignedSnapshotVector {
+    pub name: String,
+    pub now: u64,
+    pub max_age: u64,
+    pub registry_signer: String,
+    pub trust_anchors: serde_json::Value,
+    #[serde(default)]
+    pub previous: Option<SnapshotState>,
+    pub signed_snapshot: SignedSnapshot,
+    pub expect: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Runs [`verify_signed_snapshot`] against the state recorded at
+/// `state_path` and records the accepted state. Both snapshot formats share
+/// one state file, so switching formats cannot reset rollback protection.
+fn accept_signed(
+    signed: &SignedSnapshot,
+    trust: &SnapshotTrust<'_>,
+    state_path: &Path,
+    now: u64,
+) -> Result<(), ResolveError> {
+    let previous = SnapshotState::load(state_path)?;
+    let accepted = verify_signed_snapshot(signed, trust, previous.as_ref(), now)?;
+    if previous.as_ref() != Some(&accepted) {
+        accepted.save(state_path)?;
+    }
+    Ok(())
+}
+
+/// Entry names become path segments for fetchers, so they must not be able to
+/// address anything outside the registry location.
+fn validate_entry_name(name: &str) -> Result<(), ResolveError> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        return Err(ResolveError::InvalidEntryName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Resolves snapshot entries to artifact bytes, failing closed.
+#[derive(Debug)]
+pub struct SnapshotResolver<F> {
+    snapshot: RegistrySnapshot,
+    fetcher: F,
+}
+
+impl<F: ArtifactFetcher> SnapshotResolver<F> {
+    /// Verifies `snapshot_hash` and entry digest formats before accepting the
+    /// snapshot.
+    pub fn new(snapshot: RegistrySnapshot, fetcher: F) -> Result<Self, ResolveError> {
+        verify_snapshot_hash(&snapshot).map_err(ResolveError::Snapshot)?;
+        Ok(Self { snapshot, fetcher })
+    }
+
+    /// Accepts a signed v0 snapshot only if it passes
+    /// [`verify_signed_snapshot`] against the state recorded at `state_path`,
+    /// then records it there.
+    pub fn from_signed(
+        signed: SignedSnapshot,
+        fetcher: F,
+        trust: &SnapshotTrust<'_>,
+        state_path: impl AsRef<Path>,
+        now: u64,
+    ) -> Result<Self, ResolveError> {
+        let AnySnapshot::V0(snapshot) = &signed.snapshot else {
+            return Err(ResolveError::InvalidVersionedSnapshot(
+                SnapshotFormatError::UnsupportedVersion(REGISTRY_SNAPSHOT_V1),
+            ));
+        };
+        let snapshot = snapshot.clone();
+        accept_signed(&signed, trust, state_path.as_ref(), now)?;
+        Ok(Self { snapshot, fetcher })
+    }
+
+    pub fn snapshot(&self) -> &RegistrySnapshot {
+        &self.snapshot
+    }
+
+    /// Fetches `name` and returns its bytes only when they match both the
+    /// entry's `md5` transport checksum and its `sha256` identity digest.
+    pub fn resolve(&self, name: &str) -> Result<Vec<u8>, ResolveError> {
+        validate_entry_name(name)?;
+        let entry = self
+            .snapshot
+            .entries
+            .get(name)
+            .ok_or_else(|| ResolveError::UnknownEntry(name.to_string()))?;
+        fetch_verified(&self.fetcher, name, entry)
+    }
+}
+
+fn fetch_verified<F: ArtifactFetcher>(
+    fetcher: &F,
+    name: &str,
+    entry: &SnapshotEntry,
+) -> Result<Vec<u8>, ResolveError> {
+    let bytes = fetcher
+        .fetch(name, entry)
+        .map_err(|source| ResolveError::Fetch {
+            name: name.to_string(),
+            source,
+        })?;
+
+    let md5 = hex::encode(Md5::digest(&bytes));
+    if md5 != entry.md5 {
+        return Err(ResolveError::Md5Mismatch {
+            name: name.to_string(),
+            expected: entry.md5.clone(),
+            actual: md5,
+        });
+    }
+    let sha256 = sha256_prefixed(&bytes);
+    if sha256 != entry.sha256 {
+        return Err(ResolveError::Sha256Mismatch {
+            name: name.to_string(),
+            expected: entry.sha256.clone(),
+            actual: sha256,
+        });
+    }
+    Ok(bytes)
+}
+
+/// Artifact bytes for a version selected from a versioned snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedArtifact {
+    pub name: String,
+    pub version: Version,
+    /// Set when an exact query selected a yanked version.
+    pub yanked: Option<Yank>,
+    pub bytes: Vec<u8>,
+}
+
+/// Resolves `(name, version query)` against a v1 snapshot, failing closed.
+///
+/// Selection follows [`RegistrySnapshotV1::select`]; the selected version is
+/// fetched as entry name `<name>@<version>`.
+#[derive(Debug)]
+pub struct VersionedSnapshotResolver<F> {
+    snapshot: RegistrySnapshotV1,
+    fetcher: F,
+}
+
+impl<F: ArtifactFetcher> VersionedSnapshotResolver<F> {
+    pub fn new(snapshot: RegistrySnapshotV1, fetcher: F) -> Result<Self, ResolveError> {
+        verify_snapshot_v1_hash(&snapshot).map_err(ResolveError::InvalidVersionedSnapshot)?;
+        Ok(Self { snapshot, fetcher })
+    }
+
+    /// Accepts a signed snapshot like [`SnapshotResolver::from_signed`]; a
+    /// v0 payload is upgraded after its signature verifies.
+    pub fn from_signed(
+        signed: SignedSnapshot,
+        fetcher: F,
+        trust: &SnapshotTrust<'_>,
+        state_path: impl AsRef<Path>,
+        now: u64,
+    ) -> Result<Self, ResolveError> {
+        accept_signed(&signed, trust, state_path.as_ref(), now)?;
+        let snapshot = signed
+            .snapshot
+            .into_v1()
+            .map_err(ResolveError::InvalidVersionedSnapshot)?;
+        Ok(Self { snapshot, fetcher })
+    }
+
+    pub fn snapshot(&self) -> &RegistrySnapshotV1 {
+        &self.snapshot
+    }
+
+    pub fn resolve(
+        &self,
+        name: &str,
+        query: &VersionQuery,
+    ) -> Result<ResolvedArtifact, ResolveError> {
+        if !self.snapshot.skills.contains_key(name) {
+            return Err(ResolveError::UnknownSkill(name.to_string()));
+        }
+        let selected =
+            self.snapshot
+                .select(name, query)
+                .ok_or_else(|| ResolveError::NoMatchingVersion {
+                    name: name.to_string(),
+                    query: query.to_string(),
+                })?;
+        let entry_name = format!("{name}@{}", selected.key);
+        validate_entry_name(&entry_name)?;
+        let bytes = fetch_verified(&self.fetcher, &entry_name, &selected.entry.digests())?;
+        Ok(ResolvedArtifact {
+            name: name.to_string(),
+            version: selected.version,
+            yanked: selected.entry.yanked.clone(),
+            bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    fn vector() -> serde_json::Value {
+        serde_json::from_str(include_str!(
+            "../../../test-vectors/registry/resolve/local-dir.json"
+        ))
+        .unwrap()
+    }
+
+    fn snapshot() -> RegistrySnapshot {
+        serde_json::from_value(vector()["snapshot"].clone()).unwrap()
+    }
+
+    fn artifacts_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-vectors/registry/resolve/artifacts")
+    }
+
+    #[test]
+    fn local_dir_resolution_matches_vector_cases() {
+        let resolver =
+            SnapshotResolver::new(snapshot(), LocalDirFetcher::new(artifacts_dir())).unwrap();
+        for case in vector()["cases"].as_array().unwrap() {
+            let name = case["entry"].as_str().unwrap();
+            let got = match resolver.resolve(name) {
+                Ok(_) => "ok",
+                Err(err) => err.code(),
+            };
+            assert_eq!(got, case["expect"], "{name}");
+        }
+        assert_eq!(resolver.resolve("echo@1.0.0").unwrap(), b"echo skill v1\n");
+    }
+
+    #[test]
+    fn file_url_fetcher_resolves_relative_to_base() {
+        let base = Url::from_directory_path(artifacts_dir().canonicalize().unwrap()).unwrap();
+        let resolver =
+            SnapshotResolver::new(snapshot(), FileUrlFetcher::new(base).unwrap()).unwrap();
+        assert!(resolver.resolve("echo@1.0.0").is_ok());
+        assert!(matches!(
+            resolver.resolve("missing@1.0.0"),
+            Err(ResolveError::Fetch {
+                source: FetchError::NotFound { .. },
+                ..
+            })
+        ));
+    }
+
+    struct StandInHttp(BTreeMap<String, Vec<u8>>);
+
+    impl HttpClient for StandInHttp {
+        fn get(&self, url: &Url) -> Result<Vec<u8>, FetchError> {
+            self.0
+                .get(url.as_str())
+                .cloned()
+                .ok_or_else(|| FetchError::Transport {
+                    location: url.to_string(),
+                    message: "404".to_string(),
+                })
+        }
+    }
+
+    #[test]
+    fn http_fetcher_checks_served_bytes() {
+        let client = StandInHttp(BTreeMap::from([
+            (
+                "https://registry.example/artifacts/echo@1.0.0".to_string(),
+                b"echo skill v1\n".to_vec(),
+            ),
+            (
+                "https://registry.example/artifacts/sha256-mismatch@1.0.0".to_string(),
+                b"tampered\n".to_vec(),
+            ),
+        ]));
+        let base = Url::parse("https://registry.example/artifacts/").unwrap();
+        let resolver =
+            SnapshotResolver::new(snapshot(), HttpFetcher::new(base, client).unwrap()).unwrap();
+        assert!(resolver.resolve("echo@1.0.0").is_ok());
+        // Substituted bytes fail the transport checksum before the identity digest.
+        assert!(matches!(
+            resolver.resolve("sha256-mismatch@1.0.0"),
+            Err(ResolveError::Md5Mismatch { .. })
+        ));
+        assert!(HttpFetcher::new(
+            Url::parse("ftp://registry.example/").unwrap(),
+            StandInHttp(BTreeMap::new())
+        )
+        .is_err());
+    }
+
+    macro_rules! signed_vector {
+        ($file:literal) => {
+            serde_json::from_str::<SignedSnapshotVector>(include_str!(concat!(
+                "../../../test-vectors/registry/signed/",
+                $file
+            )))
+            .unwrap()
+        };
+    }
+
+    fn check_signed(vector: &SignedSnapshotVector) -> Result<SnapshotState, ResolveError> {
+        let anchors =
+            TrustAnchors::from_bytes_unpinned(&serde_json::to_vec(&vector.trust_anchors).unwrap())
+                .unwrap();
+        let trust = SnapshotTrust {
+            anchors: &anchors,
+            registry_signer: &vector.registry_signer,
+            max_age: vector.max_age,
+        };
+        verify_signed_snapshot(
+            &vector.signed_snapshot,
+            &trust,
+            vector.previous.as_ref(),
+            vector.now,
+        )
+    }
+
+    #[test]
+    fn signed_snapshot_vectors_reject_rollback_freeze_and_bad_signatures() {
+        let accepted = check_signed(&signed_vector!("accept-newer-snapshot.json")).unwrap();
+        assert_eq!(accepted.timestamp, 1767398400);
+        for (vector, code) in [
+            (signed_vector!("rollback.json"), "rollback"),
+            (signed_vector!("freeze.json"), "freeze"),
+            (signed_vector!("bad-signature.json"), "bad_signature"),
+            (signed_vector!("substituted-entries.json"), "bad_signature"),
+        ] {
+            assert_eq!(
+                check_signed(&vector).unwrap_err().code(),
+                code,
+                "{}",
+                vector.name
+            );
+        }
+    }
+
+    #[test]
+    fn from_signed_records_state_and_blocks_replay() {
+        let newer = signed_vector!("accept-first-snapshot.json");
+        let older = signed_vector!("rollback.json");
+        let anchors =
+            TrustAnchors::from_bytes_unpinned(&serde_json::to_vec(&newer.trust_anchors).unwrap())
+                .unwrap();
+        let trust = SnapshotTrust {
+            anchors: &anchors,
+            registry_signer: &newer.registry_signer,
+            max_age: newer.max_age,
+        };
+        let state_path = std::env::temp_dir().join(format!(
+            "provenact-snapshot-state-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&state_path);
+
+        SnapshotResolver::from_signed(
+            newer.signed_snapshot,
+            BTreeMap::new(),
+            &trust,
+            &state_path,
+            newer.now,
+        )
+        .unwrap();
+        let recorded = SnapshotState::load(&state_path).unwrap().unwrap();
+        assert_eq!(recorded.timestamp, 1767398400);
+
+        let err = SnapshotResolver::from_signed(
+            older.signed_snapshot,
+            BTreeMap::new(),
+            &trust,
+            &state_path,
+            older.now,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ResolveError::Rollback { .. }));
+        assert_eq!(SnapshotState::load(&state_path).unwrap(), Some(recorded));
+        std::fs::remove_file(&state_path).unwrap();
+    }
+
+    #[test]
+    fn versioned_from_signed_shares_rollback_state_with_v0() {
+        let v0 = signed_vector!("accept-first-snapshot.json");
+        let v1 = signed_vector!("accept-v1-snapshot.json");
+        let anchors =
+            TrustAnchors::from_bytes_unpinned(&serde_json::to_vec(&v1.trust_anchors).unwrap())
+                .unwrap();
+        let trust = SnapshotTrust {
+            anchors: &anchors,
+            registry_signer: &v1.registry_signer,
+            max_age: v1.max_age,
+        };
+        let state_path = std::env::temp_dir().join(format!(
+            "provenact-snapshot-state-v1-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&state_path);
+
+        let resolver = VersionedSnapshotResolver::from_signed(
+            v1.signed_snapshot.clone(),
+            BTreeMap::new(),
+            &trust,
+            &state_path,
+            v1.now,
+        )
+        .unwrap();
+        assert!(resolver.snapshot().skills.contains_key("echo"));
+        assert!(matches!(
+            SnapshotResolver::from_signed(
+                v1.signed_snapshot,
+                BTreeMap::new(),
+                &trust,
+                &state_path,
+                v1.now,
+            ),
+            Err(ResolveError::InvalidVersionedSnapshot(
+                SnapshotFormatError::UnsupportedVersion(1)
+            ))
+        ));
+
+        // The v0 snapshot has the same timestamp but a different hash.
+        let err = VersionedSnapshotResolver::from_signed(
+            v0.signed_snapshot,
+            BTreeMap::new(),
+            &trust,
+            &state_path,
+            v0.now,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ResolveError::ConflictingSnapshot { .. }));
+        std::fs::remove_file(&state_path).unwrap();
+    }
+
+    #[test]
+    fn versioned_resolver_selects_then_checks_digests() {
+        let mut skills = BTreeMap::new();
+        let mut versions = BTreeMap::new();
+        let bytes = b"echo skill v1\n".to_vec();
+        let entry = SnapshotEntry {
+            sha256: sha256_prefixed(&bytes),
+            md5: hex::encode(Md5::digest(&bytes)),
+        };
+        for (version, yanked) in [("1.0.0", None), ("1.1.0", Some("broken release"))] {
+            versions.insert(
+                version.to_string(),
+                crate::VersionedEntry {
+                    sha256: entry.sha256.clone(),
+                    md5: entry.md5.clone(),
+                    yanked: yanked.map(|reason| Yank {
+                        reason: reason.to_string(),
+                    }),
+                },
+            );
+        }
+        skills.insert("echo".to_string(), crate::SkillVersions { versions });
+        let mut snapshot = RegistrySnapshotV1 {
+            version: 1,
+            timestamp: 1767225600,
+            skills,
+            unversioned_entries: BTreeMap::new(),
+            snapshot_hash: String::new(),
+        };
+        snapshot.snapshot_hash = crate::compute_snapshot_v1_hash(&snapshot).unwrap();
+        let fetcher = BTreeMap::from([
+            ("echo@1.0.0".to_string(), bytes.clone()),
+            ("echo@1.1.0".to_string(), b"tampered".to_vec()),
+        ]);
+        let resolver = VersionedSnapshotResolver::new(snapshot, fetcher).unwrap();
+
+        let resolved = resolver
+            .resolve("echo", &VersionQuery::parse("^1").unwrap())
+            .unwrap();
+        assert_eq!(resolved.version, Version::new(1, 0, 0));
+        assert_eq!(resolved.bytes, bytes);
+        assert!(resolved.yanked.is_none());
+        assert_eq!(
+            resolver
+                .resolve("echo", &VersionQuery::parse("1.1.0").unwrap())
+                .unwrap_err()
+                .code(),
+            "md5_mismatch"
+        );
+        assert_eq!(
+            resolver
+                .resolve("other", &VersionQuery::parse("*").unwrap())
+                .unwrap_err()
+                .code(),
+            "unknown_skill"
+        );
+    }
+
+    #[test]
+    fn tampered_snapshot_is_rejected_up_front() {
+        let snapshot: RegistrySnapshot = parse_json(include_str!(
+            "../../../test-vectors/registry/snapshot/bad/invalid_entry_digest.json"
+        ))
+        .unwrap();
+        assert!(matches!(
+            SnapshotResolver::new(snapshot, BTreeMap::new()),
+            Err(ResolveError::Snapshot(
+                SpecError::InvalidMd5(_) | SpecError::InvalidSha256(_)
+            ))
+        ));
+    }
+}
@@ -0,0 +1,431 @@
+//! SPDX 2.3 JSON SBOM model, artifact binding, and install-time SBOM policy checks.
+
+use crate::{parse_json, validate_sha256_prefixed, Manifest, SpecError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const SPDX_VERSION: &str = "SPDX-2.3";
+pub const SPDX_DOCUMENT_ID: &str = "SPDXRef-DOCUMENT";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SbomError {
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("unsupported spdx version: {0}")]
+    UnsupportedSpdxVersion(String),
+    #[error("sbom does not describe any package")]
+    NoDescribedPackage,
+    #[error("sbom describes {0} packages; expected exactly one")]
+    AmbiguousDescribedPackage(usize),
+    #[error("described package {0} is not present in sbom packages")]
+    UnknownDescribedPackage(String),
+    #[error("described package {0} has no SHA256 checksum")]
+    MissingSha256Checksum(String),
+    #[error("sbom artifact mismatch: manifest={expected} sbom={actual}")]
+    ArtifactMismatch { expected: String, actual: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxDocument {
+    pub spdx_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_license: Option<String>,
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation_info: Option<SpdxCreationInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub document_describes: Vec<String>,
+    #[serde(default)]
+    pub packages: Vec<SpdxPackage>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relationships: Vec<SpdxRelationship>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxCreationInfo {
+    pub created: String,
+    #[serde(default)]
+    pub creators: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license_list_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxPackage {
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_info: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supplier: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checksums: Vec<SpdxChecksum>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license_concluded: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license_declared: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub external_refs: Vec<SpdxExternalRef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxChecksum {
+    pub algorithm: String,
+    pub checksum_value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxRelationship {
+    pub spdx_element_id: String,
+    pub relationship_type: String,
+    pub related_spdx_element: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxExternalRef {
+    pub reference_category: String,
+    pub reference_type: String,
+    pub reference_locator: String,
+}
+
+impl SpdxPackage {
+    pub fn sha256(&self) -> Option<String> {
+        self.checksums
+            .iter()
+            .find(|c| c.algorithm == "SHA256")
+            .map(|c| format!("sha256:{}", c.checksum_value.to_ascii_lowercase()))
+    }
+}
+
+impl SpdxDocument {
+    /// Packages named by `documentDescribes` or by `SPDXRef-DOCUMENT DESCRIBES`
+    /// relationships, in first-seen order.
+    pub fn described_packages(&self) -> Result<Vec<&SpdxPackage>, SbomError> {
+        let mut ids: Vec<&str> = self.document_describes.iter().map(String::as_str).collect();
+        for rel in &self.relationships {
+            if rel.spdx_element_id == SPDX_DOCUMENT_ID
+                && rel.relationship_type == "DESCRIBES"
+                && !ids.contains(&rel.related_spdx_element.as_str())
+            {
+                ids.push(&rel.related_spdx_element);
+            }
+        }
+        ids.into_iter()
+            .map(|id| {
+                self.packages
+                    .iter()
+                    .find(|p| p.spdx_id == id)
+                    .ok_or_else(|| SbomError::UnknownDescribedPackage(id.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// Parses `sbom.spdx.json`, accepting SPDX 2.3 documents only.
+pub fn parse_spdx(raw: &str) -> Result<SpdxDocument, SbomError> {
+    let doc: SpdxDocument = parse_json(raw)?;
+    if doc.spdx_version != SPDX_VERSION {
+        return Err(SbomError::UnsupportedSpdxVersion(doc.spdx_version));
+    }
+    Ok(doc)
+}
+
+/// Verifies that the single described package carries a SHA256 checksum equal
+/// to `manifest.artifact`, and returns it.
+pub fn verify_sbom_artifact<'a>(
+    sbom: &'a SpdxDocument,
+    manifest: &Manifest,
+) -> Result<&'a SpdxPackage, SbomError> {
+    validate_sha256_prefixed(&manifest.artifact)?;
+    let described = sbom.described_packages()?;
+    let package = match described.as_slice() {
+        [] => return Err(SbomError::NoDescribedPackage),
+        [package] => *package,
+        many => return Err(SbomError::AmbiguousDescribedPackage(many.len())),
+    };
+    let actual = package
+        .sha256()
+        .ok_or_else(|| SbomError::MissingSha256Checksum(package.spdx_id.clone()))?;
+    validate_sha256_prefixed(&actual)?;
+    if actual != manifest.artifact {
+        return Err(SbomError::ArtifactMismatch {
+            expected: manifest.artifact.clone(),
+            actual,
+        });
+    }
+    Ok(package)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SbomPolicy {
+    #[serde(default)]
+    pub require_sbom: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_licenses: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_licenses: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_packages: Vec<PackageDenyRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageDenyRule {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SbomViolation {
+    MissingSbom,
+    DeniedLicense {
+        package: String,
+        license: String,
+    },
+    UnapprovedLicense {
+        package: String,
+        license: String,
+    },
+    DeniedPackage {
+        package: String,
+        version: Option<String>,
+    },
+}
+
+impl fmt::Display for SbomViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSbom => write!(f, "sbom required by policy but not present"),
+            Self::DeniedLicense { package, license } => {
+                write!(f, "package {package} declares denied license {license}")
+            }
+            Self::UnapprovedLicense { package, license } => {
+                write!(f, "package {package} declares unapproved license {license}")
+            }
+            Self::DeniedPackage {
+                package,
+                version: Some(version),
+            } => write!(f, "package {package}@{version} is denylisted"),
+            Self::DeniedPackage {
+                package,
+                version: None,
+            } => write!(f, "package {package} is denylisted"),
+        }
+    }
+}
+
+/// License ids named by an SPDX expression. The exception after `WITH` is
+/// not a license id and is skipped.
+fn license_ids(expression: &str) -> Vec<&str> {
+    let mut ids = Vec::new();
+    let mut tokens = expression
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .filter(|token| !token.is_empty());
+    while let Some(token) = tokens.next() {
+        match token {
+            "AND" | "OR" => {}
+            "WITH" => {
+                tokens.next();
+            }
+            id => ids.push(id),
+        }
+    }
+    ids
+}
+
+/// Evaluates every SBOM package against `policy`. An empty result allows
+/// installation.
+///
+/// License checks use `licenseDeclared`. Every license id in a compound
+/// expression must pass, so `MIT OR GPL-3.0-only` is denied when
+/// `GPL-3.0-only` is denied. License exceptions are not checked:
+/// `Apache-2.0 WITH LLVM-exception` passes when `Apache-2.0` does. With a
+/// non-empty `allowed_licenses`, a missing, `NOASSERTION`, or `NONE`
+/// declaration is unapproved.
+pub fn evaluate_sbom_policy(
+    policy: &SbomPolicy,
+    sbom: Option<&SpdxDocument>,
+) -> Vec<SbomViolation> {
+    let Some(sbom) = sbom else {
+        return if policy.require_sbom {
+            vec![SbomViolation::MissingSbom]
+        } else {
+            Vec::new()
+        };
+    };
+    let mut violations = Vec::new();
+    for package in &sbom.packages {
+        let declared = package.license_declared.as_deref().unwrap_or("NOASSERTION");
+        for license in license_ids(declared) {
+            if policy.denied_licenses.iter().any(|x| x == license) {
+                violations.push(SbomViolation::DeniedLicense {
+                    package: package.name.clone(),
+                    license: license.to_string(),
+                });
+            } else if !policy.allowed_licenses.is_empty()
+                && !policy.allowed_licenses.iter().any(|x| x == license)
+            {
+                violations.push(SbomViolation::UnapprovedLicense {
+                    package: package.name.clone(),
+                    license: license.to_string(),
+                });
+            }
+        }
+        let denied = policy.denied_packages.iter().any(|rule| {
+            rule.name == package.name
+                && (rule.versions.is_empty()
+                    || package
+                        .version_info
+                        .as_ref()
+                        .is_some_and(|v| rule.versions.contains(v)))
+        });
+        if denied {
+            violations.push(SbomViolation::DeniedPackage {
+                package: package.name.clone(),
+                version: package.version_info.clone(),
+            });
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> Manifest {
+        Manifest {
+            name: "echo.minimal".to_string(),
+            version: "0.1.0".to_string(),
+            entrypoint: "run".to_string(),
+            artifact: format!("sha256:{}", "a".repeat(64)),
+            capabilities: vec![],
+            signers: vec!["alice.dev".to_string()],
+        }
+    }
+
+    fn sbom(described_sha: &str) -> SpdxDocument {
+        let raw = serde_json::json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": "echo.minimal",
+            "packages": [
+                {
+                    "SPDXID": "SPDXRef-skill",
+                    "name": "echo.minimal",
+                    "versionInfo": "0.1.0",
+                    "checksums": [{"algorithm": "SHA256", "checksumValue": described_sha}],
+                    "licenseDeclared": "MIT OR Apache-2.0"
+                },
+                {
+                    "SPDXID": "SPDXRef-dep-1",
+                    "name": "left-pad",
+                    "versionInfo": "1.3.0",
+                    "licenseDeclared": "WTFPL"
+                }
+            ],
+            "relationships": [{
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": "SPDXRef-skill"
+            }]
+        });
+        parse_spdx(&raw.to_string()).unwrap()
+    }
+
+    #[test]
+    fn described_package_checksum_binds_manifest_artifact() {
+        let good = sbom(&"a".repeat(64));
+        assert_eq!(
+            verify_sbom_artifact(&good, &manifest()).unwrap().spdx_id,
+            "SPDXRef-skill"
+        );
+
+        let bad = sbom(&"b".repeat(64));
+        assert!(matches!(
+            verify_sbom_artifact(&bad, &manifest()),
+            Err(SbomError::ArtifactMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_spdx_rejects_other_versions() {
+        let raw = serde_json::json!({
+            "spdxVersion": "SPDX-2.2",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": "x"
+        });
+        assert!(matches!(
+            parse_spdx(&raw.to_string()),
+            Err(SbomError::UnsupportedSpdxVersion(v)) if v == "SPDX-2.2"
+        ));
+    }
+
+    #[test]
+    fn sbom_policy_denies_licenses_and_packages() {
+        let doc = sbom(&"a".repeat(64));
+        let policy = SbomPolicy {
+            denied_licenses: vec!["WTFPL".to_string()],
+            denied_packages: vec![PackageDenyRule {
+                name: "left-pad".to_string(),
+                versions: vec!["1.3.0".to_string()],
+            }],
+            ..SbomPolicy::default()
+        };
+        assert_eq!(
+            evaluate_sbom_policy(&policy, Some(&doc)),
+            vec![
+                SbomViolation::DeniedLicense {
+                    package: "left-pad".to_string(),
+                    license: "WTFPL".to_string(),
+                },
+                SbomViolation::DeniedPackage {
+                    package: "left-pad".to_string(),
+                    version: Some("1.3.0".to_string()),
+                },
+            ]
+        );
+
+        let allowlist = SbomPolicy {
+            allowed_licenses: vec!["MIT".to_string(), "Apache-2.0".to_string()],
+            ..SbomPolicy::default()
+        };
+        assert_eq!(
+            evaluate_sbom_policy(&allowlist, Some(&doc)),
+            vec![SbomViolation::UnapprovedLicense {
+                package: "left-pad".to_string(),
+                license: "WTFPL".to_string(),
+            }]
+        );
+
+        let mut with_exception = doc.clone();
+        with_exception.packages[1].license_declared =
+            Some("(Apache-2.0 WITH LLVM-exception) AND MIT".to_string());
+        assert!(evaluate_sbom_policy(&allowlist, Some(&with_exception)).is_empty());
+
+        let required = SbomPolicy {
+            require_sbom: true,
+            ..SbomPolicy::default()
+        };
+        assert_eq!(
+            evaluate_sbom_policy(&required, None),
+            vec![SbomViolation::MissingSbom]
+        );
+    }
+}
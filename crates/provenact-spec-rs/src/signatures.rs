@@ -0,0 +1,221 @@
+//! `signatures.json` verification against trust anchors (`spec/hashing.md`).
+
+use crate::{
+    compute_manifest_hash, validate_sha256_prefixed, Manifest, SignatureRejection, Signatures,
+    SpecError, TrustAnchors,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureError {
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("signatures artifact mismatch: manifest={manifest} signatures={signatures}")]
+    ArtifactMismatch {
+        manifest: String,
+        signatures: String,
+    },
+    #[error("signatures manifest_hash mismatch: expected={expected} actual={actual}")]
+    ManifestHashMismatch { expected: String, actual: String },
+    #[error("no trusted signature: {}", display_rejected(.0))]
+    NoTrustedSignature(Vec<RejectedSignature>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedSignature {
+    pub signer: String,
+    pub key_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedSignature {
+    pub signer: String,
+    pub reason: SignatureRejection,
+}
+
+impl fmt::Display for RejectedSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.signer, self.reason)
+    }
+}
+
+pub(crate) fn display_rejected(rejected: &[RejectedSignature]) -> String {
+    if rejected.is_empty() {
+        return "no signatures".to_string();
+    }
+    rejected
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignatureVerification {
+    pub verified: Vec<VerifiedSignature>,
+    pub rejected: Vec<RejectedSignature>,
+}
+
+impl SignatureVerification {
+    pub fn signers(&self) -> impl Iterator<Item = &str> {
+        self.verified.iter().map(|v| v.signer.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignerTrustVector {
+    pub name: String,
+    pub at: u64,
+    pub trust_anchors: Value,
+    pub manifest: Manifest,
+    pub signatures: Signatures,
+    pub expect: String,
+    #[serde(default)]
+    pub expect_signers: Vec<String>,
+    #[serde(default)]
+    pub expect_rejections: Vec<ExpectedRejection>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedRejection {
+    pub signer: String,
+    pub reason: String,
+}
+
+/// Verifies `signatures.json` for `manifest` against trust anchors at UNIX
+/// time `at`.
+///
+/// The envelope must bind `manifest.artifact` and the canonical manifest hash.
+/// Each entry is checked independently; verification succeeds when at least
+/// one entry verifies under a key that is valid at `at`, so artifacts signed
+/// only by revoked or expired keys are rejected.
+pub fn verify_signatures(
+    signatures: &Signatures,
+    manifest: &Manifest,
+    anchors: &TrustAnchors,
+    at: u64,
+) -> Result<SignatureVerification, SignatureError> {
+    validate_sha256_prefixed(&signatures.artifact)?;
+    validate_sha256_prefixed(&signatures.manifest_hash)?;
+    if signatures.artifact != manifest.artifact {
+        return Err(SignatureError::ArtifactMismatch {
+            manifest: manifest.artifact.clone(),
+            signatures: signatures.artifact.clone(),
+        });
+    }
+    let actual = compute_manifest_hash(manifest)?;
+    if actual != signatures.manifest_hash {
+        return Err(SignatureError::ManifestHashMismatch {
+            expected: signatures.manifest_hash.clone(),
+            actual,
+        });
+    }
+
+    let mut outcome = SignatureVerification::default();
+    for entry in &signatures.signatures {
+        let result = if entry.algorithm == "ed25519" {
+            anchors.verify(
+                &entry.signer,
+                signatures.manifest_hash.as_bytes(),
+                &entry.signature,
+                at,
+            )
+        } else {
+            Err(SignatureRejection::UnsupportedAlgorithm(
+                entry.algorithm.clone(),
+            ))
+        };
+        match result {
+            Ok(key) => {
+                let already = outcome
+                    .verified
+                    .iter()
+                    .any(|v| v.signer == key.signer && v.key_id == key.key_id);
+                if !already {
+                    outcome.verified.push(VerifiedSignature {
+                        signer: key.signer.clone(),
+                        key_id: key.key_id.clone(),
+                    });
+                }
+            }
+            Err(reason) => outcome.rejected.push(RejectedSignature {
+                signer: entry.signer.clone(),
+                reason,
+            }),
+        }
+    }
+    if outcome.verified.is_empty() {
+        return Err(SignatureError::NoTrustedSignature(outcome.rejected));
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    macro_rules! vector {
+        ($dir:literal, $file:literal) => {
+            include_str!(concat!("../../../test-vectors/", $dir, "/", $file))
+        };
+    }
+
+    fn bundle(
+        manifest: &str,
+        signatures: &str,
+        keys: &str,
+    ) -> (Manifest, Signatures, TrustAnchors) {
+        (
+            parse_json(manifest).unwrap(),
+            parse_json(signatures).unwrap(),
+            TrustAnchors::from_bytes_unpinned(keys.as_bytes()).unwrap(),
+        )
+    }
+
+    fn minimal() -> (Manifest, Signatures, TrustAnchors) {
+        bundle(
+            vector!("good/minimal-zero-cap", "manifest.json"),
+            vector!("good/minimal-zero-cap", "signatures.json"),
+            vector!("good/minimal-zero-cap", "public-keys.json"),
+        )
+    }
+
+    #[test]
+    fn minimal_bundle_vector_verifies() {
+        let (manifest, signatures, anchors) = minimal();
+        let outcome = verify_signatures(&signatures, &manifest, &anchors, 0).unwrap();
+        assert_eq!(outcome.signers().collect::<Vec<_>>(), vec!["alice.dev"]);
+    }
+
+    #[test]
+    fn bad_signature_vector_is_rejected() {
+        let (manifest, signatures, anchors) = bundle(
+            vector!("bad/bad-signature", "manifest.json"),
+            vector!("bad/bad-signature", "signatures.json"),
+            vector!("bad/bad-signature", "public-keys.json"),
+        );
+        let err = verify_signatures(&signatures, &manifest, &anchors, 0).unwrap_err();
+        assert!(matches!(
+            err,
+            SignatureError::NoTrustedSignature(rejected)
+                if rejected[0].reason == SignatureRejection::BadSignature
+        ));
+    }
+
+    #[test]
+    fn envelope_must_bind_manifest() {
+        let (manifest, mut signatures, anchors) = minimal();
+        signatures.manifest_hash = format!("sha256:{}", "0".repeat(64));
+        assert!(matches!(
+            verify_signatures(&signatures, &manifest, &anchors, 0),
+            Err(SignatureError::ManifestHashMismatch { .. })
+        ));
+    }
+}
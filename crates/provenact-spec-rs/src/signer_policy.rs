@@ -0,0 +1,367 @@
+//! Policy signer requirements: trusted signer baseline plus M-of-N `signer_rules`
+//! (`spec/policy/policy.md`).
+
+use crate::{Manifest, Policy, SignatureVerification, CAPABILITY_KINDS};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignerRule {
+    pub threshold: u64,
+    pub signers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignerPolicyVector {
+    pub name: String,
+    pub policy: Policy,
+    pub manifest: Manifest,
+    pub cases: Vec<SignerPolicyCase>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignerPolicyCase {
+    pub name: String,
+    #[serde(default)]
+    pub capabilities: Vec<Value>,
+    pub verified_signers: Vec<String>,
+    pub expect: String,
+    #[serde(default)]
+    pub expect_no_trusted_signer: bool,
+    #[serde(default)]
+    pub expect_unmet: Vec<ExpectedUnmetRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedUnmetRule {
+    pub rule: usize,
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SignerRuleError {
+    #[error("signer rule {0} has threshold 0")]
+    ZeroThreshold(usize),
+    #[error("signer rule {rule} threshold {threshold} exceeds {signers} listed signers")]
+    ThresholdExceedsSigners {
+        rule: usize,
+        threshold: u64,
+        signers: usize,
+    },
+    #[error("signer rule {rule} lists signer {signer} more than once")]
+    DuplicateSigner { rule: usize, signer: String },
+    #[error("signer rule {rule} names unknown capability kind {kind:?}")]
+    UnknownCapability { rule: usize, kind: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmetSignerRule {
+    pub rule: usize,
+    pub threshold: u64,
+    pub triggered_by: Vec<String>,
+    pub present: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl fmt::Display for UnmetSignerRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "signer rule {} requires {} of [{}]",
+            self.rule,
+            self.threshold,
+            self.present
+                .iter()
+                .chain(&self.missing)
+                .cloned()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+        if !self.triggered_by.is_empty() {
+            write!(f, " for {}", self.triggered_by.join(", "))?;
+        }
+        write!(
+            f,
+            "; signed by [{}]; missing [{}]",
+            self.present.join(", "),
+            self.missing.join(", ")
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub struct SignerPolicyDenial {
+    pub no_trusted_signer: bool,
+    pub unmet_rules: Vec<UnmetSignerRule>,
+}
+
+impl fmt::Display for SignerPolicyDenial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.no_trusted_signer {
+            parts.push("no verified signer is both in manifest.signers and trusted_signers".into());
+        }
+        parts.extend(self.unmet_rules.iter().map(ToString::to_string));
+        f.write_str(&parts.join("; "))
+    }
+}
+
+/// Checks `signer_rules` for internal consistency. Schema validation cannot
+/// express `threshold <= len(signers)`. Unknown capability kinds are
+/// rejected, since a rule naming one would never apply.
+pub fn validate_signer_rules(policy: &Policy) -> Result<(), SignerRuleError> {
+    for (index, rule) in policy.signer_rules.iter().enumerate() {
+        if rule.threshold == 0 {
+            return Err(SignerRuleError::ZeroThreshold(index));
+        }
+        if rule.threshold > rule.signers.len() as u64 {
+            return Err(SignerRuleError::ThresholdExceedsSigners {
+                rule: index,
+                threshold: rule.threshold,
+                signers: rule.signers.len(),
+            });
+        }
+        let mut seen = BTreeSet::new();
+        for signer in &rule.signers {
+            if !seen.insert(signer) {
+                return Err(SignerRuleError::DuplicateSigner {
+                    rule: index,
+                    signer: signer.clone(),
+                });
+            }
+        }
+        if let Some(kind) = rule
+            .capabilities
+            .iter()
+            .find(|kind| !CAPABILITY_KINDS.contains(&kind.as_str()))
+        {
+            return Err(SignerRuleError::UnknownCapability {
+                rule: index,
+                kind: kind.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn requested_kinds(manifest: &Manifest) -> BTreeSet<&str> {
+    manifest
+        .capabilities
+        .iter()
+        .filter_map(|cap| cap.get("kind").and_then(|kind| kind.as_str()))
+        .collect()
+}
+
+/// Whether a rule naming `rule_kind` applies to a requested `kind`: `exec`
+/// also covers `exec.safe`, which the same ceiling flag gates.
+fn rule_kind_applies(rule_kind: &str, kind: &str) -> bool {
+    rule_kind == kind || (rule_kind == "exec" && kind == "exec.safe")
+}
+
+/// Decides whether verified signatures satisfy the policy's signer
+/// requirements for `manifest`.
+///
+/// Only verified signers listed in `manifest.signers` count, and each signer
+/// counts once however many of its keys signed. At least one counted signer
+/// must be in `trusted_signers`. Each `signer_rules` entry with empty
+/// `capabilities`, or naming a capability kind the manifest requests (`exec`
+/// also naming `exec.safe`), must
+/// then be met by at least `threshold` counted signers from its `signers`.
+/// All unmet requirements are reported together.
+pub fn evaluate_signer_policy(
+    policy: &Policy,
+    manifest: &Manifest,
+    verification: &SignatureVerification,
+) -> Result<(), SignerPolicyDenial> {
+    let counted: BTreeSet<&str> = verification
+        .signers()
+        .filter(|signer| manifest.signers.iter().any(|s| s == signer))
+        .collect();
+    let no_trusted_signer = !policy
+        .trusted_signers
+        .iter()
+        .any(|s| counted.contains(s.as_str()));
+
+    let kinds = requested_kinds(manifest);
+    let mut unmet_rules = Vec::new();
+    for (index, rule) in policy.signer_rules.iter().enumerate() {
+        let triggered_by: Vec<String> = rule
+            .capabilities
+            .iter()
+            .filter(|rule_kind| kinds.iter().any(|kind| rule_kind_applies(rule_kind, kind)))
+            .cloned()
+            .collect();
+        if !rule.capabilities.is_empty() && triggered_by.is_empty() {
+            continue;
+        }
+        let (present, missing): (Vec<String>, Vec<String>) = rule
+            .signers
+            .iter()
+            .cloned()
+            .partition(|signer| counted.contains(signer.as_str()));
+        if (present.len() as u64) < rule.threshold {
+            unmet_rules.push(UnmetSignerRule {
+                rule: index,
+                threshold: rule.threshold,
+                triggered_by,
+                present,
+                missing,
+            });
+        }
+    }
+
+    if no_trusted_signer || !unmet_rules.is_empty() {
+        return Err(SignerPolicyDenial {
+            no_trusted_signer,
+            unmet_rules,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CapabilityCeiling, VerifiedSignature};
+
+    fn policy() -> Policy {
+        Policy {
+            version: 1,
+            trusted_signers: vec!["alice.dev".to_string(), "bob.dev".to_string()],
+            capability_ceiling: CapabilityCeiling::default(),
+            sbom: None,
+            signer_rules: vec![
+                SignerRule {
+                    threshold: 2,
+                    signers: vec![
+                        "alice.dev".to_string(),
+                        "bob.dev".to_string(),
+                        "release-bot".to_string(),
+                    ],
+                    capabilities: vec![],
+                },
+                SignerRule {
+                    threshold: 1,
+                    signers: vec!["security-team".to_string()],
+                    capabilities: vec!["exec".to_string()],
+                },
+            ],
+        }
+    }
+
+    fn manifest(kinds: &[&str]) -> Manifest {
+        Manifest {
+            name: "echo.minimal".to_string(),
+            version: "0.1.0".to_string(),
+            entrypoint: "run".to_string(),
+            artifact: format!("sha256:{}", "a".repeat(64)),
+            capabilities: kinds
+                .iter()
+                .map(|kind| serde_json::json!({"kind": kind, "value": "true"}))
+                .collect(),
+            signers: ["alice.dev", "bob.dev", "release-bot", "security-team"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+
+    fn verified(signers: &[&str]) -> SignatureVerification {
+        SignatureVerification {
+            verified: signers
+                .iter()
+                .map(|s| VerifiedSignature {
+                    signer: s.to_string(),
+                    key_id: s.to_string(),
+                })
+                .collect(),
+            rejected: vec![],
+        }
+    }
+
+    #[test]
+    fn threshold_rule_requires_distinct_signers() {
+        let policy = policy();
+        assert!(evaluate_signer_policy(
+            &policy,
+            &manifest(&[]),
+            &verified(&["alice.dev", "bob.dev"])
+        )
+        .is_ok());
+
+        let mut same_signer_twice = verified(&["alice.dev", "alice.dev"]);
+        same_signer_twice.verified[1].key_id = "alice-2026".to_string();
+        let denial =
+            evaluate_signer_policy(&policy, &manifest(&[]), &same_signer_twice).unwrap_err();
+        assert!(!denial.no_trusted_signer);
+        assert_eq!(denial.unmet_rules.len(), 1);
+        assert_eq!(denial.unmet_rules[0].present, vec!["alice.dev"]);
+        assert_eq!(
+            denial.unmet_rules[0].missing,
+            vec!["bob.dev", "release-bot"]
+        );
+    }
+
+    #[test]
+    fn capability_rule_applies_only_when_requested() {
+        let policy = policy();
+        let signed = verified(&["alice.dev", "release-bot"]);
+        assert!(evaluate_signer_policy(&policy, &manifest(&["time.now"]), &signed).is_ok());
+
+        let denial = evaluate_signer_policy(&policy, &manifest(&["exec"]), &signed).unwrap_err();
+        assert_eq!(denial.unmet_rules[0].rule, 1);
+        assert_eq!(denial.unmet_rules[0].triggered_by, vec!["exec"]);
+        assert_eq!(
+            denial.to_string(),
+            "signer rule 1 requires 1 of [security-team] for exec; signed by []; missing [security-team]"
+        );
+
+        let denial =
+            evaluate_signer_policy(&policy, &manifest(&["exec.safe"]), &signed).unwrap_err();
+        assert_eq!(denial.unmet_rules[0].triggered_by, vec!["exec"]);
+    }
+
+    #[test]
+    fn signers_outside_manifest_do_not_count() {
+        let policy = policy();
+        let mut manifest = manifest(&[]);
+        manifest.signers = vec!["alice.dev".to_string()];
+        let denial =
+            evaluate_signer_policy(&policy, &manifest, &verified(&["alice.dev", "bob.dev"]))
+                .unwrap_err();
+        assert_eq!(denial.unmet_rules[0].present, vec!["alice.dev"]);
+    }
+
+    #[test]
+    fn validate_signer_rules_rejects_unsatisfiable_thresholds() {
+        let mut policy = policy();
+        policy.signer_rules[1].threshold = 2;
+        assert_eq!(
+            validate_signer_rules(&policy),
+            Err(SignerRuleError::ThresholdExceedsSigners {
+                rule: 1,
+                threshold: 2,
+                signers: 1,
+            })
+        );
+
+        let mut policy = self::policy();
+        policy.signer_rules[1].capabilities = vec!["exce".to_string()];
+        assert_eq!(
+            validate_signer_rules(&policy),
+            Err(SignerRuleError::UnknownCapability {
+                rule: 1,
+                kind: "exce".to_string(),
+            })
+        );
+    }
+}
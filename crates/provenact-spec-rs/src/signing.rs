@@ -0,0 +1,422 @@
+//! Producing `signatures.json` (`spec/hashing.md`) through pluggable key
+//! backends (`docs/signing.md`).
+
+use crate::{
+    compute_manifest_hash, validate_sha256_prefixed, Manifest, SignatureEntry, Signatures,
+    SpecError,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signer as _, SigningKey, VerifyingKey};
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SigningError {
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("invalid secret key for {signer}: expected base64 of a 32-byte ed25519 seed")]
+    InvalidSecretKey { signer: String },
+    #[error("reading secret key {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("signing agent {socket}: {message}")]
+    Agent { socket: PathBuf, message: String },
+}
+
+/// A signing backend for one signer identity.
+pub trait Signer {
+    /// Signer identity recorded in `signatures.json` and matched against
+    /// `manifest.signers` and trust anchors.
+    fn signer_id(&self) -> &str;
+
+    fn algorithm(&self) -> &str {
+        "ed25519"
+    }
+
+    /// Returns the raw signature over `message`.
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, SigningError>;
+}
+
+/// In-memory Ed25519 key.
+pub struct Ed25519Signer {
+    signer: String,
+    key: SigningKey,
+}
+
+impl Ed25519Signer {
+    pub fn new(signer: impl Into<String>, key: SigningKey) -> Self {
+        Self {
+            signer: signer.into(),
+            key,
+        }
+    }
+
+    /// Parses the secret-key text format: standard base64 of the 32-byte
+    /// Ed25519 seed, surrounding whitespace ignored.
+    pub fn from_secret_key_text(
+        signer: impl Into<String>,
+        text: &str,
+    ) -> Result<Self, SigningError> {
+        let signer = signer.into();
+        let seed: Option<[u8; 32]> = STANDARD
+            .decode(text.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok());
+        match seed {
+            Some(seed) => Ok(Self::new(signer, SigningKey::from_bytes(&seed))),
+            None => Err(SigningError::InvalidSecretKey { signer }),
+        }
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Public key in the trust-anchor encoding (base64 of 32 bytes).
+    pub fn public_key_base64(&self) -> String {
+        STANDARD.encode(self.verifying_key().as_bytes())
+    }
+}
+
+impl std::fmt::Debug for Ed25519Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ed25519Signer")
+            .field("signer", &self.signer)
+            .field("public_key", &self.public_key_base64())
+            .finish()
+    }
+}
+
+impl Signer for Ed25519Signer {
+    fn signer_id(&self) -> &str {
+        &self.signer
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, SigningError> {
+        Ok(self.key.sign(message).to_bytes().to_vec())
+    }
+}
+
+/// Ed25519 key read from a secret-key text file on every signature, so the
+/// seed is held in memory only while signing.
+#[derive(Debug, Clone)]
+pub struct FileSigner {
+    signer: String,
+    path: PathBuf,
+}
+
+impl FileSigner {
+    pub fn new(signer: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            signer: signer.into(),
+            path: path.into(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<Ed25519Signer, SigningError> {
+        let text = std::fs::read_to_string(&self.path).map_err(|source| SigningError::Io {
+            path: self.path.clone(),
+            source,
+        })?;
+        Ed25519Signer::from_secret_key_text(self.signer.clone(), &text)
+    }
+}
+
+impl Signer for FileSigner {
+    fn signer_id(&self) -> &str {
+        &self.signer
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, SigningError> {
+        self.load()?.sign(message)
+    }
+}
+
+/// Signing agent reached over a Unix domain socket.
+///
+/// Each signature opens one connection carrying one JSON request line and
+/// one JSON response line (`docs/signing.md`). Reads and writes give up
+/// after [`AGENT_TIMEOUT`] unless set with [`AgentSigner::with_timeout`].
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct AgentSigner {
+    signer: String,
+    socket: PathBuf,
+    timeout: Duration,
+}
+
+/// Default read and write timeout for [`AgentSigner`].
+#[cfg(unix)]
+pub const AGENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[cfg(unix)]
+#[derive(serde::Serialize)]
+struct AgentRequest<'a> {
+    signer: &'a str,
+    algorithm: &'a str,
+    payload: String,
+}
+
+#[cfg(unix)]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AgentResponse {
+    #[serde(default)]
+    signature: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[cfg(unix)]
+impl AgentSigner {
+    pub fn new(signer: impl Into<String>, socket: impl Into<PathBuf>) -> Self {
+        Self {
+            signer: signer.into(),
+            socket: socket.into(),
+            timeout: AGENT_TIMEOUT,
+        }
+    }
+
+    /// Sets the read and write timeout on the agent connection.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn io_error(&self, error: std::io::Error) -> SigningError {
+        match error.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+                self.agent_error(format!("timed out after {:?}", self.timeout))
+            }
+            _ => self.agent_error(error),
+        }
+    }
+
+    fn agent_error(&self, message: impl std::fmt::Display) -> SigningError {
+        SigningError::Agent {
+            socket: self.socket.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Signer for AgentSigner {
+    fn signer_id(&self) -> &str {
+        &self.signer
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, SigningError> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+
+        let mut stream = UnixStream::connect(&self.socket).map_err(|e| self.agent_error(e))?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .and_then(|()| stream.set_write_timeout(Some(self.timeout)))
+            .map_err(|e| self.agent_error(e))?;
+        let mut request = serde_json::to_vec(&AgentRequest {
+            signer: &self.signer,
+            algorithm: self.algorithm(),
+            payload: STANDARD.encode(message),
+        })
+        .map_err(|e| self.agent_error(e))?;
+        request.push(b'\n');
+        stream.write_all(&request).map_err(|e| self.io_error(e))?;
+
+        let mut line = String::new();
+        BufReader::new(&stream)
+            .read_line(&mut line)
+            .map_err(|e| self.io_error(e))?;
+        let response: AgentResponse = serde_json::from_str(&line)
+            .map_err(|e| self.agent_error(format!("malformed response: {e}")))?;
+        match (response.signature, response.error) {
+            (_, Some(error)) => Err(self.agent_error(error)),
+            (Some(signature), None) => STANDARD
+                .decode(signature)
+                .map_err(|_| self.agent_error("signature is not base64")),
+            (None, None) => Err(self.agent_error("response carries no signature")),
+        }
+    }
+}
+
+/// Signs the canonical manifest hash of `manifest` and returns the
+/// `signatures.json` document binding it to `manifest.artifact`.
+pub fn sign_manifest(manifest: &Manifest, signer: &dyn Signer) -> Result<Signatures, SigningError> {
+    validate_sha256_prefixed(&manifest.artifact)?;
+    let manifest_hash = compute_manifest_hash(manifest)?;
+    let signature = signer.sign(manifest_hash.as_bytes())?;
+    Ok(Signatures {
+        artifact: manifest.artifact.clone(),
+        manifest_hash,
+        signatures: vec![SignatureEntry {
+            signer: signer.signer_id().to_string(),
+            algorithm: signer.algorithm().to_string(),
+            signature: STANDARD.encode(signature),
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_json, verify_signatures, TrustAnchors};
+
+    const SECRET_KEY: &str =
+        include_str!("../../../test-vectors/good/verify-run-verify-receipt/signer-secret-key.txt");
+
+    fn roundtrip_manifest() -> Manifest {
+        parse_json(include_str!(
+            "../../../test-vectors/good/pack-sign-roundtrip/manifest.json"
+        ))
+        .unwrap()
+    }
+
+    fn roundtrip_signatures() -> Signatures {
+        parse_json(include_str!(
+            "../../../test-vectors/good/pack-sign-roundtrip/signatures.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn in_memory_signer_reproduces_roundtrip_vector() {
+        let signer = Ed25519Signer::from_secret_key_text("alice.dev", SECRET_KEY).unwrap();
+        let signatures = sign_manifest(&roundtrip_manifest(), &signer).unwrap();
+        assert_eq!(signatures, roundtrip_signatures());
+
+        let anchors = TrustAnchors::from_bytes_unpinned(include_bytes!(
+            "../../../test-vectors/good/pack-sign-roundtrip/public-keys.json"
+        ))
+        .unwrap();
+        assert!(verify_signatures(&signatures, &roundtrip_manifest(), &anchors, 0).is_ok());
+    }
+
+    #[test]
+    fn invalid_secret_key_vector_is_rejected() {
+        let text = include_str!(
+            "../../../test-vectors/bad/sign-invalid-secret-key/invalid-secret-key.txt"
+        );
+        assert!(matches!(
+            Ed25519Signer::from_secret_key_text("alice.dev", text),
+            Err(SigningError::InvalidSecretKey { signer }) if signer == "alice.dev"
+        ));
+        // Valid base64 of the wrong length is rejected the same way.
+        assert!(matches!(
+            Ed25519Signer::from_secret_key_text("alice.dev", "AAEC"),
+            Err(SigningError::InvalidSecretKey { .. })
+        ));
+    }
+
+    #[test]
+    fn file_signer_reads_key_per_signature() {
+        let root = env!("CARGO_MANIFEST_DIR");
+        let good = FileSigner::new(
+            "alice.dev",
+            format!(
+                "{root}/../../test-vectors/good/verify-run-verify-receipt/signer-secret-key.txt"
+            ),
+        );
+        assert_eq!(
+            sign_manifest(&roundtrip_manifest(), &good).unwrap(),
+            roundtrip_signatures()
+        );
+
+        let bad = FileSigner::new(
+            "alice.dev",
+            format!("{root}/../../test-vectors/bad/sign-invalid-secret-key/invalid-secret-key.txt"),
+        );
+        assert!(matches!(
+            sign_manifest(&roundtrip_manifest(), &bad),
+            Err(SigningError::InvalidSecretKey { .. })
+        ));
+
+        let missing = FileSigner::new("alice.dev", format!("{root}/does-not-exist.txt"));
+        assert!(matches!(
+            sign_manifest(&roundtrip_manifest(), &missing),
+            Err(SigningError::Io { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn agent_signer_round_trips_through_stand_in_agent() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixListener;
+
+        let socket = std::env::temp_dir().join(format!(
+            "provenact-signing-agent-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let agent = std::thread::spawn(move || {
+            let key = Ed25519Signer::from_secret_key_text("alice.dev", SECRET_KEY).unwrap();
+            for reply_error in [false, true] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut line = String::new();
+                BufReader::new(&stream).read_line(&mut line).unwrap();
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                let response = if reply_error {
+                    serde_json::json!({"error": "signer locked"})
+                } else {
+                    let payload = STANDARD
+                        .decode(request["payload"].as_str().unwrap())
+                        .unwrap();
+                    serde_json::json!({"signature": STANDARD.encode(key.sign(&payload).unwrap())})
+                };
+                writeln!(&stream, "{response}").unwrap();
+            }
+        });
+
+        let signer = AgentSigner::new("alice.dev", &socket);
+        assert_eq!(
+            sign_manifest(&roundtrip_manifest(), &signer).unwrap(),
+            roundtrip_signatures()
+        );
+        assert!(matches!(
+            sign_manifest(&roundtrip_manifest(), &signer),
+            Err(SigningError::Agent { message, .. }) if message == "signer locked"
+        ));
+        agent.join().unwrap();
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn agent_signer_times_out_on_a_wedged_agent() {
+        use std::os::unix::net::UnixListener;
+
+        let socket = std::env::temp_dir().join(format!(
+            "provenact-signing-agent-wedged-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let agent = std::thread::spawn(move || {
+            // Accept but never reply until the client has given up.
+            let (_stream, _) = listener.accept().unwrap();
+            wait.recv().unwrap();
+        });
+
+        let signer =
+            AgentSigner::new("alice.dev", &socket).with_timeout(Duration::from_millis(100));
+        assert!(matches!(
+            sign_manifest(&roundtrip_manifest(), &signer),
+            Err(SigningError::Agent { message, .. }) if message.starts_with("timed out")
+        ));
+        release.send(()).unwrap();
+        agent.join().unwrap();
+        std::fs::remove_file(&socket).unwrap();
+    }
+}
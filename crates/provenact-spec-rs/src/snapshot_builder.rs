@@ -0,0 +1,485 @@
+//! Building registry snapshots from bundles and install artifacts
+//! (`spec/registry/registry.md`).
+
+use crate::{
+    compute_snapshot_hash, sha256_prefixed, verify_snapshot_hash, Manifest, RegistrySnapshot,
+    SnapshotEntry, SpecError,
+};
+use md5::{Digest, Md5};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const MAX_MANIFEST_BYTES: u64 = 1024 * 1024;
+
+/// Top-level entries an install artifact may contain (`spec/install.md`).
+const INSTALL_ARTIFACT_ENTRIES: [&str; 5] = [
+    "manifest.json",
+    "skill.wasm",
+    "signatures.json",
+    "sbom.spdx.json",
+    "sigstore.bundle.json",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotBuildError {
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("{path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{path}: invalid manifest: {message}")]
+    InvalidManifest { path: PathBuf, message: String },
+    #[error("{path}: invalid install artifact: {message}")]
+    InvalidArchive { path: PathBuf, message: String },
+    #[error("{path}: manifest.artifact={manifest} but skill.wasm is {actual}")]
+    ArtifactMismatch {
+        path: PathBuf,
+        manifest: String,
+        actual: String,
+    },
+    #[error("entry {name} is produced by both {first} and {second} with different digests")]
+    NameCollision {
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+    #[error("entry {name} would be re-pointed from {previous} to {new}")]
+    Repointed {
+        name: String,
+        previous: String,
+        new: String,
+    },
+    #[error("snapshot timestamp {timestamp} is older than previous snapshot {previous}")]
+    TimestampRegression { previous: u64, timestamp: u64 },
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> SnapshotBuildError + '_ {
+    move |source| SnapshotBuildError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Registry entry name for a manifest: `<name>@<version>`.
+pub fn snapshot_entry_name(manifest: &Manifest) -> String {
+    format!("{}@{}", manifest.name, manifest.version)
+}
+
+fn parse_manifest(path: &Path, raw: &[u8]) -> Result<Manifest, SnapshotBuildError> {
+    let manifest: Manifest =
+        serde_json::from_slice(raw).map_err(|e| SnapshotBuildError::InvalidManifest {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+    if manifest.name.is_empty()
+        || manifest.version.is_empty()
+        || manifest.name.contains(['/', '\\', '@', '\0'])
+        || manifest.version.contains(['/', '\\', '\0'])
+    {
+        return Err(SnapshotBuildError::InvalidManifest {
+            path: path.to_path_buf(),
+            message: "name and version must be non-empty and usable as an entry name".to_string(),
+        });
+    }
+    Ok(manifest)
+}
+
+fn entry_for(bytes: &[u8]) -> SnapshotEntry {
+    SnapshotEntry {
+        sha256: sha256_prefixed(bytes),
+        md5: hex::encode(Md5::digest(bytes)),
+    }
+}
+
+/// An entry added from a bundle or install artifact in this build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuiltEntry {
+    pub name: String,
+    pub entry: SnapshotEntry,
+    pub source: PathBuf,
+}
+
+/// Accumulates snapshot entries, optionally on top of a previous snapshot.
+///
+/// Entry names are `<manifest.name>@<manifest.version>`. For a bundle
+/// directory the entry digests cover `skill.wasm`; for a `.tar.zst` install
+/// artifact they cover the archive bytes, matching its install identity
+/// (`spec/install.md`).
+#[derive(Debug, Default)]
+pub struct SnapshotBuilder {
+    previous_timestamp: Option<u64>,
+    entries: BTreeMap<String, SnapshotEntry>,
+    built: BTreeMap<String, BuiltEntry>,
+    allow_repoint: bool,
+}
+
+impl SnapshotBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from the entries of a verified previous snapshot. Entries not
+    /// seen again are kept.
+    pub fn from_previous(previous: &RegistrySnapshot) -> Result<Self, SnapshotBuildError> {
+        verify_snapshot_hash(previous)?;
+        Ok(Self {
+            previous_timestamp: Some(previous.timestamp),
+            entries: previous.entries.clone(),
+            ..Self::default()
+        })
+    }
+
+    /// Allows an artifact to replace a previous-snapshot entry of the same
+    /// name with different digests. Off by default: published names are
+    /// expected to be immutable.
+    pub fn allow_repoint(mut self, allow: bool) -> Self {
+        self.allow_repoint = allow;
+        self
+    }
+
+    /// Entries added from artifacts in this build, by name.
+    pub fn built(&self) -> impl Iterator<Item = &BuiltEntry> {
+        self.built.values()
+    }
+
+    fn insert(
+        &mut self,
+        name: String,
+        entry: SnapshotEntry,
+        source: &Path,
+    ) -> Result<(), SnapshotBuildError> {
+        if let Some(existing) = self.built.get(&name) {
+            if existing.entry != entry {
+                return Err(SnapshotBuildError::NameCollision {
+                    name,
+                    first: existing.source.clone(),
+                    second: source.to_path_buf(),
+                });
+            }
+            return Ok(());
+        }
+        if let Some(previous) = self.entries.get(&name) {
+            if previous.sha256 != entry.sha256 && !self.allow_repoint {
+                return Err(SnapshotBuildError::Repointed {
+                    name,
+                    previous: previous.sha256.clone(),
+                    new: entry.sha256,
+                });
+            }
+        }
+        self.entries.insert(name.clone(), entry.clone());
+        self.built.insert(
+            name.clone(),
+            BuiltEntry {
+                name,
+                entry,
+                source: source.to_path_buf(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Adds a bundle directory holding `manifest.json` and `skill.wasm`.
+    pub fn add_bundle(&mut self, dir: impl AsRef<Path>) -> Result<String, SnapshotBuildError> {
+        let dir = dir.as_ref();
+        let manifest_path = dir.join("manifest.json");
+        let raw = std::fs::read(&manifest_path).map_err(io_error(&manifest_path))?;
+        let manifest = parse_manifest(&manifest_path, &raw)?;
+        let wasm_path = dir.join("skill.wasm");
+        let wasm = std::fs::read(&wasm_path).map_err(io_error(&wasm_path))?;
+        let entry = entry_for(&wasm);
+        if entry.sha256 != manifest.artifact {
+            return Err(SnapshotBuildError::ArtifactMismatch {
+                path: dir.to_path_buf(),
+                manifest: manifest.artifact,
+                actual: entry.sha256,
+            });
+        }
+        let name = snapshot_entry_name(&manifest);
+        self.insert(name.clone(), entry, dir)?;
+        Ok(name)
+    }
+
+    /// Adds a `skill.tar.zst` install artifact.
+    pub fn add_install_artifact(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<String, SnapshotBuildError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(io_error(path))?;
+        let manifest = read_archive_manifest(path, &bytes)?;
+        let name = snapshot_entry_name(&manifest);
+        self.insert(name.clone(), entry_for(&bytes), path)?;
+        Ok(name)
+    }
+
+    /// Adds every bundle subdirectory and `.tar.zst` file directly inside
+    /// `dir`, in file-name order. Other entries are ignored.
+    pub fn add_dir(&mut self, dir: impl AsRef<Path>) -> Result<Vec<String>, SnapshotBuildError> {
+        let dir = dir.as_ref();
+        let mut paths = Vec::new();
+        for item in std::fs::read_dir(dir).map_err(io_error(dir))? {
+            paths.push(item.map_err(io_error(dir))?.path());
+        }
+        paths.sort();
+
+        let mut names = Vec::new();
+        for path in paths {
+            if path.is_dir() && path.join("manifest.json").is_file() {
+                names.push(self.add_bundle(&path)?);
+            } else if path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.ends_with(".tar.zst"))
+            {
+                names.push(self.add_install_artifact(&path)?);
+            }
+        }
+        Ok(names)
+    }
+
+    /// Emits the snapshot with `snapshot_hash` computed from its entries.
+    pub fn build(&self, timestamp: u64) -> Result<RegistrySnapshot, SnapshotBuildError> {
+        if let Some(previous) = self.previous_timestamp {
+            if timestamp < previous {
+                return Err(SnapshotBuildError::TimestampRegression {
+                    previous,
+                    timestamp,
+                });
+            }
+        }
+        let mut snapshot = RegistrySnapshot {
+            timestamp,
+            entries: self.entries.clone(),
+            snapshot_hash: String::new(),
+        };
+        snapshot.snapshot_hash = compute_snapshot_hash(&snapshot)?;
+        Ok(snapshot)
+    }
+}
+
+/// Reads `manifest.json` from an install artifact and checks that the
+/// archive's `skill.wasm` matches `manifest.artifact`. Unknown and repeated
+/// entries are rejected, so every extractor sees the files hashed here.
+fn read_archive_manifest(path: &Path, bytes: &[u8]) -> Result<Manifest, SnapshotBuildError> {
+    let invalid = |message: String| SnapshotBuildError::InvalidArchive {
+        path: path.to_path_buf(),
+        message,
+    };
+    let decoder = zstd::stream::read::Decoder::new(bytes).map_err(|e| invalid(e.to_string()))?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut manifest_raw = None;
+    let mut wasm_digest = None;
+    let mut seen = Vec::new();
+    for item in archive.entries().map_err(|e| invalid(e.to_string()))? {
+        let mut item = item.map_err(|e| invalid(e.to_string()))?;
+        let entry_path = item.path().map_err(|e| invalid(e.to_string()))?;
+        let entry_name = entry_path.to_string_lossy().into_owned();
+        if !INSTALL_ARTIFACT_ENTRIES.contains(&entry_name.as_str()) {
+            return Err(invalid(format!("unknown entry {entry_name}")));
+        }
+        if seen.contains(&entry_name) {
+            return Err(invalid(format!("duplicate entry {entry_name}")));
+        }
+        seen.push(entry_name.clone());
+        match entry_name.as_str() {
+            "manifest.json" => {
+                let mut raw = Vec::new();
+                (&mut item)
+                    .take(MAX_MANIFEST_BYTES + 1)
+                    .read_to_end(&mut raw)
+                    .map_err(|e| invalid(e.to_string()))?;
+                if raw.len() as u64 > MAX_MANIFEST_BYTES {
+                    return Err(invalid("manifest.json exceeds 1 MiB".to_string()));
+                }
+                manifest_raw = Some(raw);
+            }
+            "skill.wasm" => {
+                let mut hasher = sha2::Sha256::new();
+                std::io::copy(&mut item, &mut hasher).map_err(|e| invalid(e.to_string()))?;
+                wasm_digest = Some(format!("sha256:{}", hex::encode(hasher.finalize())));
+            }
+            _ => {}
+        }
+    }
+    let manifest_raw = manifest_raw.ok_or_else(|| invalid("missing manifest.json".to_string()))?;
+    let wasm_digest = wasm_digest.ok_or_else(|| invalid("missing skill.wasm".to_string()))?;
+    let manifest = parse_manifest(path, &manifest_raw)?;
+    if manifest.artifact != wasm_digest {
+        return Err(SnapshotBuildError::ArtifactMismatch {
+            path: path.to_path_buf(),
+            manifest: manifest.artifact,
+            actual: wasm_digest,
+        });
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector_dir(rel: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../test-vectors")
+            .join(rel)
+    }
+
+    fn write_archive(path: &Path, manifest: &[u8], wasm: &[u8]) {
+        let mut tar = tar::Builder::new(Vec::new());
+        for (name, data, mode) in [
+            ("manifest.json", manifest, 0o644),
+            ("skill.wasm", wasm, 0o755),
+        ] {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(data.len() as u64);
+            header.set_mode(mode);
+            header.set_mtime(0);
+            header.set_cksum();
+            tar.append_data(&mut header, name, data).unwrap();
+        }
+        let archive = zstd::encode_all(&tar.into_inner().unwrap()[..], 3).unwrap();
+        std::fs::write(path, archive).unwrap();
+    }
+
+    fn bundle_files() -> (Vec<u8>, Vec<u8>) {
+        let dir = vector_dir("good/minimal-zero-cap");
+        (
+            std::fs::read(dir.join("manifest.json")).unwrap(),
+            std::fs::read(dir.join("skill.wasm")).unwrap(),
+        )
+    }
+
+    #[test]
+    fn builds_verifiable_snapshot_from_bundles_and_archives() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (manifest, wasm) = bundle_files();
+        let bundle = tmp.path().join("echo-bundle");
+        std::fs::create_dir(&bundle).unwrap();
+        std::fs::write(bundle.join("manifest.json"), &manifest).unwrap();
+        std::fs::write(bundle.join("skill.wasm"), &wasm).unwrap();
+
+        let mut other: Manifest = serde_json::from_slice(&manifest).unwrap();
+        other.name = "echo.archived".to_string();
+        write_archive(
+            &tmp.path().join("echo-archived.tar.zst"),
+            &serde_json::to_vec(&other).unwrap(),
+            &wasm,
+        );
+        std::fs::write(tmp.path().join("README.md"), "ignored").unwrap();
+
+        let mut builder = SnapshotBuilder::new();
+        let names = builder.add_dir(tmp.path()).unwrap();
+        assert_eq!(names, vec!["echo.archived@0.1.0", "echo.minimal@0.1.0"]);
+        let snapshot = builder.build(1767225600).unwrap();
+        verify_snapshot_hash(&snapshot).unwrap();
+        assert_eq!(
+            snapshot.entries["echo.minimal@0.1.0"].sha256,
+            other.artifact
+        );
+        let archive = std::fs::read(tmp.path().join("echo-archived.tar.zst")).unwrap();
+        assert_eq!(snapshot.entries["echo.archived@0.1.0"], entry_for(&archive));
+    }
+
+    #[test]
+    fn identical_bundles_share_a_name_but_different_bytes_collide() {
+        let mut builder = SnapshotBuilder::new();
+        builder
+            .add_bundle(vector_dir("good/minimal-zero-cap"))
+            .unwrap();
+        builder
+            .add_bundle(vector_dir("good/pack-sign-roundtrip"))
+            .unwrap();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let (manifest, wasm) = bundle_files();
+        write_archive(&tmp.path().join("echo.tar.zst"), &manifest, b"tampered");
+        let err = builder
+            .add_install_artifact(tmp.path().join("echo.tar.zst"))
+            .unwrap_err();
+        assert!(matches!(err, SnapshotBuildError::ArtifactMismatch { .. }));
+
+        // Same manifest, but the archive bytes differ from the bundle's skill.wasm.
+        write_archive(&tmp.path().join("echo.tar.zst"), &manifest, &wasm);
+        let err = builder
+            .add_install_artifact(tmp.path().join("echo.tar.zst"))
+            .unwrap_err();
+        assert!(
+            matches!(err, SnapshotBuildError::NameCollision { ref name, .. } if name == "echo.minimal@0.1.0")
+        );
+    }
+
+    #[test]
+    fn archives_with_duplicate_or_unknown_entries_are_rejected() {
+        for (name, message) in [
+            (
+                "duplicate-manifest.tar.zst",
+                "duplicate entry manifest.json",
+            ),
+            ("unknown-entry.tar.zst", "unknown entry README.md"),
+        ] {
+            let err = SnapshotBuilder::new()
+                .add_install_artifact(vector_dir("registry/build/bad").join(name))
+                .unwrap_err();
+            assert!(
+                matches!(err, SnapshotBuildError::InvalidArchive { message: ref m, .. } if m == message),
+                "{name}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn incremental_build_keeps_previous_entries_and_guards_repoints() {
+        let mut builder = SnapshotBuilder::new();
+        builder
+            .add_bundle(vector_dir("good/minimal-zero-cap"))
+            .unwrap();
+        let first = builder.build(1767225600).unwrap();
+
+        let mut previous = first.clone();
+        previous.entries.insert(
+            "fs.cleaner@0.3.1".to_string(),
+            SnapshotEntry {
+                sha256: format!("sha256:{}", "2".repeat(64)),
+                md5: "2".repeat(32),
+            },
+        );
+        previous.snapshot_hash = compute_snapshot_hash(&previous).unwrap();
+
+        let mut builder = SnapshotBuilder::from_previous(&previous).unwrap();
+        builder
+            .add_bundle(vector_dir("good/minimal-zero-cap"))
+            .unwrap();
+        let next = builder.build(1767312000).unwrap();
+        assert_eq!(next.entries, previous.entries);
+        assert!(matches!(
+            builder.build(1767225599),
+            Err(SnapshotBuildError::TimestampRegression { .. })
+        ));
+
+        let mut repointed = previous.clone();
+        repointed
+            .entries
+            .get_mut("echo.minimal@0.1.0")
+            .unwrap()
+            .sha256 = format!("sha256:{}", "3".repeat(64));
+        repointed.snapshot_hash = compute_snapshot_hash(&repointed).unwrap();
+        let mut builder = SnapshotBuilder::from_previous(&repointed).unwrap();
+        assert!(matches!(
+            builder.add_bundle(vector_dir("good/minimal-zero-cap")),
+            Err(SnapshotBuildError::Repointed { .. })
+        ));
+        let mut builder = SnapshotBuilder::from_previous(&repointed)
+            .unwrap()
+            .allow_repoint(true);
+        builder
+            .add_bundle(vector_dir("good/minimal-zero-cap"))
+            .unwrap();
+        assert_eq!(builder.build(1767312000).unwrap().entries, previous.entries);
+    }
+}
@@ -0,0 +1,173 @@
+//! Entry-level differences between two registry snapshots.
+
+use crate::{RegistrySnapshot, SnapshotEntry};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotDiff {
+    pub old_timestamp: u64,
+    pub new_timestamp: u64,
+    /// The new snapshot is older than the old one.
+    pub timestamp_regression: bool,
+    pub added: Vec<SnapshotDiffEntry>,
+    pub removed: Vec<SnapshotDiffEntry>,
+    pub changed: Vec<ChangedSnapshotEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotDiffEntry {
+    pub name: String,
+    pub sha256: String,
+    pub md5: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChangedSnapshotEntry {
+    pub name: String,
+    pub old: SnapshotEntry,
+    pub new: SnapshotEntry,
+    /// The name now identifies different artifact bytes.
+    pub repointed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotDiffVector {
+    pub name: String,
+    pub old: RegistrySnapshot,
+    pub new: RegistrySnapshot,
+    pub expect: SnapshotDiff,
+    pub expect_text: String,
+}
+
+impl SnapshotDiff {
+    /// No entry was added, removed, or changed. Timestamps are not compared.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn repointed(&self) -> impl Iterator<Item = &ChangedSnapshotEntry> {
+        self.changed.iter().filter(|c| c.repointed)
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    /// Reviewer-oriented text: one line per entry, `+` added, `-` removed,
+    /// `~` changed, with re-points and timestamp regressions called out.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "snapshot {} -> {}: {} added, {} removed, {} changed ({} re-pointed)",
+            self.old_timestamp,
+            self.new_timestamp,
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len(),
+            self.repointed().count()
+        )?;
+        if self.timestamp_regression {
+            writeln!(f, "WARNING timestamp regression: new snapshot is older")?;
+        }
+        for entry in &self.added {
+            writeln!(f, "+ {} {}", entry.name, entry.sha256)?;
+        }
+        for entry in &self.removed {
+            writeln!(f, "- {} {}", entry.name, entry.sha256)?;
+        }
+        for change in &self.changed {
+            if change.repointed {
+                writeln!(
+                    f,
+                    "~ {} {} -> {} [re-pointed]",
+                    change.name, change.old.sha256, change.new.sha256
+                )?;
+            } else {
+                writeln!(
+                    f,
+                    "~ {} md5 {} -> {}",
+                    change.name, change.old.md5, change.new.md5
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn record(name: &str, entry: &SnapshotEntry) -> SnapshotDiffEntry {
+    SnapshotDiffEntry {
+        name: name.to_string(),
+        sha256: entry.sha256.clone(),
+        md5: entry.md5.clone(),
+    }
+}
+
+/// Compares snapshot entries by name. Records are sorted by name.
+///
+/// Snapshot hashes are not verified here; diff verified snapshots.
+pub fn diff_snapshots(old: &RegistrySnapshot, new: &RegistrySnapshot) -> SnapshotDiff {
+    let mut diff = SnapshotDiff {
+        old_timestamp: old.timestamp,
+        new_timestamp: new.timestamp,
+        timestamp_regression: new.timestamp < old.timestamp,
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+    for (name, old_entry) in &old.entries {
+        match new.entries.get(name) {
+            None => diff.removed.push(record(name, old_entry)),
+            Some(new_entry) if new_entry != old_entry => diff.changed.push(ChangedSnapshotEntry {
+                name: name.clone(),
+                old: old_entry.clone(),
+                new: new_entry.clone(),
+                repointed: new_entry.sha256 != old_entry.sha256,
+            }),
+            Some(_) => {}
+        }
+    }
+    for (name, new_entry) in &new.entries {
+        if !old.entries.contains_key(name) {
+            diff.added.push(record(name, new_entry));
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    fn vector() -> SnapshotDiffVector {
+        parse_json(include_str!(
+            "../../../test-vectors/registry/diff/repoint-and-regression.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn diff_vector_matches_json_and_text() {
+        let v = vector();
+        let diff = diff_snapshots(&v.old, &v.new);
+        assert_eq!(diff, v.expect);
+        assert_eq!(diff.to_string(), v.expect_text);
+        assert_eq!(
+            diff.repointed()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["echo@1.0.0"]
+        );
+    }
+
+    #[test]
+    fn identical_snapshots_diff_empty() {
+        let v = vector();
+        let diff = diff_snapshots(&v.old, &v.old);
+        assert!(diff.is_empty());
+        assert!(!diff.timestamp_regression);
+    }
+}
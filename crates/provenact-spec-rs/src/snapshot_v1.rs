@@ -0,0 +1,473 @@
+//! Versioned registry snapshots (`spec/registry/snapshot.v1.schema.json`).
+
+use crate::{
+    compute_snapshot_hash, sha256_prefixed, to_jcs_bytes, validate_md5_hex,
+    validate_sha256_prefixed, verify_snapshot_hash, RegistrySnapshot, SnapshotEntry, SpecError,
+};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const REGISTRY_SNAPSHOT_V1: u64 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotFormatError {
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("unsupported snapshot version {0}")]
+    UnsupportedVersion(u64),
+    #[error("invalid skill name {0:?}")]
+    InvalidSkillName(String),
+    #[error("skill {name} has invalid semver version {version:?}")]
+    InvalidVersion { name: String, version: String },
+    #[error("skill {name} has no versions")]
+    EmptySkill { name: String },
+    #[error("skill {name} version {version} is yanked without a reason")]
+    EmptyYankReason { name: String, version: String },
+    #[error("unversioned entry {0:?} has the form <name>@<semver>; list it under skills")]
+    MisfiledEntry(String),
+    #[error("skill {name} version {version} is yanked; v0 snapshots cannot represent yanks")]
+    YankNotRepresentable { name: String, version: String },
+    #[error("invalid version query {0:?}")]
+    InvalidQuery(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistrySnapshotV1 {
+    pub version: u64,
+    pub timestamp: u64,
+    pub skills: BTreeMap<String, SkillVersions>,
+    /// v0 entries whose names are not `<name>@<semver>`, kept under their
+    /// exact v0 name so upgrades are lossless. Version queries never select
+    /// them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub unversioned_entries: BTreeMap<String, SnapshotEntry>,
+    pub snapshot_hash: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillVersions {
+    pub versions: BTreeMap<String, VersionedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VersionedEntry {
+    pub sha256: String,
+    pub md5: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yanked: Option<Yank>,
+}
+
+impl VersionedEntry {
+    pub fn digests(&self) -> SnapshotEntry {
+        SnapshotEntry {
+            sha256: self.sha256.clone(),
+            md5: self.md5.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Yank {
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VersionQueryVector {
+    pub name: String,
+    pub snapshot: RegistrySnapshotV1,
+    pub cases: Vec<VersionQueryCase>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VersionQueryCase {
+    pub skill: String,
+    pub query: String,
+    #[serde(default)]
+    pub expect_version: Option<String>,
+    #[serde(default)]
+    pub expect_yanked: bool,
+    #[serde(default)]
+    pub expect_error: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotUpgradeVector {
+    pub name: String,
+    pub v0: RegistrySnapshot,
+    pub v1: RegistrySnapshotV1,
+}
+
+/// Either snapshot format, dispatched on the top-level `version` field.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum AnySnapshot {
+    V0(RegistrySnapshot),
+    V1(RegistrySnapshotV1),
+}
+
+impl<'de> Deserialize<'de> for AnySnapshot {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::from_value(Value::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+impl AnySnapshot {
+    pub fn parse(raw: &str) -> Result<Self, SnapshotFormatError> {
+        Self::from_value(serde_json::from_str(raw).map_err(SpecError::from)?)
+    }
+
+    fn from_value(value: Value) -> Result<Self, SnapshotFormatError> {
+        match value.get("version") {
+            None => Ok(Self::V0(
+                serde_json::from_value(value).map_err(SpecError::from)?,
+            )),
+            Some(Value::Number(n)) if n.as_u64() == Some(REGISTRY_SNAPSHOT_V1) => Ok(Self::V1(
+                serde_json::from_value(value).map_err(SpecError::from)?,
+            )),
+            Some(other) => Err(SnapshotFormatError::UnsupportedVersion(
+                other.as_u64().unwrap_or(u64::MAX),
+            )),
+        }
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            Self::V0(snapshot) => snapshot.timestamp,
+            Self::V1(snapshot) => snapshot.timestamp,
+        }
+    }
+
+    pub fn snapshot_hash(&self) -> &str {
+        match self {
+            Self::V0(snapshot) => &snapshot.snapshot_hash,
+            Self::V1(snapshot) => &snapshot.snapshot_hash,
+        }
+    }
+
+    /// Verifies the snapshot hash and converts to v1.
+    pub fn into_v1(self) -> Result<RegistrySnapshotV1, SnapshotFormatError> {
+        match self {
+            Self::V0(snapshot) => upgrade_snapshot(&snapshot),
+            Self::V1(snapshot) => {
+                verify_snapshot_v1_hash(&snapshot)?;
+                Ok(snapshot)
+            }
+        }
+    }
+}
+
+fn validate_skill_name(name: &str) -> Result<(), SnapshotFormatError> {
+    if name.is_empty() || name.contains(['@', '/', '\\', '\0']) {
+        return Err(SnapshotFormatError::InvalidSkillName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Splits a v0 entry name at its last `@` into skill name and semver
+/// version; `None` when it does not have that form.
+fn split_versioned(entry_name: &str) -> Option<(&str, &str)> {
+    entry_name.rsplit_once('@').filter(|(name, version)| {
+        validate_skill_name(name).is_ok() && Version::parse(version).is_ok()
+    })
+}
+
+/// The `snapshot_hash` preimage: the document without `snapshot_hash`.
+pub fn compute_snapshot_v1_hash(snapshot: &RegistrySnapshotV1) -> Result<String, SpecError> {
+    let mut payload = serde_json::json!({
+        "version": snapshot.version,
+        "timestamp": snapshot.timestamp,
+        "skills": snapshot.skills,
+    });
+    if !snapshot.unversioned_entries.is_empty() {
+        payload["unversioned_entries"] = serde_json::to_value(&snapshot.unversioned_entries)?;
+    }
+    Ok(sha256_prefixed(&to_jcs_bytes(&payload)?))
+}
+
+/// Checks the format version, names, semver keys, digests, yank reasons,
+/// and `snapshot_hash`.
+pub fn verify_snapshot_v1_hash(snapshot: &RegistrySnapshotV1) -> Result<(), SnapshotFormatError> {
+    if snapshot.version != REGISTRY_SNAPSHOT_V1 {
+        return Err(SnapshotFormatError::UnsupportedVersion(snapshot.version));
+    }
+    for (name, skill) in &snapshot.skills {
+        validate_skill_name(name)?;
+        if skill.versions.is_empty() {
+            return Err(SnapshotFormatError::EmptySkill { name: name.clone() });
+        }
+        for (version, entry) in &skill.versions {
+            if Version::parse(version).is_err() {
+                return Err(SnapshotFormatError::InvalidVersion {
+                    name: name.clone(),
+                    version: version.clone(),
+                });
+            }
+            validate_sha256_prefixed(&entry.sha256)?;
+            validate_md5_hex(&entry.md5)?;
+            if entry.yanked.as_ref().is_some_and(|y| y.reason.is_empty()) {
+                return Err(SnapshotFormatError::EmptyYankReason {
+                    name: name.clone(),
+                    version: version.clone(),
+                });
+            }
+        }
+    }
+    for (entry_name, entry) in &snapshot.unversioned_entries {
+        if split_versioned(entry_name).is_some() {
+            return Err(SnapshotFormatError::MisfiledEntry(entry_name.clone()));
+        }
+        validate_sha256_prefixed(&entry.sha256)?;
+        validate_md5_hex(&entry.md5)?;
+    }
+    validate_sha256_prefixed(&snapshot.snapshot_hash)?;
+    let actual = compute_snapshot_v1_hash(snapshot)?;
+    if actual != snapshot.snapshot_hash {
+        return Err(SpecError::HashMismatch {
+            expected: snapshot.snapshot_hash.clone(),
+            actual,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Converts a verified v0 snapshot. Entry names split at their last `@`
+/// into skill name and semver version; other names move unchanged to
+/// `unversioned_entries`, so [`downgrade_snapshot`] restores the v0 entries
+/// exactly.
+///
+/// The result has a new `snapshot_hash`; signatures over the v0 hash do not
+/// carry over.
+pub fn upgrade_snapshot(
+    snapshot: &RegistrySnapshot,
+) -> Result<RegistrySnapshotV1, SnapshotFormatError> {
+    verify_snapshot_hash(snapshot)?;
+    let mut skills: BTreeMap<String, SkillVersions> = BTreeMap::new();
+    let mut unversioned_entries = BTreeMap::new();
+    for (entry_name, entry) in &snapshot.entries {
+        let Some((name, version)) = split_versioned(entry_name) else {
+            unversioned_entries.insert(entry_name.clone(), entry.clone());
+            continue;
+        };
+        skills.entry(name.to_string()).or_default().versions.insert(
+            version.to_string(),
+            VersionedEntry {
+                sha256: entry.sha256.clone(),
+                md5: entry.md5.clone(),
+                yanked: None,
+            },
+        );
+    }
+    let mut upgraded = RegistrySnapshotV1 {
+        version: REGISTRY_SNAPSHOT_V1,
+        timestamp: snapshot.timestamp,
+        skills,
+        unversioned_entries,
+        snapshot_hash: String::new(),
+    };
+    upgraded.snapshot_hash = compute_snapshot_v1_hash(&upgraded)?;
+    Ok(upgraded)
+}
+
+/// Converts back to v0 with `<name>@<version>` entry names plus the
+/// `unversioned_entries`. Fails when any version is yanked, since v0 cannot
+/// represent it.
+pub fn downgrade_snapshot(
+    snapshot: &RegistrySnapshotV1,
+) -> Result<RegistrySnapshot, SnapshotFormatError> {
+    verify_snapshot_v1_hash(snapshot)?;
+    let mut entries = snapshot.unversioned_entries.clone();
+    for (name, skill) in &snapshot.skills {
+        for (version, entry) in &skill.versions {
+            if entry.yanked.is_some() {
+                return Err(SnapshotFormatError::YankNotRepresentable {
+                    name: name.clone(),
+                    version: version.clone(),
+                });
+            }
+            entries.insert(format!("{name}@{version}"), entry.digests());
+        }
+    }
+    let mut downgraded = RegistrySnapshot {
+        timestamp: snapshot.timestamp,
+        entries,
+        snapshot_hash: String::new(),
+    };
+    downgraded.snapshot_hash = compute_snapshot_hash(&downgraded)?;
+    Ok(downgraded)
+}
+
+/// A version query: an exact semver version or a semver range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionQuery {
+    Exact(Version),
+    Range(VersionReq),
+}
+
+impl VersionQuery {
+    /// A full version (`1.2.3`) is exact; anything else parses as a range
+    /// (`^1.2`, `>=1.0, <2.0`, `*`). Use `=1.2.3` for the range form.
+    pub fn parse(query: &str) -> Result<Self, SnapshotFormatError> {
+        if let Ok(version) = Version::parse(query.trim()) {
+            return Ok(Self::Exact(version));
+        }
+        VersionReq::parse(query)
+            .map(Self::Range)
+            .map_err(|_| SnapshotFormatError::InvalidQuery(query.to_string()))
+    }
+}
+
+impl fmt::Display for VersionQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(version) => write!(f, "{version}"),
+            Self::Range(req) => write!(f, "{req}"),
+        }
+    }
+}
+
+/// Outcome of selecting a version within a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedVersion<'a> {
+    pub version: Version,
+    pub key: &'a str,
+    pub entry: &'a VersionedEntry,
+}
+
+impl RegistrySnapshotV1 {
+    /// Selects a version of `name`.
+    ///
+    /// Exact queries match a version equal under semver precedence and may
+    /// select a yanked version; callers see it in `entry.yanked`. Range
+    /// queries never select yanked versions and pick the highest matching
+    /// version. Returns `None` for an unknown skill or no match.
+    pub fn select(&self, name: &str, query: &VersionQuery) -> Option<SelectedVersion<'_>> {
+        let skill = self.skills.get(name)?;
+        let candidates = skill
+            .versions
+            .iter()
+            .filter_map(|(key, entry)| Some((Version::parse(key).ok()?, key, entry)));
+        let chosen = match query {
+            VersionQuery::Exact(want) => candidates
+                .filter(|(version, _, _)| version.cmp_precedence(want).is_eq())
+                .max_by(|a, b| a.0.cmp(&b.0)),
+            VersionQuery::Range(req) => candidates
+                .filter(|(version, _, entry)| entry.yanked.is_none() && req.matches(version))
+                .max_by(|a, b| a.0.cmp(&b.0)),
+        }?;
+        Some(SelectedVersion {
+            version: chosen.0,
+            key: chosen.1,
+            entry: chosen.2,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    fn v1() -> RegistrySnapshotV1 {
+        parse_json(include_str!(
+            "../../../test-vectors/registry/snapshot-v1/good/basic.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn v1_vector_verifies_and_bad_hash_fails() {
+        let snapshot = v1();
+        verify_snapshot_v1_hash(&snapshot).unwrap();
+        let bad: RegistrySnapshotV1 = parse_json(include_str!(
+            "../../../test-vectors/registry/snapshot-v1/bad/hash-mismatch.json"
+        ))
+        .unwrap();
+        assert!(matches!(
+            verify_snapshot_v1_hash(&bad),
+            Err(SnapshotFormatError::Spec(SpecError::HashMismatch { .. }))
+        ));
+    }
+
+    #[test]
+    fn v0_upgrade_round_trips() {
+        let v0: RegistrySnapshot = parse_json(include_str!(
+            "../../../test-vectors/registry/snapshot/good/basic.json"
+        ))
+        .unwrap();
+        let upgraded = upgrade_snapshot(&v0).unwrap();
+        verify_snapshot_v1_hash(&upgraded).unwrap();
+        assert_eq!(upgraded.timestamp, v0.timestamp);
+        assert!(upgraded.skills["echo"].versions.contains_key("1.0.0"));
+        let back = downgrade_snapshot(&upgraded).unwrap();
+        assert_eq!(back.entries, v0.entries);
+        assert_eq!(back.snapshot_hash, v0.snapshot_hash);
+
+        assert!(matches!(
+            downgrade_snapshot(&v1()),
+            Err(SnapshotFormatError::YankNotRepresentable { .. })
+        ));
+    }
+
+    #[test]
+    fn unversioned_v0_names_survive_the_upgrade() {
+        let vector: SnapshotUpgradeVector = parse_json(include_str!(
+            "../../../test-vectors/registry/upgrade/unversioned-names.json"
+        ))
+        .unwrap();
+        let upgraded = upgrade_snapshot(&vector.v0).unwrap();
+        assert!(upgraded.unversioned_entries.contains_key("legacy-tool"));
+        assert_eq!(
+            downgrade_snapshot(&upgraded).unwrap().entries,
+            vector.v0.entries
+        );
+
+        let mut misfiled = upgraded.clone();
+        let entry = misfiled.skills["echo"].versions["1.0.0"].digests();
+        misfiled
+            .unversioned_entries
+            .insert("echo@2.0.0".to_string(), entry);
+        misfiled.snapshot_hash = compute_snapshot_v1_hash(&misfiled).unwrap();
+        assert!(matches!(
+            verify_snapshot_v1_hash(&misfiled),
+            Err(SnapshotFormatError::MisfiledEntry(name)) if name == "echo@2.0.0"
+        ));
+    }
+
+    #[test]
+    fn any_snapshot_dispatches_on_version() {
+        let raw = include_str!("../../../test-vectors/registry/snapshot/good/basic.json");
+        assert!(matches!(AnySnapshot::parse(raw), Ok(AnySnapshot::V0(_))));
+        let raw = include_str!("../../../test-vectors/registry/snapshot-v1/good/basic.json");
+        assert!(matches!(AnySnapshot::parse(raw), Ok(AnySnapshot::V1(_))));
+        assert!(matches!(
+            AnySnapshot::parse(r#"{"version": 2}"#),
+            Err(SnapshotFormatError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn version_queries_parse_exact_or_range() {
+        assert!(matches!(
+            VersionQuery::parse("0.2.0"),
+            Ok(VersionQuery::Exact(_))
+        ));
+        assert!(matches!(
+            VersionQuery::parse("^0.2"),
+            Ok(VersionQuery::Range(_))
+        ));
+        assert!(VersionQuery::parse("latest").is_err());
+    }
+}
@@ -0,0 +1,618 @@
+//! Signer trust anchors with digest pinning, rotation, and revocation
+//! (`docs/KEY_MANAGEMENT.md`, `spec/trust/trust-anchors.md`).
+
+use crate::{decode_ed25519_public_key, sha256_prefixed, validate_sha256_prefixed, SpecError};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub const TRUST_ANCHORS_VERSION: u64 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrustError {
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("reading trust anchors {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid trust anchor pin: {0}")]
+    InvalidPin(String),
+    #[error("trust anchor pin mismatch: pinned={pinned} actual={actual}")]
+    PinMismatch { pinned: String, actual: String },
+    #[error("empty signer id in trust anchors")]
+    EmptySignerId,
+    #[error("empty key id in trust anchors")]
+    EmptyKeyId,
+    #[error("unsupported trust anchors version: {0}")]
+    UnsupportedVersion(u64),
+    #[error("duplicate key id in trust anchors: {0}")]
+    DuplicateKeyId(String),
+    #[error("unsupported algorithm {algorithm} for key {key_id}")]
+    UnsupportedAlgorithm { key_id: String, algorithm: String },
+    #[error("key {0} has not_before later than not_after")]
+    InvalidValidityWindow(String),
+    #[error("revocation references unknown key id: {0}")]
+    UnknownRevokedKey(String),
+    #[error("duplicate revocation for key id: {0}")]
+    DuplicateRevocation(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustAnchorDocument {
+    pub version: u64,
+    pub keys: Vec<TrustAnchorKey>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revocations: Vec<KeyRevocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustAnchorKey {
+    pub key_id: String,
+    pub signer: String,
+    pub algorithm: String,
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyRevocation {
+    pub key_id: String,
+    pub revoked_at: u64,
+    pub reason: RevocationReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationReason {
+    KeyCompromise,
+    Superseded,
+    CessationOfOperation,
+    Unspecified,
+}
+
+impl fmt::Display for RevocationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::KeyCompromise => "key_compromise",
+            Self::Superseded => "superseded",
+            Self::CessationOfOperation => "cessation_of_operation",
+            Self::Unspecified => "unspecified",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStatus {
+    Valid,
+    NotYetValid,
+    Expired,
+    Revoked(RevocationReason),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SignatureRejection {
+    #[error("signer has no trust anchor key")]
+    UnknownSigner,
+    #[error("unsupported signature algorithm {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("signature does not verify under any key for signer")]
+    BadSignature,
+    #[error("key {key_id} is revoked ({reason})")]
+    Revoked {
+        key_id: String,
+        reason: RevocationReason,
+    },
+    #[error("key {key_id} is not yet valid")]
+    NotYetValid { key_id: String },
+    #[error("key {key_id} has expired")]
+    Expired { key_id: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct AnchorKey {
+    pub key_id: String,
+    pub signer: String,
+    pub public_key: VerifyingKey,
+    pub not_before: Option<u64>,
+    pub not_after: Option<u64>,
+    pub revocation: Option<KeyRevocation>,
+}
+
+impl AnchorKey {
+    /// Status at UNIX time `at`. Validity windows are inclusive at both ends;
+    /// a revocation takes effect from `revoked_at` onward.
+    pub fn status_at(&self, at: u64) -> KeyStatus {
+        if let Some(revocation) = &self.revocation {
+            if at >= revocation.revoked_at {
+                return KeyStatus::Revoked(revocation.reason);
+            }
+        }
+        if self.not_before.is_some_and(|t| at < t) {
+            return KeyStatus::NotYetValid;
+        }
+        if self.not_after.is_some_and(|t| at > t) {
+            return KeyStatus::Expired;
+        }
+        KeyStatus::Valid
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrustAnchors {
+    keys: Vec<AnchorKey>,
+    digest: String,
+}
+
+impl TrustAnchors {
+    /// Loads trust anchors from disk, failing closed unless the file digest
+    /// equals `pin`.
+    pub fn load(path: impl AsRef<Path>, pin: &str) -> Result<Self, TrustError> {
+        let path = path.as_ref();
+        let raw = std::fs::read(path).map_err(|source| TrustError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_bytes_pinned(&raw, pin)
+    }
+
+    /// Parses trust anchor bytes, failing closed unless their digest equals
+    /// `pin`. The pin is checked before any key material is parsed.
+    pub fn from_bytes_pinned(raw: &[u8], pin: &str) -> Result<Self, TrustError> {
+        validate_sha256_prefixed(pin).map_err(|_| TrustError::InvalidPin(pin.to_string()))?;
+        let actual = sha256_prefixed(raw);
+        if actual != pin {
+            return Err(TrustError::PinMismatch {
+                pinned: pin.to_string(),
+                actual,
+            });
+        }
+        Self::from_bytes_unpinned(raw)
+    }
+
+    /// Parses trust anchor bytes without a pin. Verified flows should use
+    /// [`TrustAnchors::load`] or [`TrustAnchors::from_bytes_pinned`].
+    ///
+    /// Accepts a versioned [`TrustAnchorDocument`] (top-level numeric
+    /// `version`) or the legacy flat `public-keys.json` map, where each
+    /// signer id doubles as its key id and keys never expire.
+    pub fn from_bytes_unpinned(raw: &[u8]) -> Result<Self, TrustError> {
+        let digest = sha256_prefixed(raw);
+        let value: Value = serde_json::from_slice(raw).map_err(SpecError::from)?;
+        let keys = if value.get("version").is_some_and(Value::is_u64) {
+            let document: TrustAnchorDocument =
+                serde_json::from_slice(raw).map_err(SpecError::from)?;
+            keys_from_document(&document)?
+        } else {
+            let UniqueKeyMap::<String>(encoded) =
+                serde_json::from_slice(raw).map_err(SpecError::from)?;
+            let mut keys = Vec::new();
+            for (signer, key) in encoded {
+                if signer.is_empty() {
+                    return Err(TrustError::EmptySignerId);
+                }
+                keys.push(AnchorKey {
+                    public_key: decode_ed25519_public_key(&signer, &key)?,
+                    key_id: signer.clone(),
+                    signer,
+                    not_before: None,
+                    not_after: None,
+                    revocation: None,
+                });
+            }
+            keys
+        };
+        Ok(Self { keys, digest })
+    }
+
+    /// `sha256:<hex>` over the exact trust anchor file bytes, matching the
+    /// runbook's `shasum -a 256` pin.
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    pub fn keys(&self) -> &[AnchorKey] {
+        &self.keys
+    }
+
+    pub fn key(&self, key_id: &str) -> Option<&AnchorKey> {
+        self.keys.iter().find(|k| k.key_id == key_id)
+    }
+
+    pub fn keys_for<'a>(&'a self, signer: &'a str) -> impl Iterator<Item = &'a AnchorKey> + 'a {
+        self.keys.iter().filter(move |k| k.signer == signer)
+    }
+
+    pub fn signers(&self) -> impl Iterator<Item = &str> {
+        self.keys
+            .iter()
+            .map(|k| k.signer.as_str())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Verifies a base64 Ed25519 `signature` over `message` by `signer` at
+    /// UNIX time `at`, returning the key that verified it.
+    ///
+    /// Any of the signer's keys may verify. A signature that only verifies
+    /// under a revoked, expired, or not-yet-valid key is rejected with that
+    /// key's status.
+    pub fn verify(
+        &self,
+        signer: &str,
+        message: &[u8],
+        signature: &str,
+        at: u64,
+    ) -> Result<&AnchorKey, SignatureRejection> {
+        let mut rejection = None;
+        let mut known = false;
+        for key in self.keys.iter().filter(|k| k.signer == signer) {
+            known = true;
+            if !verify_ed25519(&key.public_key, message, signature) {
+                continue;
+            }
+            match key.status_at(at) {
+                KeyStatus::Valid => return Ok(key),
+                status => {
+                    rejection.get_or_insert_with(|| status_rejection(key, status));
+                }
+            }
+        }
+        if !known {
+            return Err(SignatureRejection::UnknownSigner);
+        }
+        Err(rejection.unwrap_or(SignatureRejection::BadSignature))
+    }
+}
+
+impl SignatureRejection {
+    /// Stable machine-readable reason code, as used in `test-vectors/signer-trust/`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownSigner => "unknown_signer",
+            Self::UnsupportedAlgorithm(_) => "unsupported_algorithm",
+            Self::BadSignature => "bad_signature",
+            Self::Revoked { .. } => "revoked",
+            Self::NotYetValid { .. } => "not_yet_valid",
+            Self::Expired { .. } => "expired",
+        }
+    }
+}
+
+fn status_rejection(key: &AnchorKey, status: KeyStatus) -> SignatureRejection {
+    let key_id = key.key_id.clone();
+    match status {
+        KeyStatus::Revoked(reason) => SignatureRejection::Revoked { key_id, reason },
+        KeyStatus::NotYetValid => SignatureRejection::NotYetValid { key_id },
+        KeyStatus::Expired | KeyStatus::Valid => SignatureRejection::Expired { key_id },
+    }
+}
+
+fn keys_from_document(document: &TrustAnchorDocument) -> Result<Vec<AnchorKey>, TrustError> {
+    if document.version != TRUST_ANCHORS_VERSION {
+        return Err(TrustError::UnsupportedVersion(document.version));
+    }
+    let mut revocations = BTreeMap::new();
+    for revocation in &document.revocations {
+        if !document.keys.iter().any(|k| k.key_id == revocation.key_id) {
+            return Err(TrustError::UnknownRevokedKey(revocation.key_id.clone()));
+        }
+        if revocations
+            .insert(revocation.key_id.as_str(), revocation)
+            .is_some()
+        {
+            return Err(TrustError::DuplicateRevocation(revocation.key_id.clone()));
+        }
+    }
+
+    let mut seen = BTreeSet::new();
+    let mut keys = Vec::new();
+    for key in &document.keys {
+        if key.key_id.is_empty() {
+            return Err(TrustError::EmptyKeyId);
+        }
+        if key.signer.is_empty() {
+            return Err(TrustError::EmptySignerId);
+        }
+        if !seen.insert(key.key_id.as_str()) {
+            return Err(TrustError::DuplicateKeyId(key.key_id.clone()));
+        }
+        if key.algorithm != "ed25519" {
+            return Err(TrustError::UnsupportedAlgorithm {
+                key_id: key.key_id.clone(),
+                algorithm: key.algorithm.clone(),
+            });
+        }
+        if let (Some(not_before), Some(not_after)) = (key.not_before, key.not_after) {
+            if not_before > not_after {
+                return Err(TrustError::InvalidValidityWindow(key.key_id.clone()));
+            }
+        }
+        keys.push(AnchorKey {
+            public_key: decode_ed25519_public_key(&key.key_id, &key.public_key)?,
+            key_id: key.key_id.clone(),
+            signer: key.signer.clone(),
+            not_before: key.not_before,
+            not_after: key.not_after,
+            revocation: revocations.get(key.key_id.as_str()).map(|r| (*r).clone()),
+        });
+    }
+    Ok(keys)
+}
+
+pub(crate) fn verify_ed25519(key: &VerifyingKey, message: &[u8], signature: &str) -> bool {
+    let Ok(bytes) = BASE64.decode(signature) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(&bytes) else {
+        return false;
+    };
+    key.verify(message, &signature).is_ok()
+}
+
+/// JSON object that rejects duplicate member names instead of keeping the last.
+pub(crate) struct UniqueKeyMap<V>(pub(crate) BTreeMap<String, V>);
+
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for UniqueKeyMap<V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct UniqueKeyVisitor<V>(std::marker::PhantomData<V>);
+
+        impl<'de, V: Deserialize<'de>> Visitor<'de> for UniqueKeyVisitor<V> {
+            type Value = UniqueKeyMap<V>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an object with unique keys")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut out = BTreeMap::new();
+                while let Some((key, value)) = access.next_entry::<String, V>()? {
+                    if out.contains_key(&key) {
+                        return Err(serde::de::Error::custom(format!("duplicate key `{key}`")));
+                    }
+                    out.insert(key, value);
+                }
+                Ok(UniqueKeyMap(out))
+            }
+        }
+
+        deserializer.deserialize_map(UniqueKeyVisitor(std::marker::PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const MINIMAL_KEYS: &str =
+        include_str!("../../../test-vectors/good/minimal-zero-cap/public-keys.json");
+    const MINIMAL_SIGNATURES: &str =
+        include_str!("../../../test-vectors/good/minimal-zero-cap/signatures.json");
+
+    #[test]
+    fn pinned_load_verifies_vector_signature() {
+        let pin = sha256_prefixed(MINIMAL_KEYS.as_bytes());
+        let anchors = TrustAnchors::from_bytes_pinned(MINIMAL_KEYS.as_bytes(), &pin).unwrap();
+        assert_eq!(anchors.digest(), pin);
+        assert_eq!(anchors.signers().collect::<Vec<_>>(), vec!["alice.dev"]);
+
+        let signatures: serde_json::Value = serde_json::from_str(MINIMAL_SIGNATURES).unwrap();
+        let manifest_hash = signatures["manifest_hash"].as_str().unwrap();
+        let signature = signatures["signatures"][0]["signature"].as_str().unwrap();
+        assert!(anchors
+            .verify("alice.dev", manifest_hash.as_bytes(), signature, 0)
+            .is_ok());
+        assert_eq!(
+            anchors
+                .verify("bob.dev", manifest_hash.as_bytes(), signature, 0)
+                .unwrap_err(),
+            SignatureRejection::UnknownSigner
+        );
+    }
+
+    #[test]
+    fn stale_or_malformed_pin_fails_closed() {
+        let stale = format!("sha256:{}", "0".repeat(64));
+        assert!(matches!(
+            TrustAnchors::from_bytes_pinned(MINIMAL_KEYS.as_bytes(), &stale),
+            Err(TrustError::PinMismatch { .. })
+        ));
+        assert!(matches!(
+            TrustAnchors::from_bytes_pinned(MINIMAL_KEYS.as_bytes(), "sha256:ABC"),
+            Err(TrustError::InvalidPin(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_key_encoding_length_and_duplicates() {
+        let short = SigningKey::from_bytes(&[1u8; 32])
+            .verifying_key()
+            .to_bytes();
+        let raw = serde_json::json!({"alice.dev": BASE64.encode(&short[..31])}).to_string();
+        assert!(matches!(
+            TrustAnchors::from_bytes_unpinned(raw.as_bytes()),
+            Err(TrustError::Spec(SpecError::InvalidPublicKey(s))) if s == "alice.dev"
+        ));
+
+        let raw = r#"{"alice.dev": "not base64!"}"#;
+        assert!(matches!(
+            TrustAnchors::from_bytes_unpinned(raw.as_bytes()),
+            Err(TrustError::Spec(SpecError::InvalidPublicKey(_)))
+        ));
+
+        let key = BASE64.encode(short);
+        let raw = format!(r#"{{"alice.dev": "{key}", "alice.dev": "{key}"}}"#);
+        assert!(matches!(
+            TrustAnchors::from_bytes_unpinned(raw.as_bytes()),
+            Err(TrustError::Spec(SpecError::InvalidJson(_)))
+        ));
+    }
+
+    #[test]
+    fn verify_rejects_other_messages() {
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let raw = serde_json::json!({"bob.dev": BASE64.encode(key.verifying_key().to_bytes())})
+            .to_string();
+        let anchors = TrustAnchors::from_bytes_unpinned(raw.as_bytes()).unwrap();
+        let signature = BASE64.encode(key.sign(b"sha256:aa").to_bytes());
+        assert!(anchors
+            .verify("bob.dev", b"sha256:aa", &signature, 0)
+            .is_ok());
+        assert_eq!(
+            anchors
+                .verify("bob.dev", b"sha256:bb", &signature, 0)
+                .unwrap_err(),
+            SignatureRejection::BadSignature
+        );
+        assert!(anchors.verify("bob.dev", b"sha256:aa", "AA==", 0).is_err());
+    }
+
+    fn rotated_document() -> (TrustAnchorDocument, SigningKey, SigningKey) {
+        let old = SigningKey::from_bytes(&[1u8; 32]);
+        let new = SigningKey::from_bytes(&[2u8; 32]);
+        let key = |key_id: &str, signing: &SigningKey, not_before, not_after| TrustAnchorKey {
+            key_id: key_id.to_string(),
+            signer: "alice.dev".to_string(),
+            algorithm: "ed25519".to_string(),
+            public_key: BASE64.encode(signing.verifying_key().to_bytes()),
+            not_before,
+            not_after,
+        };
+        let document = TrustAnchorDocument {
+            version: TRUST_ANCHORS_VERSION,
+            keys: vec![
+                key("alice-2025", &old, Some(100), Some(300)),
+                key("alice-2026", &new, Some(200), None),
+            ],
+            revocations: vec![],
+        };
+        (document, old, new)
+    }
+
+    fn anchors(document: &TrustAnchorDocument) -> TrustAnchors {
+        TrustAnchors::from_bytes_unpinned(&serde_json::to_vec(document).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn rotation_windows_select_valid_keys() {
+        let (document, old, new) = rotated_document();
+        let anchors = anchors(&document);
+        let old_sig = BASE64.encode(old.sign(b"m").to_bytes());
+        let new_sig = BASE64.encode(new.sign(b"m").to_bytes());
+
+        assert_eq!(
+            anchors
+                .verify("alice.dev", b"m", &old_sig, 250)
+                .unwrap()
+                .key_id,
+            "alice-2025"
+        );
+        assert_eq!(
+            anchors
+                .verify("alice.dev", b"m", &new_sig, 250)
+                .unwrap()
+                .key_id,
+            "alice-2026"
+        );
+        assert_eq!(
+            anchors
+                .verify("alice.dev", b"m", &old_sig, 301)
+                .unwrap_err(),
+            SignatureRejection::Expired {
+                key_id: "alice-2025".to_string()
+            }
+        );
+        assert_eq!(
+            anchors
+                .verify("alice.dev", b"m", &new_sig, 150)
+                .unwrap_err(),
+            SignatureRejection::NotYetValid {
+                key_id: "alice-2026".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn revocation_applies_from_revoked_at() {
+        let (mut document, old, _) = rotated_document();
+        document.revocations.push(KeyRevocation {
+            key_id: "alice-2025".to_string(),
+            revoked_at: 150,
+            reason: RevocationReason::KeyCompromise,
+        });
+        let anchors = anchors(&document);
+        let old_sig = BASE64.encode(old.sign(b"m").to_bytes());
+        assert!(anchors.verify("alice.dev", b"m", &old_sig, 149).is_ok());
+        assert_eq!(
+            anchors
+                .verify("alice.dev", b"m", &old_sig, 150)
+                .unwrap_err(),
+            SignatureRejection::Revoked {
+                key_id: "alice-2025".to_string(),
+                reason: RevocationReason::KeyCompromise,
+            }
+        );
+    }
+
+    #[test]
+    fn document_validation_rejects_inconsistent_entries() {
+        let (document, _, _) = rotated_document();
+        let load = |document: &TrustAnchorDocument| {
+            TrustAnchors::from_bytes_unpinned(&serde_json::to_vec(document).unwrap())
+        };
+
+        let mut duplicate = document.clone();
+        duplicate.keys[1].key_id = "alice-2025".to_string();
+        assert!(matches!(
+            load(&duplicate),
+            Err(TrustError::DuplicateKeyId(_))
+        ));
+
+        let mut window = document.clone();
+        window.keys[0].not_before = Some(400);
+        assert!(matches!(
+            load(&window),
+            Err(TrustError::InvalidValidityWindow(_))
+        ));
+
+        let mut unknown = document.clone();
+        unknown.revocations.push(KeyRevocation {
+            key_id: "mallory".to_string(),
+            revoked_at: 1,
+            reason: RevocationReason::Unspecified,
+        });
+        assert!(matches!(
+            load(&unknown),
+            Err(TrustError::UnknownRevokedKey(_))
+        ));
+
+        let mut version = document;
+        version.version = 2;
+        assert!(matches!(
+            load(&version),
+            Err(TrustError::UnsupportedVersion(2))
+        ));
+    }
+}
@@ -0,0 +1,453 @@
+//! Document-kind detection and dispatch for mixed JSON inputs.
+
+use crate::{DocumentKind, SchemaRegistry, SchemaRegistryError, ValidationReport, UNVERSIONED};
+use provenact_spec_rs::{
+    AuditExecutionEvent, ExecutionReceipt, ExecutionReceiptV1, InstallIndex, InstallMeta, Manifest,
+    ManifestV1, Pipeline, Policy, Provenance, RegistrySnapshot, RegistrySnapshotV1, Signatures,
+    SignedSnapshot, TrustAnchorDocument,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DetectError {
+    #[error("document is not a JSON object")]
+    NotAnObject,
+    #[error("unrecognized document (top-level fields: {})", .0.join(", "))]
+    Unknown(Vec<String>),
+    #[error("ambiguous document: matches {}", describe(.0))]
+    Ambiguous(Vec<(DocumentKind, &'static str)>),
+    #[error("unsupported {} version {found}", join_kinds(.kinds))]
+    UnsupportedVersion {
+        kinds: Vec<DocumentKind>,
+        found: String,
+    },
+    #[error("{kind} {version} document failed schema validation\n{report}")]
+    Invalid {
+        kind: DocumentKind,
+        version: &'static str,
+        report: ValidationReport,
+    },
+    #[error("{kind} {version} document: {source}")]
+    Deserialize {
+        kind: DocumentKind,
+        version: &'static str,
+        source: serde_json::Error,
+    },
+    #[error(transparent)]
+    Registry(#[from] SchemaRegistryError),
+}
+
+impl DetectError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotAnObject => "not_an_object",
+            Self::Unknown(_) => "unknown_document",
+            Self::Ambiguous(_) => "ambiguous_document",
+            Self::UnsupportedVersion { .. } => "unsupported_version",
+            Self::Invalid { .. } => "schema_invalid",
+            Self::Deserialize { .. } => "deserialize_failed",
+            Self::Registry(_) => "registry",
+        }
+    }
+}
+
+fn describe(matches: &[(DocumentKind, &str)]) -> String {
+    matches
+        .iter()
+        .map(|(kind, version)| format!("{kind} {version}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn join_kinds(kinds: &[DocumentKind]) -> String {
+    kinds
+        .iter()
+        .map(|kind| kind.as_str())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// How a format carries its version.
+#[derive(Clone, Copy)]
+enum Discriminator {
+    /// No `schema_version` member.
+    Unversioned,
+    /// `schema_version` equal to the string.
+    SchemaVersion(&'static str),
+    /// Integer `version` and no `schema_version` member.
+    Version(u64),
+}
+
+/// A format is recognized by its discriminator plus marker fields it
+/// requires; markers are chosen so that no two formats with the same
+/// discriminator share all of them.
+const SIGNATURES: &[(DocumentKind, &str, Discriminator, &[&str])] = &[
+    (
+        DocumentKind::Manifest,
+        UNVERSIONED,
+        Discriminator::Unversioned,
+        &["name", "entrypoint", "artifact", "capabilities"],
+    ),
+    (
+        DocumentKind::Manifest,
+        "1.0.0-draft",
+        Discriminator::SchemaVersion("1.0.0-draft"),
+        &["id", "entrypoint"],
+    ),
+    (
+        DocumentKind::LegacyManifest,
+        UNVERSIONED,
+        Discriminator::SchemaVersion("v0"),
+        &["skill_id"],
+    ),
+    (
+        DocumentKind::Pipeline,
+        UNVERSIONED,
+        Discriminator::SchemaVersion("v0"),
+        &["pipeline_id"],
+    ),
+    (
+        DocumentKind::Policy,
+        "1",
+        Discriminator::Version(1),
+        &["capability_ceiling"],
+    ),
+    (
+        DocumentKind::Policy,
+        "2",
+        Discriminator::Version(2),
+        &["capability_ceiling"],
+    ),
+    (
+        DocumentKind::Receipt,
+        UNVERSIONED,
+        Discriminator::Unversioned,
+        &["receipt_hash", "inputs_hash"],
+    ),
+    (
+        DocumentKind::Receipt,
+        "1.0.0-draft",
+        Discriminator::SchemaVersion("1.0.0-draft"),
+        &["receipt_hash", "inputs_hash"],
+    ),
+    (
+        DocumentKind::Snapshot,
+        UNVERSIONED,
+        Discriminator::Unversioned,
+        &["entries", "snapshot_hash"],
+    ),
+    (
+        DocumentKind::Snapshot,
+        "1",
+        Discriminator::Version(1),
+        &["skills", "snapshot_hash"],
+    ),
+    (
+        DocumentKind::SignedSnapshot,
+        UNVERSIONED,
+        Discriminator::Unversioned,
+        &["snapshot", "signatures"],
+    ),
+    (
+        DocumentKind::Signatures,
+        UNVERSIONED,
+        Discriminator::Unversioned,
+        &["manifest_hash", "signatures"],
+    ),
+    (
+        DocumentKind::Provenance,
+        UNVERSIONED,
+        Discriminator::Unversioned,
+        &["build_system", "commit"],
+    ),
+    (
+        DocumentKind::TrustAnchors,
+        "1",
+        Discriminator::Version(1),
+        &["keys"],
+    ),
+    (
+        DocumentKind::InstallMeta,
+        "1.0.0",
+        Discriminator::SchemaVersion("1.0.0"),
+        &["skill", "installed_at"],
+    ),
+    (
+        DocumentKind::InstallIndex,
+        "1.0.0",
+        Discriminator::SchemaVersion("1.0.0"),
+        &["entries"],
+    ),
+    (
+        DocumentKind::AuditEvent,
+        "1.0.0",
+        Discriminator::SchemaVersion("1.0.0"),
+        &["request_id", "decision"],
+    ),
+];
+
+impl Discriminator {
+    fn matches(self, object: &Map<String, Value>) -> bool {
+        let schema_version = object.get("schema_version");
+        match self {
+            Self::Unversioned => schema_version.is_none(),
+            Self::SchemaVersion(expected) => {
+                schema_version.and_then(Value::as_str) == Some(expected)
+            }
+            Self::Version(expected) => {
+                schema_version.is_none()
+                    && object.get("version").and_then(Value::as_u64) == Some(expected)
+            }
+        }
+    }
+}
+
+/// A detected, schema-valid document in its typed form.
+#[derive(Debug, Clone)]
+pub enum DetectedDocument {
+    ManifestV0(Manifest),
+    ManifestV1(ManifestV1),
+    /// Legacy `spec/v0/skill-manifest.schema.json`; no typed model.
+    LegacyManifest(Value),
+    Pipeline(Pipeline),
+    Policy(Policy),
+    ReceiptV0(ExecutionReceipt),
+    ReceiptV1(ExecutionReceiptV1),
+    SnapshotV0(RegistrySnapshot),
+    SnapshotV1(RegistrySnapshotV1),
+    SignedSnapshot(SignedSnapshot),
+    Signatures(Signatures),
+    Provenance(Provenance),
+    TrustAnchors(TrustAnchorDocument),
+    InstallMeta(InstallMeta),
+    InstallIndex(InstallIndex),
+    AuditEvent(AuditExecutionEvent),
+}
+
+impl DetectedDocument {
+    pub fn kind(&self) -> DocumentKind {
+        match self {
+            Self::ManifestV0(_) | Self::ManifestV1(_) => DocumentKind::Manifest,
+            Self::LegacyManifest(_) => DocumentKind::LegacyManifest,
+            Self::Pipeline(_) => DocumentKind::Pipeline,
+            Self::Policy(_) => DocumentKind::Policy,
+            Self::ReceiptV0(_) | Self::ReceiptV1(_) => DocumentKind::Receipt,
+            Self::SnapshotV0(_) | Self::SnapshotV1(_) => DocumentKind::Snapshot,
+            Self::SignedSnapshot(_) => DocumentKind::SignedSnapshot,
+            Self::Signatures(_) => DocumentKind::Signatures,
+            Self::Provenance(_) => DocumentKind::Provenance,
+            Self::TrustAnchors(_) => DocumentKind::TrustAnchors,
+            Self::InstallMeta(_) => DocumentKind::InstallMeta,
+            Self::InstallIndex(_) => DocumentKind::InstallIndex,
+            Self::AuditEvent(_) => DocumentKind::AuditEvent,
+        }
+    }
+
+    /// The registry version key, e.g. `v0` or `1.0.0-draft`.
+    pub fn version(&self) -> &'static str {
+        match self {
+            Self::ManifestV1(_) | Self::ReceiptV1(_) => "1.0.0-draft",
+            Self::Policy(policy) if policy.version == 2 => "2",
+            Self::Policy(_) | Self::SnapshotV1(_) | Self::TrustAnchors(_) => "1",
+            Self::InstallMeta(_) | Self::InstallIndex(_) | Self::AuditEvent(_) => "1.0.0",
+            _ => UNVERSIONED,
+        }
+    }
+}
+
+/// Classifies `value` by its version discriminator and marker fields
+/// without validating it.
+pub fn detect_kind(value: &Value) -> Result<(DocumentKind, &'static str), DetectError> {
+    let object = value.as_object().ok_or(DetectError::NotAnObject)?;
+    let has_markers = |markers: &[&str]| markers.iter().all(|m| object.contains_key(*m));
+    let matches: Vec<_> = SIGNATURES
+        .iter()
+        .filter(|(_, _, discriminator, markers)| {
+            discriminator.matches(object) && has_markers(markers)
+        })
+        .map(|&(kind, version, _, _)| (kind, version))
+        .collect();
+    match matches.as_slice() {
+        [single] => return Ok(*single),
+        [] => {}
+        _ => return Err(DetectError::Ambiguous(matches)),
+    }
+    let mut kinds: Vec<DocumentKind> = SIGNATURES
+        .iter()
+        .filter(|(_, _, _, markers)| has_markers(markers))
+        .map(|&(kind, _, _, _)| kind)
+        .collect();
+    kinds.sort();
+    kinds.dedup();
+    if kinds.is_empty() {
+        return Err(DetectError::Unknown(object.keys().cloned().collect()));
+    }
+    let found = object
+        .get("schema_version")
+        .or_else(|| object.get("version"))
+        .map_or_else(|| "(none)".to_string(), Value::to_string);
+    Err(DetectError::UnsupportedVersion { kinds, found })
+}
+
+impl SchemaRegistry {
+    /// Detects the kind of `value`, validates it against the registered
+    /// schema for that kind and version, and deserializes it.
+    pub fn detect_and_validate(&self, value: &Value) -> Result<DetectedDocument, DetectError> {
+        let (kind, version) = detect_kind(value)?;
+        let report = self.validate_kind(kind, version, value)?;
+        if !report.is_valid() {
+            return Err(DetectError::Invalid {
+                kind,
+                version,
+                report,
+            });
+        }
+        typed(kind, version, value.clone()).map_err(|source| DetectError::Deserialize {
+            kind,
+            version,
+            source,
+        })
+    }
+}
+
+/// [`SchemaRegistry::detect_and_validate`] against the embedded schemas.
+pub fn detect_and_validate(value: &Value) -> Result<DetectedDocument, DetectError> {
+    SchemaRegistry::shared().detect_and_validate(value)
+}
+
+fn typed(
+    kind: DocumentKind,
+    version: &str,
+    value: Value,
+) -> Result<DetectedDocument, serde_json::Error> {
+    use serde_json::from_value;
+    Ok(match (kind, version) {
+        (DocumentKind::Manifest, UNVERSIONED) => DetectedDocument::ManifestV0(from_value(value)?),
+        (DocumentKind::Manifest, _) => DetectedDocument::ManifestV1(from_value(value)?),
+        (DocumentKind::LegacyManifest, _) => DetectedDocument::LegacyManifest(value),
+        (DocumentKind::Pipeline, _) => DetectedDocument::Pipeline(from_value(value)?),
+        (DocumentKind::Policy, _) => DetectedDocument::Policy(from_value(value)?),
+        (DocumentKind::Receipt, UNVERSIONED) => DetectedDocument::ReceiptV0(from_value(value)?),
+        (DocumentKind::Receipt, _) => DetectedDocument::ReceiptV1(from_value(value)?),
+        (DocumentKind::Snapshot, UNVERSIONED) => DetectedDocument::SnapshotV0(from_value(value)?),
+        (DocumentKind::Snapshot, _) => DetectedDocument::SnapshotV1(from_value(value)?),
+        (DocumentKind::SignedSnapshot, _) => DetectedDocument::SignedSnapshot(from_value(value)?),
+        (DocumentKind::Signatures, _) => DetectedDocument::Signatures(from_value(value)?),
+        (DocumentKind::Provenance, _) => DetectedDocument::Provenance(from_value(value)?),
+        (DocumentKind::TrustAnchors, _) => DetectedDocument::TrustAnchors(from_value(value)?),
+        (DocumentKind::InstallMeta, _) => DetectedDocument::InstallMeta(from_value(value)?),
+        (DocumentKind::InstallIndex, _) => DetectedDocument::InstallIndex(from_value(value)?),
+        (DocumentKind::AuditEvent, _) => DetectedDocument::AuditEvent(from_value(value)?),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DetectVector {
+    pub name: String,
+    pub cases: Vec<DetectCase>,
+}
+
+/// Exactly one of `expect_kind` (with `expect_version`) or `expect_error`
+/// (a [`DetectError::code`]) is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DetectCase {
+    pub name: String,
+    pub document: Value,
+    #[serde(default)]
+    pub expect_kind: Option<String>,
+    #[serde(default)]
+    pub expect_version: Option<String>,
+    #[serde(default)]
+    pub expect_error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn load(raw: &str) -> Value {
+        serde_json::from_str(raw).unwrap()
+    }
+
+    #[test]
+    fn dispatches_repo_documents_to_typed_models() {
+        let manifest = load(include_str!(
+            "../../../test-vectors/good/minimal-zero-cap/manifest.json"
+        ));
+        assert!(matches!(
+            detect_and_validate(&manifest).unwrap(),
+            DetectedDocument::ManifestV0(m) if m.name == manifest["name"]
+        ));
+        let signatures = load(include_str!(
+            "../../../test-vectors/good/minimal-zero-cap/signatures.json"
+        ));
+        assert!(matches!(
+            detect_and_validate(&signatures).unwrap(),
+            DetectedDocument::Signatures(_)
+        ));
+        let receipt = load(include_str!(
+            "../../../test-vectors/receipt-v1/good/basic-success.json"
+        ));
+        let detected = detect_and_validate(&receipt).unwrap();
+        assert_eq!(
+            (detected.kind(), detected.version()),
+            (DocumentKind::Receipt, "1.0.0-draft")
+        );
+        let event = load(include_str!(
+            "../../../docs/audit/execution-event.example.json"
+        ));
+        assert!(matches!(
+            detect_and_validate(&event).unwrap(),
+            DetectedDocument::AuditEvent(e) if e.signer_ids == ["alice.dev"]
+        ));
+    }
+
+    #[test]
+    fn detected_but_invalid_documents_carry_the_report() {
+        let err = detect_and_validate(&json!({
+            "version": 1,
+            "trusted_signers": [],
+            "capability_ceiling": {"fs": {"read": ["relative"]}}
+        }))
+        .unwrap_err();
+        let DetectError::Invalid { kind, report, .. } = err else {
+            panic!("expected schema_invalid, got {err}");
+        };
+        assert_eq!(kind, DocumentKind::Policy);
+        assert_eq!(
+            report.errors[0].instance_path,
+            "/capability_ceiling/fs/read/0"
+        );
+    }
+
+    #[test]
+    fn detect_vectors() {
+        let vector: DetectVector =
+            serde_json::from_str(include_str!("../../../test-vectors/detect/documents.json"))
+                .unwrap();
+        for case in vector.cases {
+            match (detect_and_validate(&case.document), &case.expect_error) {
+                (Ok(doc), None) => {
+                    assert_eq!(
+                        Some(doc.kind().as_str()),
+                        case.expect_kind.as_deref(),
+                        "{}",
+                        case.name
+                    );
+                    assert_eq!(
+                        Some(doc.version()),
+                        case.expect_version.as_deref(),
+                        "{}",
+                        case.name
+                    );
+                }
+                (Err(e), Some(code)) => assert_eq!(e.code(), code, "{}: {e}", case.name),
+                (got, _) => panic!("{}: unexpected {got:?}", case.name),
+            }
+        }
+    }
+}
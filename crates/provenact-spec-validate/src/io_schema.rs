@@ -0,0 +1,535 @@
+//! Skill input/output schema enforcement (`spec/io-schemas.md`).
+//!
+//! `$ref`s resolve only within the schema itself or from a [`SchemaCache`]
+//! loaded ahead of time. Nothing is ever fetched over the network.
+
+use jsonschema::{JSONSchema, SchemaResolver, SchemaResolverError};
+use provenact_spec_rs::{sha256_prefixed, to_jcs_bytes, ManifestV1, SchemaRef, SpecError};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+#[derive(Debug, thiserror::Error)]
+pub enum IoSchemaError {
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("{path}: {message}")]
+    Cache { path: PathBuf, message: String },
+    #[error("$ref {reference:?} is not in the offline schema cache")]
+    UncachedRef { reference: String },
+    #[error("$ref {reference:?} is relative and its schema has no $id to resolve against")]
+    RelativeRef { reference: String },
+    #[error("$ref {reference:?} points at nothing")]
+    UnresolvedRef { reference: String },
+    #[error("legacy io descriptor is missing json_schema")]
+    MissingJsonSchema,
+    #[error("schema does not compile: {0}")]
+    Compile(String),
+    #[error("{direction} document does not match its schema: {}", .errors.join("; "))]
+    Invalid {
+        direction: IoDirection,
+        errors: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoDirection {
+    Input,
+    Output,
+}
+
+impl std::fmt::Display for IoDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            IoDirection::Input => "input",
+            IoDirection::Output => "output",
+        })
+    }
+}
+
+/// Schema documents available to URI `$ref`s, keyed by absolute URI without
+/// fragment.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCache {
+    documents: BTreeMap<String, Value>,
+}
+
+impl SchemaCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, uri: &str, document: Value) -> Result<(), IoSchemaError> {
+        let uri = cache_key(uri).ok_or_else(|| IoSchemaError::RelativeRef {
+            reference: uri.to_string(),
+        })?;
+        self.documents.insert(uri, document);
+        Ok(())
+    }
+
+    /// Loads every `*.json` file in `dir`, keyed by its top-level `$id`.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, IoSchemaError> {
+        let dir = dir.as_ref();
+        let cache_error = |path: &Path, message: String| IoSchemaError::Cache {
+            path: path.to_path_buf(),
+            message,
+        };
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(|e| cache_error(dir, e.to_string()))? {
+            let path = entry.map_err(|e| cache_error(dir, e.to_string()))?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut cache = Self::new();
+        for path in paths {
+            let raw =
+                std::fs::read_to_string(&path).map_err(|e| cache_error(&path, e.to_string()))?;
+            let document: Value =
+                serde_json::from_str(&raw).map_err(|e| cache_error(&path, e.to_string()))?;
+            let id = document
+                .get("$id")
+                .and_then(Value::as_str)
+                .ok_or_else(|| cache_error(&path, "missing top-level $id".to_string()))?
+                .to_string();
+            cache
+                .insert(&id, document)
+                .map_err(|e| cache_error(&path, e.to_string()))?;
+        }
+        Ok(cache)
+    }
+
+    pub fn get(&self, uri: &str) -> Option<&Value> {
+        self.documents.get(&cache_key(uri)?)
+    }
+}
+
+fn cache_key(uri: &str) -> Option<String> {
+    let mut url = Url::parse(uri).ok()?;
+    url.set_fragment(None);
+    Some(url.to_string())
+}
+
+/// Serves `$ref`s from the documents collected at compile time and refuses
+/// everything else.
+struct OfflineResolver {
+    documents: Arc<BTreeMap<String, Value>>,
+}
+
+impl SchemaResolver for OfflineResolver {
+    fn resolve(
+        &self,
+        _root_schema: &Value,
+        url: &Url,
+        original_reference: &str,
+    ) -> Result<Arc<Value>, SchemaResolverError> {
+        let mut key = url.clone();
+        key.set_fragment(None);
+        self.documents
+            .get(key.as_str())
+            .map(|document| Arc::new(document.clone()))
+            .ok_or_else(|| {
+                anyhow::anyhow!("{original_reference}: remote schema fetching is disabled")
+            })
+    }
+}
+
+/// A compiled input or output schema and its reference closure.
+pub struct IoSchema {
+    schema: Value,
+    documents: Arc<BTreeMap<String, Value>>,
+    digest: String,
+    compiled: JSONSchema,
+}
+
+impl std::fmt::Debug for IoSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IoSchema")
+            .field("digest", &self.digest)
+            .field("documents", &self.documents.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl IoSchema {
+    /// Compiles `schema`, first checking that every `$ref` reachable from it
+    /// is local and resolvable or served by `cache`.
+    pub fn compile(schema: Value, cache: &SchemaCache) -> Result<Self, IoSchemaError> {
+        let mut documents = BTreeMap::new();
+        collect_refs(&schema, &schema, None, cache, &mut documents)?;
+        let documents = Arc::new(documents);
+        let digest = sha256_prefixed(&to_jcs_bytes(&serde_json::json!({
+            "schema": &schema,
+            "documents": &*documents,
+        }))?);
+        let compiled = JSONSchema::options()
+            .with_resolver(OfflineResolver {
+                documents: documents.clone(),
+            })
+            .compile(&schema)
+            .map_err(|e| IoSchemaError::Compile(e.to_string()))?;
+        Ok(Self {
+            schema,
+            documents,
+            digest,
+            compiled,
+        })
+    }
+
+    /// Compiles a v1 `inputs_schema`/`outputs_schema`. A URI becomes
+    /// `{"$ref": uri}` and must be in `cache`.
+    pub fn from_schema_ref(schema: &SchemaRef, cache: &SchemaCache) -> Result<Self, IoSchemaError> {
+        match schema {
+            SchemaRef::Embedded(map) => Self::compile(Value::Object(map.clone()), cache),
+            SchemaRef::Uri(uri) => Self::compile(serde_json::json!({ "$ref": uri }), cache),
+        }
+    }
+
+    /// `sha256(JCS({"schema": ..., "documents": {uri: document}}))` over the
+    /// schema and every cached document it references.
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    fn check(&self, direction: IoDirection, document: &Value) -> Result<(), IoSchemaError> {
+        match self.compiled.validate(document) {
+            Ok(()) => Ok(()),
+            Err(errors) => Err(IoSchemaError::Invalid {
+                direction,
+                errors: errors
+                    .map(|e| format!("{}: {e}", e.instance_path))
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// Keywords whose values are instance data rather than subschemas.
+const DATA_KEYWORDS: [&str; 4] = ["const", "enum", "examples", "default"];
+
+/// Keywords whose values map arbitrary names to subschemas.
+const SCHEMA_MAP_KEYWORDS: [&str; 6] = [
+    "properties",
+    "patternProperties",
+    "definitions",
+    "$defs",
+    "dependencies",
+    "dependentSchemas",
+];
+
+/// The base URL a schema's `$id` establishes, joined onto `base` when
+/// relative. Plain-name fragment ids (`"#foo"`) do not change the base.
+fn rebase(node: &Value, base: Option<&Url>) -> Option<Option<Url>> {
+    let id = node.get("$id").and_then(Value::as_str)?;
+    if id.starts_with('#') {
+        return None;
+    }
+    Some(match base {
+        Some(base) => base.join(id).ok(),
+        None => Url::parse(id).ok(),
+    })
+}
+
+/// Walks the schema `node` inside the resource `document`, following every
+/// `$ref`. A subschema with its own `$id` becomes the resource its
+/// references resolve against.
+fn collect_refs(
+    node: &Value,
+    document: &Value,
+    base: Option<Url>,
+    cache: &SchemaCache,
+    seen: &mut BTreeMap<String, Value>,
+) -> Result<(), IoSchemaError> {
+    let (document, base) = match rebase(node, base.as_ref()) {
+        Some(rebased) => (node, rebased),
+        None => (document, base),
+    };
+    match node {
+        Value::Object(map) => {
+            if let Some(Value::String(reference)) = map.get("$ref") {
+                follow_ref(reference, document, base.clone(), cache, seen)?;
+            }
+            for (keyword, value) in map {
+                if DATA_KEYWORDS.contains(&keyword.as_str()) {
+                    continue;
+                }
+                match value {
+                    Value::Object(schemas) if SCHEMA_MAP_KEYWORDS.contains(&keyword.as_str()) => {
+                        for schema in schemas.values() {
+                            collect_refs(schema, document, base.clone(), cache, seen)?;
+                        }
+                    }
+                    _ => collect_refs(value, document, base.clone(), cache, seen)?,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for value in items {
+                collect_refs(value, document, base.clone(), cache, seen)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn follow_ref(
+    reference: &str,
+    document: &Value,
+    base: Option<Url>,
+    cache: &SchemaCache,
+    seen: &mut BTreeMap<String, Value>,
+) -> Result<(), IoSchemaError> {
+    if let Some(pointer) = reference.strip_prefix('#') {
+        return resolve_pointer(document, pointer, reference).map(|_| ());
+    }
+    let url = match (Url::parse(reference), &base) {
+        (Ok(url), _) => url,
+        (Err(url::ParseError::RelativeUrlWithoutBase), Some(base)) => base
+            .join(reference)
+            .map_err(|_| IoSchemaError::UnresolvedRef {
+                reference: reference.to_string(),
+            })?,
+        _ => {
+            return Err(IoSchemaError::RelativeRef {
+                reference: reference.to_string(),
+            })
+        }
+    };
+    let mut key = url.clone();
+    key.set_fragment(None);
+    if base.is_some_and(|base| base == key) {
+        return resolve_pointer(document, url.fragment().unwrap_or_default(), reference)
+            .map(|_| ());
+    }
+    let target = cache
+        .documents
+        .get(key.as_str())
+        .ok_or_else(|| IoSchemaError::UncachedRef {
+            reference: reference.to_string(),
+        })?;
+    resolve_pointer(target, url.fragment().unwrap_or_default(), reference)?;
+    if seen.insert(key.to_string(), target.clone()).is_none() {
+        collect_refs(target, target, Some(key), cache, seen)?;
+    }
+    Ok(())
+}
+
+fn resolve_pointer<'a>(
+    document: &'a Value,
+    pointer: &str,
+    reference: &str,
+) -> Result<&'a Value, IoSchemaError> {
+    document
+        .pointer(pointer)
+        .ok_or_else(|| IoSchemaError::UnresolvedRef {
+            reference: reference.to_string(),
+        })
+}
+
+/// Compiled input and output schemas of one manifest.
+#[derive(Debug)]
+pub struct IoSchemas {
+    pub inputs: IoSchema,
+    pub outputs: IoSchema,
+}
+
+impl IoSchemas {
+    pub fn from_manifest_v1(
+        manifest: &ManifestV1,
+        cache: &SchemaCache,
+    ) -> Result<Self, IoSchemaError> {
+        Ok(Self {
+            inputs: IoSchema::from_schema_ref(&manifest.inputs_schema, cache)?,
+            outputs: IoSchema::from_schema_ref(&manifest.outputs_schema, cache)?,
+        })
+    }
+
+    /// Compiles `inputs.json_schema` and `outputs.json_schema` of a legacy
+    /// v0 manifest (`spec/v0/skill-manifest.schema.json`).
+    ///
+    /// Legacy `#/schemas/<name>` refs resolve against `definitions`, the
+    /// named schemas shipped with the legacy package.
+    pub fn from_legacy_manifest(
+        manifest: &Value,
+        definitions: &Map<String, Value>,
+        cache: &SchemaCache,
+    ) -> Result<Self, IoSchemaError> {
+        let compile = |descriptor: &str| {
+            let schema = manifest
+                .get(descriptor)
+                .and_then(|io| io.get("json_schema"))
+                .ok_or(IoSchemaError::MissingJsonSchema)?;
+            let mut root = match schema {
+                Value::Object(map) => map.clone(),
+                _ => return Err(IoSchemaError::MissingJsonSchema),
+            };
+            if !definitions.is_empty() {
+                root.insert("schemas".to_string(), Value::Object(definitions.clone()));
+            }
+            IoSchema::compile(Value::Object(root), cache)
+        };
+        Ok(Self {
+            inputs: compile("inputs")?,
+            outputs: compile("outputs")?,
+        })
+    }
+
+    pub fn validate_input(&self, document: &Value) -> Result<(), IoSchemaError> {
+        self.inputs.check(IoDirection::Input, document)
+    }
+
+    pub fn validate_output(&self, document: &Value) -> Result<(), IoSchemaError> {
+        self.outputs.check(IoDirection::Output, document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discover_repo_root;
+    use provenact_spec_rs::parse_json;
+    use serde_json::json;
+
+    fn root() -> PathBuf {
+        discover_repo_root(".").unwrap()
+    }
+
+    fn read(rel: &str) -> Value {
+        serde_json::from_str(&std::fs::read_to_string(root().join(rel)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn v1_embedded_schemas_validate_documents() {
+        let manifest: ManifestV1 = parse_json(
+            &std::fs::read_to_string(
+                root().join("test-vectors/skill-format/manifest-v1/good/basic-draft.json"),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let schemas = IoSchemas::from_manifest_v1(&manifest, &SchemaCache::new()).unwrap();
+        assert!(schemas.validate_input(&json!({"msg": "hi"})).is_ok());
+        assert!(matches!(
+            schemas.validate_input(&json!({"msg": 1})),
+            Err(IoSchemaError::Invalid {
+                direction: IoDirection::Input,
+                ..
+            })
+        ));
+        assert!(schemas.validate_output(&json!({"ok": true})).is_ok());
+        assert!(schemas.validate_output(&json!({})).is_err());
+        assert_ne!(schemas.inputs.digest(), schemas.outputs.digest());
+    }
+
+    #[test]
+    fn uri_refs_resolve_only_from_cache() {
+        let cache = SchemaCache::load_dir(root().join("test-vectors/io-schema/cache")).unwrap();
+        let uri = SchemaRef::Uri("https://schemas.provenact.dev/fetch/v1/input.json".into());
+        let schema = IoSchema::from_schema_ref(&uri, &cache).unwrap();
+        assert!(schema
+            .check(IoDirection::Input, &json!({"url": "https://example.com"}))
+            .is_ok());
+        assert!(schema
+            .check(IoDirection::Input, &json!({"url": "ftp://example.com"}))
+            .is_err());
+        // The digest covers both cached documents, not just the `$ref` stub.
+        assert_eq!(schema.documents.len(), 2);
+        assert!(matches!(
+            IoSchema::from_schema_ref(&uri, &SchemaCache::new()),
+            Err(IoSchemaError::UncachedRef { .. })
+        ));
+
+        let remote = json!({"$ref": "https://evil.example/schema.json"});
+        assert!(matches!(
+            IoSchema::compile(remote, &cache),
+            Err(IoSchemaError::UncachedRef { .. })
+        ));
+        let relative = json!({"$ref": "other.json"});
+        assert!(matches!(
+            IoSchema::compile(relative, &cache),
+            Err(IoSchemaError::RelativeRef { .. })
+        ));
+        let dangling = json!({"$ref": "#/definitions/missing"});
+        assert!(matches!(
+            IoSchema::compile(dangling, &cache),
+            Err(IoSchemaError::UnresolvedRef { .. })
+        ));
+    }
+
+    #[test]
+    fn nested_ids_rebase_refs() {
+        let cache = SchemaCache::load_dir(root().join("test-vectors/io-schema/cache")).unwrap();
+        let nested = json!({
+            "type": "object",
+            "properties": {
+                "request": {
+                    "$id": "https://schemas.provenact.dev/fetch/v1/request.json",
+                    "$ref": "input.json"
+                },
+                "local": {
+                    "$id": "https://schemas.provenact.dev/local.json",
+                    "definitions": {"flag": {"type": "boolean"}},
+                    "$ref": "#/definitions/flag"
+                }
+            }
+        });
+        let schema = IoSchema::compile(nested, &cache).unwrap();
+        assert_eq!(schema.documents.len(), 2);
+        assert!(schema
+            .check(IoDirection::Input, &json!({"request": {"url": "ftp://x"}}))
+            .is_err());
+        assert!(schema
+            .check(IoDirection::Input, &json!({"local": "yes"}))
+            .is_err());
+    }
+
+    #[test]
+    fn data_keywords_are_not_walked_for_refs() {
+        let data = json!({
+            "const": {"$ref": "https://evil.example/const.json"},
+            "enum": [{"$ref": "other.json"}],
+            "examples": [{"$ref": "#/missing"}],
+            "default": {"$ref": "https://evil.example/default.json"}
+        });
+        assert!(IoSchema::compile(data, &SchemaCache::new()).is_ok());
+
+        // A property that happens to be named like a data keyword is a schema.
+        let property = json!({
+            "properties": {"enum": {"$ref": "https://evil.example/schema.json"}}
+        });
+        assert!(matches!(
+            IoSchema::compile(property, &SchemaCache::new()),
+            Err(IoSchemaError::UncachedRef { .. })
+        ));
+    }
+
+    #[test]
+    fn legacy_manifest_refs_resolve_against_definitions() {
+        let manifest = read("test-vectors/v0/skill-manifest/good/basic.json");
+        let definitions = read("test-vectors/io-schema/legacy-definitions.json");
+        let definitions = definitions.as_object().unwrap();
+        let schemas =
+            IoSchemas::from_legacy_manifest(&manifest, definitions, &SchemaCache::new()).unwrap();
+        assert!(schemas
+            .validate_input(&json!({"url": "https://example.com"}))
+            .is_ok());
+        assert!(schemas.validate_input(&json!({})).is_err());
+        assert!(schemas
+            .validate_output(&json!({"status": 200, "body": ""}))
+            .is_ok());
+
+        assert!(matches!(
+            IoSchemas::from_legacy_manifest(&manifest, &Map::new(), &SchemaCache::new()),
+            Err(IoSchemaError::UnresolvedRef { .. })
+        ));
+    }
+}
@@ -1,78 +1,138 @@
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{bail, Context, Result};
 use serde_json::Value;
-use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+mod detect;
+mod io_schema;
+mod policy;
+mod report;
+mod schema_registry;
+
+pub use detect::{
+    detect_and_validate, detect_kind, DetectCase, DetectError, DetectVector, DetectedDocument,
+};
+pub use io_schema::{IoDirection, IoSchema, IoSchemaError, IoSchemas, SchemaCache};
+pub use policy::{
+    load_policy, PolicyLoadCase, PolicyLoadError, PolicyLoadVector, PolicySource, YamlConstruct,
+};
+pub use report::{SourceLocation, ValidationIssue, ValidationReport};
+pub use schema_registry::{
+    DocumentKind, SchemaEntry, SchemaRegistry, SchemaRegistryError, SchemaSource, KIND_ANNOTATION,
+    UNVERSIONED, VERSION_ANNOTATION,
+};
+
+/// A [`SchemaRegistry`] bound to a repository checkout, adding the
+/// conformance vector formats and file-based document loading.
 pub struct SchemaStore {
     root: PathBuf,
-    schemas: HashMap<String, Value>,
+    registry: SchemaRegistry,
 }
 
 impl SchemaStore {
     pub fn load(root: impl AsRef<Path>) -> Result<Self> {
+        Self::with_registry(root, SchemaRegistry::embedded())
+    }
+
+    pub fn with_registry(root: impl AsRef<Path>, mut registry: SchemaRegistry) -> Result<Self> {
         let root = root.as_ref().to_path_buf();
-        let mut schemas = HashMap::new();
         for rel in [
-            "spec/v0/skill-manifest.schema.json",
-            "spec/v0/pipeline.schema.json",
-            "spec/policy/policy.schema.json",
-            "spec/execution-receipt.schema.json",
-            "spec/execution-receipt.v1.experimental.schema.json",
-            "spec/registry/snapshot.schema.json",
-            "spec/skill-format/manifest.schema.json",
-            "spec/skill-format/provenance.schema.json",
-            "spec/skill-format/signatures.schema.json",
-            "spec/skill-format/manifest.v1.experimental.schema.json",
             "test-vectors/capability-eval/schema.json",
+            "test-vectors/registry/resolve/schema.json",
+            "test-vectors/registry/signed/schema.json",
+            "test-vectors/signer-policy/schema.json",
+            "test-vectors/signer-trust/schema.json",
         ] {
             let schema_path = root.join(rel);
             let schema_text = fs::read_to_string(&schema_path)
                 .with_context(|| format!("reading schema {}", schema_path.display()))?;
             let schema_json: Value = serde_json::from_str(&schema_text)
                 .with_context(|| format!("parsing schema {}", schema_path.display()))?;
-            schemas.insert(rel.to_string(), schema_json);
+            registry.insert_path(rel, schema_json);
         }
-        Ok(Self { root, schemas })
+        Ok(Self { root, registry })
     }
 
     pub fn root(&self) -> &Path {
         &self.root
     }
 
+    pub fn registry(&self) -> &SchemaRegistry {
+        &self.registry
+    }
+
     pub fn validate_value(&self, schema_rel: &str, value: &Value) -> Result<()> {
-        let schema = self
-            .schemas
-            .get(schema_rel)
-            .ok_or_else(|| anyhow!("unknown schema {schema_rel}"))?;
-        if !jsonschema::is_valid(schema, value) {
-            bail!("schema {schema_rel} validation failed");
+        let report = self.validate_value_report(schema_rel, value)?;
+        if !report.is_valid() {
+            bail!("schema {schema_rel} validation failed\n{report}");
         }
         Ok(())
     }
 
+    /// Validates `value`, returning every error rather than failing on the
+    /// first. `schema_rel` is a repo path or `$id`; `Err` is reserved for
+    /// unknown or uncompilable schemas.
+    pub fn validate_value_report(
+        &self,
+        schema_rel: &str,
+        value: &Value,
+    ) -> Result<ValidationReport> {
+        Ok(self.registry.validate(schema_rel, value)?)
+    }
+
+    /// As [`Self::validate_value_report`], with issues located in the
+    /// source file by line and column.
+    pub fn validate_file_report(
+        &self,
+        schema_rel: &str,
+        doc_rel: &str,
+    ) -> Result<ValidationReport> {
+        let raw = self.read_doc_file(doc_rel)?;
+        let value = self.parse_doc_text(doc_rel, &raw)?;
+        Ok(self.validate_value_report(schema_rel, &value)?.with_source(
+            doc_rel,
+            &raw,
+            is_yaml(doc_rel),
+        ))
+    }
+
     pub fn parse_doc_file(&self, rel: &str) -> Result<Value> {
+        let raw = self.read_doc_file(rel)?;
+        self.parse_doc_text(rel, &raw)
+    }
+
+    fn read_doc_file(&self, rel: &str) -> Result<String> {
+        let path = self.root.join(rel);
+        fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))
+    }
+
+    fn parse_doc_text(&self, rel: &str, raw: &str) -> Result<Value> {
         let path = self.root.join(rel);
-        let raw =
-            fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
-        if rel.ends_with(".yaml") || rel.ends_with(".yml") {
-            let yaml: serde_yaml::Value = serde_yaml::from_str(&raw)
+        if is_yaml(rel) {
+            let yaml: serde_yaml::Value = serde_yaml::from_str(raw)
                 .with_context(|| format!("parsing yaml {}", path.display()))?;
             let json = serde_json::to_value(yaml)?;
             Ok(json)
         } else {
-            let json = serde_json::from_str(&raw)
+            let json = serde_json::from_str(raw)
                 .with_context(|| format!("parsing json {}", path.display()))?;
             Ok(json)
         }
     }
 
     pub fn validate_file(&self, schema_rel: &str, doc_rel: &str) -> Result<()> {
-        let value = self.parse_doc_file(doc_rel)?;
-        self.validate_value(schema_rel, &value)
+        let report = self.validate_file_report(schema_rel, doc_rel)?;
+        if !report.is_valid() {
+            bail!("schema {schema_rel} validation failed\n{report}");
+        }
+        Ok(())
     }
 }
 
+fn is_yaml(rel: &str) -> bool {
+    rel.ends_with(".yaml") || rel.ends_with(".yml")
+}
+
 pub fn discover_repo_root(from: impl AsRef<Path>) -> Result<PathBuf> {
     let mut cur = from.as_ref().canonicalize()?;
     loop {
@@ -0,0 +1,452 @@
+//! Typed policy loading from YAML or JSON with schema and semantic checks.
+
+use crate::{DocumentKind, SchemaRegistry, SchemaRegistryError, ValidationReport};
+use provenact_spec_rs::{validate_policy, Policy, PolicyIssue};
+use serde::de::{
+    self, Deserializer, EnumAccess, IgnoredAny, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy)]
+pub enum PolicySource<'a> {
+    Path(&'a Path),
+    /// YAML or JSON text.
+    Text(&'a str),
+}
+
+impl<'a> From<&'a Path> for PolicySource<'a> {
+    fn from(path: &'a Path) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl<'a> From<&'a PathBuf> for PolicySource<'a> {
+    fn from(path: &'a PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl<'a> From<&'a str> for PolicySource<'a> {
+    fn from(text: &'a str) -> Self {
+        Self::Text(text)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyLoadError {
+    #[error("reading {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("line {line} column {column}: YAML {construct} is not allowed in policies")]
+    Indirection {
+        construct: YamlConstruct,
+        line: usize,
+        column: usize,
+    },
+    #[error("duplicate mapping key {key}{}", .location.as_ref().map(|l| format!(" in the mapping at line {} column {}", l.line(), l.column())).unwrap_or_default())]
+    DuplicateKey {
+        key: String,
+        location: Option<serde_yaml::Location>,
+    },
+    #[error("parsing policy: {0}")]
+    Parse(serde_yaml::Error),
+    #[error("policy is not representable as JSON: {0}")]
+    NotJson(serde_json::Error),
+    #[error("policy failed schema validation\n{0}")]
+    Schema(ValidationReport),
+    #[error("policy does not match the typed model: {0}")]
+    Deserialize(serde_json::Error),
+    #[error("policy failed semantic validation: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Semantic(Vec<PolicyIssue>),
+    #[error(transparent)]
+    Registry(#[from] SchemaRegistryError),
+}
+
+impl PolicyLoadError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Io { .. } => "io",
+            Self::Indirection {
+                construct: YamlConstruct::Anchor,
+                ..
+            } => "yaml_anchor",
+            Self::Indirection {
+                construct: YamlConstruct::Alias,
+                ..
+            } => "yaml_alias",
+            Self::Indirection {
+                construct: YamlConstruct::MergeKey,
+                ..
+            } => "yaml_merge_key",
+            Self::DuplicateKey { .. } => "duplicate_key",
+            Self::Parse(_) => "parse",
+            Self::NotJson(_) => "not_json",
+            Self::Schema(_) => "schema_invalid",
+            Self::Deserialize(_) => "deserialize_failed",
+            Self::Semantic(_) => "semantic_invalid",
+            Self::Registry(_) => "registry",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YamlConstruct {
+    Anchor,
+    Alias,
+    MergeKey,
+}
+
+impl std::fmt::Display for YamlConstruct {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Anchor => "anchor",
+            Self::Alias => "alias",
+            Self::MergeKey => "merge key",
+        })
+    }
+}
+
+/// Loads a policy from a YAML or JSON file or string.
+///
+/// Anchors, aliases, merge keys, and duplicate keys are rejected so that
+/// every encoding of a policy reads the same way. The document must then
+/// satisfy the schema for its `version` (`spec/policy/policy.schema.json` or
+/// `policy.v2.schema.json`) and [`validate_policy`]. The
+/// typed result hashes identically whichever encoding it came from.
+pub fn load_policy<'a>(source: impl Into<PolicySource<'a>>) -> Result<Policy, PolicyLoadError> {
+    let (name, owned);
+    let text = match source.into() {
+        PolicySource::Path(path) => {
+            owned = fs::read_to_string(path).map_err(|source| PolicyLoadError::Io {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            name = path.display().to_string();
+            owned.as_str()
+        }
+        PolicySource::Text(text) => {
+            name = "<policy>".to_string();
+            text
+        }
+    };
+    reject_yaml_indirection(text)?;
+    let DuplicateKeys(duplicate) = serde_yaml::from_str(text).map_err(PolicyLoadError::Parse)?;
+    let parsed = serde_yaml::from_str::<serde_yaml::Value>(text);
+    if let Some(key) = duplicate {
+        // serde_yaml's own mapping rejects the same key; borrow the position
+        // of the mapping it reports.
+        let location = parsed.err().and_then(|e| e.location());
+        return Err(PolicyLoadError::DuplicateKey { key, location });
+    }
+    let yaml = parsed.map_err(PolicyLoadError::Parse)?;
+    let value: Value = serde_json::to_value(yaml).map_err(PolicyLoadError::NotJson)?;
+    // Unknown versions fail the version 1 schema's `version` constant.
+    let version = match value.get("version").and_then(Value::as_u64) {
+        Some(2) => "2",
+        _ => "1",
+    };
+    let report = SchemaRegistry::shared().validate_kind(DocumentKind::Policy, version, &value)?;
+    if !report.is_valid() {
+        let is_yaml = !text.trim_start().starts_with('{');
+        return Err(PolicyLoadError::Schema(
+            report.with_source(&name, text, is_yaml),
+        ));
+    }
+    let policy: Policy = serde_json::from_value(value).map_err(PolicyLoadError::Deserialize)?;
+    validate_policy(&policy).map_err(PolicyLoadError::Semantic)?;
+    Ok(policy)
+}
+
+/// Walks a YAML/JSON document and keeps the first mapping key that repeats
+/// within its mapping, rendered as YAML.
+struct DuplicateKeys(Option<String>);
+
+impl<'de> Deserialize<'de> for DuplicateKeys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DuplicateKeyVisitor;
+
+        impl<'de> Visitor<'de> for DuplicateKeyVisitor {
+            type Value = DuplicateKeys;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("any YAML value")
+            }
+
+            fn visit_bool<E: de::Error>(self, _: bool) -> Result<Self::Value, E> {
+                Ok(DuplicateKeys(None))
+            }
+
+            fn visit_i64<E: de::Error>(self, _: i64) -> Result<Self::Value, E> {
+                Ok(DuplicateKeys(None))
+            }
+
+            fn visit_i128<E: de::Error>(self, _: i128) -> Result<Self::Value, E> {
+                Ok(DuplicateKeys(None))
+            }
+
+            fn visit_u64<E: de::Error>(self, _: u64) -> Result<Self::Value, E> {
+                Ok(DuplicateKeys(None))
+            }
+
+            fn visit_u128<E: de::Error>(self, _: u128) -> Result<Self::Value, E> {
+                Ok(DuplicateKeys(None))
+            }
+
+            fn visit_f64<E: de::Error>(self, _: f64) -> Result<Self::Value, E> {
+                Ok(DuplicateKeys(None))
+            }
+
+            fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+                Ok(DuplicateKeys(None))
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(DuplicateKeys(None))
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(DuplicateKeys(None))
+            }
+
+            fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+                DuplicateKeys::deserialize(d)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut found = None;
+                while let Some(DuplicateKeys(inner)) = access.next_element()? {
+                    found = found.or(inner);
+                }
+                Ok(DuplicateKeys(found))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut keys = Vec::new();
+                let mut found = None;
+                while let Some(key) = access.next_key::<serde_yaml::Value>()? {
+                    let DuplicateKeys(inner) = access.next_value()?;
+                    if found.is_none() && keys.contains(&key) {
+                        found = Some(
+                            serde_yaml::to_string(&key)
+                                .map_err(de::Error::custom)?
+                                .trim_end()
+                                .to_string(),
+                        );
+                    }
+                    found = found.or(inner);
+                    keys.push(key);
+                }
+                Ok(DuplicateKeys(found))
+            }
+
+            /// Tagged values (`!tag value`).
+            fn visit_enum<A: EnumAccess<'de>>(self, access: A) -> Result<Self::Value, A::Error> {
+                let (IgnoredAny, variant) = access.variant()?;
+                variant.newtype_variant()
+            }
+        }
+
+        deserializer.deserialize_any(DuplicateKeyVisitor)
+    }
+}
+
+/// Conservatively rejects anchors (`&a`), aliases (`*a`), and merge keys
+/// (`<<`) outside quoted and block scalars and comments. A plain scalar
+/// that starts with `&` or `*` is always one of these in YAML, so policies
+/// must quote such values (e.g. `"*"` in `kv` allowlists).
+fn reject_yaml_indirection(text: &str) -> Result<(), PolicyLoadError> {
+    let found = |construct, line: usize, column: usize| PolicyLoadError::Indirection {
+        construct,
+        line: line + 1,
+        column: column + 1,
+    };
+    let mut quote: Option<char> = None;
+    let mut flow_depth = 0usize;
+    let mut block_parent: Option<usize> = None;
+
+    for (line_no, line) in text.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let indent = chars.iter().take_while(|c| **c == ' ').count();
+        if let Some(parent) = block_parent {
+            if chars.iter().all(|c| c.is_whitespace()) || indent > parent {
+                continue;
+            }
+            block_parent = None;
+        }
+        let mut at_token = quote.is_none();
+        let mut node_column = indent;
+        let mut i = 0;
+        if quote.is_none()
+            && (line.starts_with("---") || line.starts_with("..."))
+            && chars.get(3).is_none_or(|c| c.is_whitespace())
+        {
+            i = 3;
+        }
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            if let Some(q) = quote {
+                if q == '"' && c == '\\' {
+                    i += 2;
+                    continue;
+                }
+                if c == q {
+                    if q == '\'' && next == Some('\'') {
+                        i += 2;
+                        continue;
+                    }
+                    quote = None;
+                }
+                i += 1;
+                continue;
+            }
+            let followed_by_space = next.is_none_or(|n| n.is_whitespace());
+            match c {
+                ' ' | '\t' => {}
+                '#' if i == 0 || chars[i - 1].is_whitespace() => break,
+                '&' if at_token => return Err(found(YamlConstruct::Anchor, line_no, i)),
+                '*' if at_token => return Err(found(YamlConstruct::Alias, line_no, i)),
+                '<' if at_token && next == Some('<') => {
+                    let rest: String = chars[i + 2..].iter().collect();
+                    if rest.trim_start().starts_with(':') {
+                        return Err(found(YamlConstruct::MergeKey, line_no, i));
+                    }
+                    at_token = false;
+                }
+                '"' | '\'' if at_token => {
+                    quote = Some(c);
+                    if flow_depth == 0 {
+                        node_column = i;
+                    }
+                    at_token = false;
+                }
+                '-' | '?' if at_token && followed_by_space => node_column = i,
+                ':' if followed_by_space || flow_depth > 0 => at_token = true,
+                '[' | '{' if at_token => flow_depth += 1,
+                ']' | '}' if flow_depth > 0 => {
+                    flow_depth -= 1;
+                    at_token = false;
+                }
+                ',' if flow_depth > 0 => at_token = true,
+                '|' | '>' if at_token && flow_depth == 0 => {
+                    block_parent = Some(node_column);
+                    break;
+                }
+                _ => {
+                    if at_token && flow_depth == 0 {
+                        node_column = i;
+                    }
+                    at_token = false;
+                }
+            }
+            i += 1;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyLoadVector {
+    pub name: String,
+    pub cases: Vec<PolicyLoadCase>,
+}
+
+/// `source` is YAML or JSON text. Cases sharing an `expect_hash` are
+/// encodings of the same policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyLoadCase {
+    pub name: String,
+    pub source: String,
+    #[serde(default)]
+    pub expect_hash: Option<String>,
+    #[serde(default)]
+    pub expect_error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discover_repo_root;
+    use provenact_spec_rs::compute_policy_hash;
+
+    fn code(text: &str) -> &'static str {
+        load_policy(text).unwrap_err().code()
+    }
+
+    #[test]
+    fn repo_policies_load() {
+        let root = discover_repo_root(".").unwrap();
+        for rel in [
+            "spec/policy/policy.example.yaml",
+            "test-vectors/policy/valid/basic.yaml",
+            "test-vectors/policy/valid/sbom.yaml",
+            "test-vectors/policy/valid/signer_rules.yaml",
+        ] {
+            load_policy(&root.join(rel)).unwrap_or_else(|e| panic!("{rel}: {e}"));
+        }
+        let err = load_policy(&root.join("test-vectors/policy/invalid/relative_fs_path.yaml"))
+            .unwrap_err();
+        let PolicyLoadError::Schema(report) = err else {
+            panic!("expected schema error, got {err}");
+        };
+        assert_eq!(report.errors[0].location.map(|l| l.line), Some(9));
+    }
+
+    #[test]
+    fn indirection_is_rejected_with_position() {
+        let err = load_policy(
+            "version: 1\ntrusted_signers: [alice.dev]\ncapability_ceiling:\n  fs:\n    read: &ro [/data]\n",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            PolicyLoadError::Indirection {
+                construct: YamlConstruct::Anchor,
+                line: 5,
+                column: 11
+            }
+        ));
+        assert_eq!(code("a:\n  - x\n  - *ref\n"), "yaml_alias");
+        assert_eq!(code("a:\n  <<: {b: 1}\n"), "yaml_merge_key");
+        assert_eq!(code("{\"a\": 1, \"a\": 2}"), "duplicate_key");
+        assert!(matches!(
+            load_policy("a:\n  - b: 1\n    c: 2\n    b: 3\n").unwrap_err(),
+            PolicyLoadError::DuplicateKey { key, location: Some(location) }
+                if key == "b" && (location.line(), location.column()) == (2, 5)
+        ));
+        assert_eq!(code("a:\n  - b: 1\n  - b: 2\n"), "schema_invalid");
+    }
+
+    #[test]
+    fn quoted_and_block_scalars_are_not_indirection() {
+        reject_yaml_indirection(
+            "a: \"*\"\nb: '&x'\nc: x&y # *comment\nd: |\n  *text\n  &more\ne: [\"*\", 'it''s *']\n",
+        )
+        .unwrap();
+        assert!(reject_yaml_indirection("- k: |\n    ok\n  other: *x\n").is_err());
+    }
+
+    #[test]
+    fn yaml_and_json_encodings_hash_identically() {
+        let yaml = load_policy(
+            "version: 1\ntrusted_signers:\n  - alice.dev\ncapability_ceiling:\n  net:\n    - https://api.github.com\n",
+        )
+        .unwrap();
+        let json = load_policy(
+            r#"{"capability_ceiling": {"net": ["https://api.github.com"]}, "trusted_signers": ["alice.dev"], "version": 1}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            compute_policy_hash(&yaml).unwrap(),
+            compute_policy_hash(&json).unwrap()
+        );
+    }
+}
@@ -0,0 +1,458 @@
+//! Schema validation reports with instance pointers and source positions.
+
+use jsonschema::paths::PathChunk;
+use jsonschema::JSONSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// 1-based line and column in the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+    /// JSON pointer into the validated document (`""` is the root).
+    pub instance_path: String,
+    /// The failing schema keyword, e.g. `required` or `pattern`.
+    pub keyword: String,
+    /// JSON pointer to the keyword within the schema.
+    pub schema_path: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub schema: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub errors: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub(crate) fn collect(schema_name: &str, compiled: &JSONSchema, value: &Value) -> Self {
+        let errors = match compiled.validate(value) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .map(|e| ValidationIssue {
+                    instance_path: e.instance_path.to_string(),
+                    keyword: match e.schema_path.last() {
+                        Some(PathChunk::Keyword(keyword)) => keyword.to_string(),
+                        Some(PathChunk::Property(keyword)) => keyword.to_string(),
+                        Some(PathChunk::Index(_)) | None => String::new(),
+                    },
+                    schema_path: e.schema_path.to_string(),
+                    message: e.to_string(),
+                    location: None,
+                })
+                .collect(),
+        };
+        Self {
+            schema: schema_name.to_string(),
+            source: None,
+            errors,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Records `source` and attaches line/column to each issue. `text` is
+    /// the raw document; YAML is located by block structure (`is_yaml`).
+    pub fn with_source(mut self, source: &str, text: &str, is_yaml: bool) -> Self {
+        let locations = if is_yaml {
+            yaml_locations(text)
+        } else {
+            json_locations(text)
+        };
+        for issue in &mut self.errors {
+            issue.location = locate(&locations, &issue.instance_path);
+        }
+        self.source = Some(source.to_string());
+        self
+    }
+
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    /// One line per issue: `source:line:col: /pointer: message [keyword]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return writeln!(
+                f,
+                "{}: valid against {}",
+                self.source.as_deref().unwrap_or("<value>"),
+                self.schema
+            );
+        }
+        writeln!(f, "{} error(s) against {}:", self.errors.len(), self.schema)?;
+        for issue in &self.errors {
+            let source = self.source.as_deref().unwrap_or("<value>");
+            match issue.location {
+                Some(loc) => write!(f, "{source}:{}:{}: ", loc.line, loc.column)?,
+                None => write!(f, "{source}: ")?,
+            }
+            let pointer = if issue.instance_path.is_empty() {
+                "/"
+            } else {
+                &issue.instance_path
+            };
+            writeln!(f, "{pointer}: {} [{}]", issue.message, issue.keyword)?;
+        }
+        Ok(())
+    }
+}
+
+/// Location of `pointer`, or of its nearest located ancestor.
+fn locate(locations: &BTreeMap<String, SourceLocation>, pointer: &str) -> Option<SourceLocation> {
+    let mut pointer = pointer;
+    loop {
+        if let Some(location) = locations.get(pointer) {
+            return Some(*location);
+        }
+        pointer = &pointer[..pointer.rfind('/')?];
+    }
+}
+
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+/// Positions of every value in a JSON text, keyed by JSON pointer. Object
+/// members are located at their key.
+fn json_locations(text: &str) -> BTreeMap<String, SourceLocation> {
+    let mut scanner = JsonScanner {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+        out: BTreeMap::new(),
+    };
+    scanner.skip_ws();
+    let here = scanner.location();
+    scanner.value(String::new(), here);
+    scanner.out
+}
+
+struct JsonScanner {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+    out: BTreeMap<String, SourceLocation>,
+}
+
+impl JsonScanner {
+    fn location(&self) -> SourceLocation {
+        SourceLocation {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn string(&mut self) -> String {
+        let mut out = String::new();
+        self.bump();
+        while let Some(c) = self.bump() {
+            match c {
+                '"' => break,
+                '\\' => {
+                    if let Some(escaped) = self.bump() {
+                        out.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            'r' => '\r',
+                            'b' => '\u{8}',
+                            'f' => '\u{c}',
+                            'u' => {
+                                let hex: String = (0..4).filter_map(|_| self.bump()).collect();
+                                u32::from_str_radix(&hex, 16)
+                                    .ok()
+                                    .and_then(char::from_u32)
+                                    .unwrap_or('\u{fffd}')
+                            }
+                            other => other,
+                        });
+                    }
+                }
+                other => out.push(other),
+            }
+        }
+        out
+    }
+
+    /// Scans one value, recording `pointer` at `at`. Malformed input stops
+    /// the scan; the document was already parsed successfully.
+    fn value(&mut self, pointer: String, at: SourceLocation) {
+        self.out.insert(pointer.clone(), at);
+        match self.peek() {
+            Some('{') => {
+                self.bump();
+                loop {
+                    self.skip_ws();
+                    match self.peek() {
+                        Some('"') => {}
+                        Some('}') | None => {
+                            self.bump();
+                            return;
+                        }
+                        Some(_) => {
+                            self.bump();
+                            continue;
+                        }
+                    }
+                    let key_at = self.location();
+                    let key = self.string();
+                    self.skip_ws();
+                    self.bump(); // ':'
+                    self.skip_ws();
+                    self.value(format!("{pointer}/{}", escape(&key)), key_at);
+                    self.skip_ws();
+                    if self.peek() == Some(',') {
+                        self.bump();
+                    }
+                }
+            }
+            Some('[') => {
+                self.bump();
+                let mut index = 0usize;
+                loop {
+                    self.skip_ws();
+                    match self.peek() {
+                        Some(']') | None => {
+                            self.bump();
+                            return;
+                        }
+                        Some(',') => {
+                            self.bump();
+                            continue;
+                        }
+                        Some(_) => {}
+                    }
+                    let item_at = self.location();
+                    self.value(format!("{pointer}/{index}"), item_at);
+                    index += 1;
+                }
+            }
+            Some('"') => {
+                self.string();
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, ',' | '}' | ']') && !c.is_whitespace())
+                {
+                    self.bump();
+                }
+            }
+        }
+    }
+}
+
+/// Best-effort positions for block-style YAML, keyed by JSON pointer.
+///
+/// Block mappings and `- ` sequences are located; flow collections and
+/// multi-line keys are not, and resolve to their nearest located ancestor.
+fn yaml_locations(text: &str) -> BTreeMap<String, SourceLocation> {
+    struct Frame {
+        indent: usize,
+        pointer: String,
+        is_item: bool,
+    }
+    let mut out = BTreeMap::new();
+    out.insert(String::new(), SourceLocation { line: 1, column: 1 });
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut next_index: BTreeMap<String, usize> = BTreeMap::new();
+    let mut block_scalar_indent: Option<usize> = None;
+
+    for (line_no, line) in text.lines().enumerate() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let content = line[indent..].trim_end();
+        if let Some(block) = block_scalar_indent {
+            if content.is_empty() || indent > block {
+                continue;
+            }
+            block_scalar_indent = None;
+        }
+        if content.is_empty() || content.starts_with('#') || content == "---" || content == "..." {
+            continue;
+        }
+
+        let mut column = indent;
+        let mut rest = content;
+        loop {
+            let is_item = rest == "-" || rest.starts_with("- ");
+            while let Some(top) = stack.last() {
+                // A sequence may sit at its parent key's indentation.
+                let owns_item = is_item && !top.is_item && top.indent == column;
+                if top.indent > column || (top.indent == column && !owns_item) {
+                    stack.pop();
+                } else {
+                    break;
+                }
+            }
+            let parent = stack.last().map_or(String::new(), |f| f.pointer.clone());
+            let location = SourceLocation {
+                line: line_no + 1,
+                column: column + 1,
+            };
+            if is_item {
+                let index = next_index.entry(parent.clone()).or_insert(0);
+                let pointer = format!("{parent}/{index}");
+                *index += 1;
+                out.insert(pointer.clone(), location);
+                stack.push(Frame {
+                    indent: column,
+                    pointer,
+                    is_item: true,
+                });
+                let after = rest[1..].trim_start_matches(' ');
+                if after.is_empty() {
+                    break;
+                }
+                column += rest.len() - after.len();
+                rest = after;
+                continue;
+            }
+            let Some((key, value)) = split_yaml_key(rest) else {
+                break;
+            };
+            let pointer = format!("{parent}/{}", escape(&key));
+            out.insert(pointer.clone(), location);
+            if value.starts_with('|') || value.starts_with('>') {
+                block_scalar_indent = Some(column);
+            }
+            stack.push(Frame {
+                indent: column,
+                pointer,
+                is_item: false,
+            });
+            break;
+        }
+    }
+    out
+}
+
+/// Splits `key: value` (or `key:`) with plain or quoted keys.
+fn split_yaml_key(content: &str) -> Option<(String, &str)> {
+    let (key, after) = match content.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = content[1..].find(quote)? + 1;
+            (content[1..end].to_string(), &content[end + 1..])
+        }
+        '{' | '[' => return None,
+        _ => {
+            let end = content
+                .match_indices(':')
+                .map(|(i, _)| i)
+                .find(|&i| content[i + 1..].is_empty() || content[i + 1..].starts_with(' '))?;
+            (content[..end].trim_end().to_string(), &content[end..])
+        }
+    };
+    let value = after.strip_prefix(':')?;
+    Some((key, value.trim_start()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loc(line: usize, column: usize) -> Option<SourceLocation> {
+        Some(SourceLocation { line, column })
+    }
+
+    #[test]
+    fn json_pointers_map_to_keys_and_items() {
+        let text =
+            "{\n  \"a\": {\"b/c\": 1},\n  \"list\": [\n    true,\n    {\"x\": \"y\"}\n  ]\n}\n";
+        let locations = json_locations(text);
+        assert_eq!(locate(&locations, ""), loc(1, 1));
+        assert_eq!(locate(&locations, "/a/b~1c"), loc(2, 9));
+        assert_eq!(locate(&locations, "/list/1/x"), loc(5, 6));
+        assert_eq!(locate(&locations, "/list/0/missing"), loc(4, 5));
+    }
+
+    #[test]
+    fn yaml_block_structure_is_located() {
+        let text = "\
+version: 1
+# comment
+capabilities:
+  fs:
+    read:
+      - /data
+      - /tmp
+  net:
+  - host: example.com
+    port: 443
+notes: |
+  key: not a key
+exec: false
+";
+        let locations = yaml_locations(text);
+        assert_eq!(locate(&locations, "/version"), loc(1, 1));
+        assert_eq!(locate(&locations, "/capabilities/fs/read/1"), loc(7, 7));
+        assert_eq!(locate(&locations, "/capabilities/net/0"), loc(9, 3));
+        assert_eq!(locate(&locations, "/capabilities/net/0/port"), loc(10, 5));
+        assert_eq!(locate(&locations, "/exec"), loc(13, 1));
+        assert!(!locations.contains_key("/notes/key"));
+    }
+
+    #[test]
+    fn store_reports_yaml_errors_with_locations() {
+        let store = crate::SchemaStore::load(crate::discover_repo_root(".").unwrap()).unwrap();
+        let report = store
+            .validate_file_report(
+                "spec/policy/policy.schema.json",
+                "test-vectors/policy/invalid/relative_fs_path.yaml",
+            )
+            .unwrap();
+        let issue = report
+            .errors
+            .iter()
+            .find(|issue| issue.instance_path == "/capability_ceiling/fs/read/0")
+            .expect("relative path reported");
+        assert_eq!(issue.location, loc(9, 7));
+        assert!(!issue.keyword.is_empty());
+        assert!(issue.schema_path.ends_with(&issue.keyword));
+        assert!(report.to_string().contains(
+            "test-vectors/policy/invalid/relative_fs_path.yaml:9:7: /capability_ceiling/fs/read/0"
+        ));
+        assert_eq!(
+            report.to_json()["errors"][0]["location"],
+            serde_json::json!({"line": 9, "column": 7})
+        );
+    }
+}
@@ -0,0 +1,558 @@
+//! Normative schemas embedded at build time, addressable by `$id`, repo
+//! path, or `(DocumentKind, version)`.
+
+use crate::ValidationReport;
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+/// Version key for document formats that carry no version field.
+pub const UNVERSIONED: &str = "v0";
+
+/// Override annotations naming the key of a schema with a new `$id`.
+pub const KIND_ANNOTATION: &str = "x-provenact-kind";
+pub const VERSION_ANNOTATION: &str = "x-provenact-version";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaRegistryError {
+    #[error("reading {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("parsing schema {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("schema {0} has no $id")]
+    MissingId(PathBuf),
+    #[error("schema {path}: new $id {id} needs {KIND_ANNOTATION} and {VERSION_ANNOTATION}")]
+    MissingKey { path: PathBuf, id: String },
+    #[error("unknown document kind {0:?}")]
+    UnknownKind(String),
+    #[error("unknown schema {0}")]
+    UnknownSchema(String),
+    #[error("compiling schema {id}: {message}")]
+    Compile { id: String, message: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DocumentKind {
+    Manifest,
+    LegacyManifest,
+    Pipeline,
+    Policy,
+    Receipt,
+    Snapshot,
+    SignedSnapshot,
+    Signatures,
+    Provenance,
+    TrustAnchors,
+    InstallMeta,
+    InstallIndex,
+    AuditEvent,
+}
+
+impl DocumentKind {
+    pub const ALL: [DocumentKind; 13] = [
+        Self::Manifest,
+        Self::LegacyManifest,
+        Self::Pipeline,
+        Self::Policy,
+        Self::Receipt,
+        Self::Snapshot,
+        Self::SignedSnapshot,
+        Self::Signatures,
+        Self::Provenance,
+        Self::TrustAnchors,
+        Self::InstallMeta,
+        Self::InstallIndex,
+        Self::AuditEvent,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Manifest => "manifest",
+            Self::LegacyManifest => "legacy-manifest",
+            Self::Pipeline => "pipeline",
+            Self::Policy => "policy",
+            Self::Receipt => "receipt",
+            Self::Snapshot => "snapshot",
+            Self::SignedSnapshot => "signed-snapshot",
+            Self::Signatures => "signatures",
+            Self::Provenance => "provenance",
+            Self::TrustAnchors => "trust-anchors",
+            Self::InstallMeta => "install-meta",
+            Self::InstallIndex => "install-index",
+            Self::AuditEvent => "audit-event",
+        }
+    }
+}
+
+impl fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DocumentKind {
+    type Err = SchemaRegistryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| SchemaRegistryError::UnknownKind(s.to_string()))
+    }
+}
+
+/// Embedded schemas as `(repo path, kind, version, text)`.
+const EMBEDDED: &[(&str, DocumentKind, &str, &str)] = &[
+    (
+        "spec/skill-format/manifest.schema.json",
+        DocumentKind::Manifest,
+        UNVERSIONED,
+        include_str!("../../../spec/skill-format/manifest.schema.json"),
+    ),
+    (
+        "spec/skill-format/manifest.v1.experimental.schema.json",
+        DocumentKind::Manifest,
+        "1.0.0-draft",
+        include_str!("../../../spec/skill-format/manifest.v1.experimental.schema.json"),
+    ),
+    (
+        "spec/v0/skill-manifest.schema.json",
+        DocumentKind::LegacyManifest,
+        UNVERSIONED,
+        include_str!("../../../spec/v0/skill-manifest.schema.json"),
+    ),
+    (
+        "spec/v0/pipeline.schema.json",
+        DocumentKind::Pipeline,
+        UNVERSIONED,
+        include_str!("../../../spec/v0/pipeline.schema.json"),
+    ),
+    (
+        "spec/policy/policy.schema.json",
+        DocumentKind::Policy,
+        "1",
+        include_str!("../../../spec/policy/policy.schema.json"),
+    ),
+    (
+        "spec/policy/policy.v2.schema.json",
+        DocumentKind::Policy,
+        "2",
+        include_str!("../../../spec/policy/policy.v2.schema.json"),
+    ),
+    (
+        "spec/execution-receipt.schema.json",
+        DocumentKind::Receipt,
+        UNVERSIONED,
+        include_str!("../../../spec/execution-receipt.schema.json"),
+    ),
+    (
+        "spec/execution-receipt.v1.experimental.schema.json",
+        DocumentKind::Receipt,
+        "1.0.0-draft",
+        include_str!("../../../spec/execution-receipt.v1.experimental.schema.json"),
+    ),
+    (
+        "spec/registry/snapshot.schema.json",
+        DocumentKind::Snapshot,
+        UNVERSIONED,
+        include_str!("../../../spec/registry/snapshot.schema.json"),
+    ),
+    (
+        "spec/registry/snapshot.v1.schema.json",
+        DocumentKind::Snapshot,
+        "1",
+        include_str!("../../../spec/registry/snapshot.v1.schema.json"),
+    ),
+    (
+        "spec/registry/signed-snapshot.schema.json",
+        DocumentKind::SignedSnapshot,
+        UNVERSIONED,
+        include_str!("../../../spec/registry/signed-snapshot.schema.json"),
+    ),
+    (
+        "spec/skill-format/signatures.schema.json",
+        DocumentKind::Signatures,
+        UNVERSIONED,
+        include_str!("../../../spec/skill-format/signatures.schema.json"),
+    ),
+    (
+        "spec/skill-format/provenance.schema.json",
+        DocumentKind::Provenance,
+        UNVERSIONED,
+        include_str!("../../../spec/skill-format/provenance.schema.json"),
+    ),
+    (
+        "spec/trust/trust-anchors.schema.json",
+        DocumentKind::TrustAnchors,
+        "1",
+        include_str!("../../../spec/trust/trust-anchors.schema.json"),
+    ),
+    (
+        "spec/install/meta.schema.json",
+        DocumentKind::InstallMeta,
+        "1.0.0",
+        include_str!("../../../spec/install/meta.schema.json"),
+    ),
+    (
+        "spec/install/index.schema.json",
+        DocumentKind::InstallIndex,
+        "1.0.0",
+        include_str!("../../../spec/install/index.schema.json"),
+    ),
+    (
+        "docs/audit/execution-event.schema.json",
+        DocumentKind::AuditEvent,
+        "1.0.0",
+        include_str!("../../../docs/audit/execution-event.schema.json"),
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaSource {
+    Embedded,
+    Override(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub struct SchemaEntry {
+    pub id: String,
+    /// Repo-relative path for embedded schemas.
+    pub path: Option<String>,
+    pub kind: Option<DocumentKind>,
+    pub version: Option<String>,
+    pub source: SchemaSource,
+    pub schema: Value,
+    /// `schema` compiled on first use; reset whenever `schema` is replaced.
+    compiled: OnceLock<Result<Arc<jsonschema::JSONSchema>, String>>,
+}
+
+impl SchemaEntry {
+    fn compiled(&self) -> Result<&jsonschema::JSONSchema, SchemaRegistryError> {
+        self.compiled
+            .get_or_init(|| {
+                jsonschema::JSONSchema::compile(&self.schema)
+                    .map(Arc::new)
+                    .map_err(|e| e.to_string())
+            })
+            .as_deref()
+            .map_err(|message| SchemaRegistryError::Compile {
+                id: self.id.clone(),
+                message: message.clone(),
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SchemaRegistry {
+    entries: Vec<SchemaEntry>,
+}
+
+impl SchemaRegistry {
+    /// The schemas compiled into this crate; touches no filesystem.
+    pub fn embedded() -> Self {
+        let entries = EMBEDDED
+            .iter()
+            .map(|&(path, kind, version, text)| {
+                let schema: Value = serde_json::from_str(text)
+                    .unwrap_or_else(|e| panic!("embedded schema {path}: {e}"));
+                SchemaEntry {
+                    id: schema_id(&schema)
+                        .unwrap_or_else(|| panic!("embedded schema {path} has no $id"))
+                        .to_string(),
+                    path: Some(path.to_string()),
+                    kind: Some(kind),
+                    version: Some(version.to_string()),
+                    source: SchemaSource::Embedded,
+                    schema,
+                    compiled: OnceLock::new(),
+                }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// A process-wide [`Self::embedded`] registry.
+    pub fn shared() -> &'static Self {
+        static EMBEDDED_REGISTRY: OnceLock<SchemaRegistry> = OnceLock::new();
+        EMBEDDED_REGISTRY.get_or_init(Self::embedded)
+    }
+
+    /// Loads every `*.json` schema in `dir` (non-recursive, sorted by name).
+    ///
+    /// A schema whose `$id` matches a registered one replaces it and keeps
+    /// its key. Any other schema is added under the key named by its
+    /// `x-provenact-kind`/`x-provenact-version` annotations, replacing the
+    /// schema previously registered for that key.
+    pub fn with_override_dir(mut self, dir: impl AsRef<Path>) -> Result<Self, SchemaRegistryError> {
+        let dir = dir.as_ref();
+        let read_dir = fs::read_dir(dir).map_err(|source| SchemaRegistryError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+        let mut paths = Vec::new();
+        for entry in read_dir {
+            let path = entry
+                .map_err(|source| SchemaRegistryError::Io {
+                    path: dir.to_path_buf(),
+                    source,
+                })?
+                .path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in paths {
+            self.insert_override(&path)?;
+        }
+        Ok(self)
+    }
+
+    fn insert_override(&mut self, path: &Path) -> Result<(), SchemaRegistryError> {
+        let text = fs::read_to_string(path).map_err(|source| SchemaRegistryError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let schema: Value =
+            serde_json::from_str(&text).map_err(|source| SchemaRegistryError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        let id = schema_id(&schema)
+            .ok_or_else(|| SchemaRegistryError::MissingId(path.to_path_buf()))?
+            .to_string();
+        let source = SchemaSource::Override(path.to_path_buf());
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == id) {
+            entry.schema = schema;
+            entry.source = source;
+            entry.compiled = OnceLock::new();
+            return Ok(());
+        }
+        let kind = schema.get(KIND_ANNOTATION).and_then(Value::as_str);
+        let version = schema.get(VERSION_ANNOTATION).and_then(Value::as_str);
+        let (Some(kind), Some(version)) = (kind, version) else {
+            return Err(SchemaRegistryError::MissingKey {
+                path: path.to_path_buf(),
+                id,
+            });
+        };
+        let kind: DocumentKind = kind.parse()?;
+        let version = version.to_string();
+        self.entries
+            .retain(|e| !(e.kind == Some(kind) && e.version.as_deref() == Some(&version)));
+        self.entries.push(SchemaEntry {
+            id,
+            path: None,
+            kind: Some(kind),
+            version: Some(version),
+            source,
+            schema,
+            compiled: OnceLock::new(),
+        });
+        Ok(())
+    }
+
+    /// Registers an unkeyed schema addressable by `path` (and its `$id`, if
+    /// any), e.g. a conformance vector format.
+    pub fn insert_path(&mut self, path: &str, schema: Value) {
+        let id = schema_id(&schema).unwrap_or(path).to_string();
+        self.entries.retain(|e| e.path.as_deref() != Some(path));
+        self.entries.push(SchemaEntry {
+            id,
+            path: Some(path.to_string()),
+            kind: None,
+            version: None,
+            source: SchemaSource::Embedded,
+            schema,
+            compiled: OnceLock::new(),
+        });
+    }
+
+    pub fn entries(&self) -> &[SchemaEntry] {
+        &self.entries
+    }
+
+    pub fn by_id(&self, id: &str) -> Option<&SchemaEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    pub fn by_kind(&self, kind: DocumentKind, version: &str) -> Option<&SchemaEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.kind == Some(kind) && e.version.as_deref() == Some(version))
+    }
+
+    /// Looks up a `$id` or an embedded schema's repo path.
+    pub fn get(&self, key: &str) -> Option<&SchemaEntry> {
+        self.by_id(key)
+            .or_else(|| self.entries.iter().find(|e| e.path.as_deref() == Some(key)))
+    }
+
+    /// Validates `value` against the schema named by `key` (see [`Self::get`]).
+    pub fn validate(
+        &self,
+        key: &str,
+        value: &Value,
+    ) -> Result<ValidationReport, SchemaRegistryError> {
+        let entry = self
+            .get(key)
+            .ok_or_else(|| SchemaRegistryError::UnknownSchema(key.to_string()))?;
+        Self::validate_entry(key, entry, value)
+    }
+
+    pub fn validate_kind(
+        &self,
+        kind: DocumentKind,
+        version: &str,
+        value: &Value,
+    ) -> Result<ValidationReport, SchemaRegistryError> {
+        let entry = self
+            .by_kind(kind, version)
+            .ok_or_else(|| SchemaRegistryError::UnknownSchema(format!("{kind}/{version}")))?;
+        Self::validate_entry(&entry.id, entry, value)
+    }
+
+    fn validate_entry(
+        name: &str,
+        entry: &SchemaEntry,
+        value: &Value,
+    ) -> Result<ValidationReport, SchemaRegistryError> {
+        Ok(ValidationReport::collect(name, entry.compiled()?, value))
+    }
+}
+
+impl Default for SchemaRegistry {
+    fn default() -> Self {
+        Self::embedded()
+    }
+}
+
+fn schema_id(schema: &Value) -> Option<&str> {
+    schema.get("$id").and_then(Value::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn embedded_schemas_compile_and_are_keyed() {
+        let registry = SchemaRegistry::embedded();
+        for entry in registry.entries() {
+            assert!(
+                entry.id.starts_with("https://provenact.dev/"),
+                "{}",
+                entry.id
+            );
+            let path = entry.path.as_deref().unwrap();
+            assert!(entry.id.ends_with(path), "{} vs {path}", entry.id);
+            let compiled = entry
+                .compiled()
+                .unwrap_or_else(|e| panic!("{}: {e}", entry.id));
+            assert!(std::ptr::eq(compiled, entry.compiled().unwrap()));
+        }
+        let policy = registry.by_kind(DocumentKind::Policy, "1").unwrap();
+        assert_eq!(
+            registry
+                .get("https://provenact.dev/spec/policy/policy.schema.json")
+                .unwrap()
+                .path,
+            policy.path
+        );
+        assert_eq!(
+            registry.by_kind(DocumentKind::Policy, "2").unwrap().id,
+            "https://provenact.dev/spec/policy/policy.v2.schema.json"
+        );
+        assert!(registry.by_kind(DocumentKind::Policy, "3").is_none());
+    }
+
+    #[test]
+    fn validates_manifests_without_a_checkout() {
+        let registry = SchemaRegistry::embedded();
+        let manifest: Value = serde_json::from_str(include_str!(
+            "../../../test-vectors/good/minimal-zero-cap/manifest.json"
+        ))
+        .unwrap();
+        let report = registry
+            .validate_kind(DocumentKind::Manifest, UNVERSIONED, &manifest)
+            .unwrap();
+        assert!(report.is_valid(), "{report}");
+        let report = registry
+            .validate_kind(DocumentKind::Manifest, UNVERSIONED, &json!({"name": 1}))
+            .unwrap();
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn override_dir_replaces_and_adds_schemas() {
+        let dir =
+            std::env::temp_dir().join(format!("provenact-schema-override-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("a-policy.json"),
+            json!({
+                "$id": "https://provenact.dev/spec/policy/policy.schema.json",
+                "type": "object",
+                "required": ["experimental"]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("b-policy-v2.json"),
+            json!({
+                "$id": "https://example.test/policy.v2.json",
+                KIND_ANNOTATION: "policy",
+                VERSION_ANNOTATION: "2",
+                "type": "object"
+            })
+            .to_string(),
+        )
+        .unwrap();
+        // Compile and cache the embedded schema before it is overridden.
+        let registry = SchemaRegistry::embedded();
+        assert!(!registry
+            .validate(
+                "spec/policy/policy.schema.json",
+                &json!({"experimental": 1})
+            )
+            .unwrap()
+            .is_valid());
+        let registry = registry.with_override_dir(&dir).unwrap();
+        assert!(registry
+            .validate(
+                "spec/policy/policy.schema.json",
+                &json!({"experimental": 1})
+            )
+            .unwrap()
+            .is_valid());
+
+        let v1 = registry.by_kind(DocumentKind::Policy, "1").unwrap();
+        assert_eq!(v1.source, SchemaSource::Override(dir.join("a-policy.json")));
+        assert!(!registry
+            .validate("spec/policy/policy.schema.json", &json!({}))
+            .unwrap()
+            .is_valid());
+        assert_eq!(
+            registry.by_kind(DocumentKind::Policy, "2").unwrap().id,
+            "https://example.test/policy.v2.json"
+        );
+
+        fs::write(dir.join("c-bad.json"), json!({"$id": "urn:x"}).to_string()).unwrap();
+        assert!(matches!(
+            SchemaRegistry::embedded().with_override_dir(&dir),
+            Err(SchemaRegistryError::MissingKey { .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -113,6 +113,129 @@ function netUriWithinPrefix(requested, allowed) {
   return isWithinPrefix(requestedPath, allowedPath);
 }
 
+// Policy version 2 `kv`/`queue` patterns (spec/policy/policy.md "Name
+// Patterns"): segments split at `/` (kv) or `.` (queue), each a literal,
+// `*` (one non-empty segment) or `p*` (a longer segment starting with `p`),
+// with a final `>` matching the non-empty rest. A bare `*` matches any name.
+// Invalid patterns match nothing.
+const NAME_PATTERN_POLICY_VERSION = 2;
+
+function parseNamePattern(pattern, separator) {
+  if (pattern === "") return null;
+  if (pattern === "*") return { segments: null, rest: false };
+  const parts = pattern.split(separator);
+  const segments = [];
+  let rest = false;
+  for (const [index, part] of parts.entries()) {
+    if (part === "") return null;
+    if (part === ">") {
+      if (index + 1 !== parts.length) return null;
+      rest = true;
+      continue;
+    }
+    const star = part.indexOf("*");
+    if (star === -1) {
+      segments.push({ literal: part });
+    } else if (star === part.length - 1) {
+      segments.push(star === 0 ? { any: true } : { prefix: part.slice(0, -1) });
+    } else {
+      return null;
+    }
+  }
+  return { segments, rest };
+}
+
+function segmentMatches(segment, part) {
+  if (segment.any) return part.length > 0;
+  if (segment.prefix !== undefined) {
+    return part.length > segment.prefix.length && part.startsWith(segment.prefix);
+  }
+  return part === segment.literal;
+}
+
+function namePatternMatches(pattern, separator, name) {
+  const parsed = parseNamePattern(pattern, separator);
+  if (!parsed) return false;
+  if (!parsed.segments) return true;
+  const { segments, rest } = parsed;
+  const parts = name.split(separator);
+  const arityOk = rest
+    ? parts.length > segments.length &&
+      !(parts.length === segments.length + 1 && parts[segments.length] === "")
+    : parts.length === segments.length;
+  return arityOk && segments.every((segment, index) => segmentMatches(segment, parts[index]));
+}
+
+function nameAllowed(policy, entries, separator, value) {
+  if (!Array.isArray(entries) || value.length === 0) return false;
+  if ((policy.version ?? 1) >= NAME_PATTERN_POLICY_VERSION) {
+    return entries.some((entry) => namePatternMatches(entry, separator, value));
+  }
+  return entries.includes("*") || entries.includes(value);
+}
+
+// A `net` entry is a prefix string or, in policy version 2, a rule
+// `{prefix, methods, max_request_bytes, max_response_bytes, timeout_ms}`
+// (spec/policy/policy.md "Net Rules").
+const NET_LIMIT_KEYS = ["max_request_bytes", "max_response_bytes", "timeout_ms"];
+
+function isValidHttpMethod(value) {
+  return /^[A-Z]+$/.test(value);
+}
+
+function netEntryPrefix(entry) {
+  return typeof entry === "string" ? entry : entry?.prefix;
+}
+
+// A request without a method only matches entries that allow any method.
+function netEntryAllowsMethod(entry, method) {
+  const methods = typeof entry === "string" ? [] : entry.methods ?? [];
+  return methods.length === 0 || (method !== undefined && methods.includes(method));
+}
+
+function netEntryAllows(entry, requested, method) {
+  const prefix = netEntryPrefix(entry);
+  if (typeof prefix !== "string") return false;
+  let allowed;
+  try {
+    allowed = new URL(prefix);
+  } catch {
+    return false;
+  }
+  return netUriWithinPrefix(requested, allowed) && netEntryAllowsMethod(entry, method);
+}
+
+function netMatchingEntries(policy, capability) {
+  const ceiling = policy.capability_ceiling ?? {};
+  if (!Array.isArray(ceiling.net)) return [];
+  const method = capability.method;
+  if (method !== undefined && !isValidHttpMethod(method)) return [];
+  let requested;
+  try {
+    requested = new URL(capability.value);
+  } catch {
+    return [];
+  }
+  return ceiling.net.filter((entry) => netEntryAllows(entry, requested, method));
+}
+
+// Each limit is the loosest across the entries allowing the request; a
+// missing limit is unlimited and wins.
+function netLimits(entries) {
+  const limits = {};
+  for (const key of NET_LIMIT_KEYS) {
+    const values = entries.map((entry) => (typeof entry === "string" ? undefined : entry[key]));
+    if (values.every((value) => value !== undefined)) {
+      limits[key] = Math.max(...values);
+    }
+  }
+  return limits;
+}
+
+function sameLimits(actual, expected) {
+  return NET_LIMIT_KEYS.every((key) => actual[key] === expected[key]);
+}
+
 function isValidEnvName(value) {
   return /^[A-Z_][A-Z0-9_]*$/.test(value);
 }
@@ -122,6 +245,10 @@ function evaluateCapability(policy, capability) {
   const kind = capability.kind;
   const value = capability.value;
 
+  if (capability.method !== undefined && kind !== "net.http") {
+    return false;
+  }
+
   if (kind === "exec") {
     return value === "true" && ceiling.exec === true;
   }
@@ -143,20 +270,7 @@ function evaluateCapability(policy, capability) {
   }
 
   if (kind === "net.http") {
-    if (!Array.isArray(ceiling.net)) return false;
-    let requested;
-    try {
-      requested = new URL(value);
-    } catch {
-      return false;
-    }
-    return ceiling.net.some((allowed) => {
-      try {
-        return netUriWithinPrefix(requested, new URL(allowed));
-      } catch {
-        return false;
-      }
-    });
+    return netMatchingEntries(policy, capability).length > 0;
   }
 
   if (kind === "fs.read" || kind === "fs.write") {
@@ -175,15 +289,13 @@ function evaluateCapability(policy, capability) {
   if (kind === "kv.read" || kind === "kv.write") {
     const kvCaps = ceiling.kv ?? {};
     const key = kind.split(".")[1];
-    if (!Array.isArray(kvCaps[key])) return false;
-    return kvCaps[key].includes("*") || kvCaps[key].includes(value);
+    return nameAllowed(policy, kvCaps[key], "/", value);
   }
 
   if (kind === "queue.publish" || kind === "queue.consume") {
     const queueCaps = ceiling.queue ?? {};
     const key = kind.split(".")[1];
-    if (!Array.isArray(queueCaps[key])) return false;
-    return queueCaps[key].includes("*") || queueCaps[key].includes(value);
+    return nameAllowed(policy, queueCaps[key], ".", value);
   }
 
   return false;
@@ -210,6 +322,15 @@ function runCapabilityVectors() {
           `capability mismatch in ${vectorFile} for ${testCase.capability.kind}:${testCase.capability.value}; expected=${testCase.expect} actual=${decision}`
         );
       }
+      if (testCase.expect_limits !== undefined) {
+        checks += 1;
+        const limits = netLimits(netMatchingEntries(vector.policy, testCase.capability));
+        if (decision !== "allow" || !sameLimits(limits, testCase.expect_limits)) {
+          throw new Error(
+            `net limits mismatch in ${vectorFile} for ${testCase.capability.value}; expected=${JSON.stringify(testCase.expect_limits)} actual=${JSON.stringify(limits)}`
+          );
+        }
+      }
     }
 
     console.log(`OK capability-eval ${vectorFile} cases=${vector.cases.length}`);
@@ -235,6 +356,11 @@ const schemaGroups = [
     goodDir: "test-vectors/policy/valid",
     badDir: "test-vectors/policy/invalid"
   },
+  {
+    schemaFile: "spec/policy/policy.v2.schema.json",
+    goodDir: "test-vectors/policy/v2/valid",
+    badDir: "test-vectors/policy/v2/invalid"
+  },
   {
     schemaFile: "spec/execution-receipt.schema.json",
     goodDir: "test-vectors/receipt/good",
@@ -252,6 +378,12 @@ const schemaGroups = [
     badDir: "test-vectors/registry/snapshot/bad",
     semanticBad: ["test-vectors/registry/snapshot/bad/hash-mismatch.json"]
   },
+  {
+    schemaFile: "spec/registry/snapshot.v1.schema.json",
+    goodDir: "test-vectors/registry/snapshot-v1/good",
+    badDir: "test-vectors/registry/snapshot-v1/bad",
+    semanticBad: ["test-vectors/registry/snapshot-v1/bad/hash-mismatch.json"]
+  },
   {
     schemaFile: "spec/skill-format/manifest.schema.json",
     goodDir: "test-vectors/skill-format/manifest/good",
@@ -271,6 +403,11 @@ const schemaGroups = [
     schemaFile: "spec/skill-format/manifest.v1.experimental.schema.json",
     goodDir: "test-vectors/skill-format/manifest-v1/good",
     badDir: "test-vectors/skill-format/manifest-v1/bad"
+  },
+  {
+    schemaFile: "spec/trust/trust-anchors.schema.json",
+    goodDir: "test-vectors/trust-anchors/good",
+    badDir: "test-vectors/trust-anchors/bad"
   }
 ];
 